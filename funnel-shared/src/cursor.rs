@@ -0,0 +1,13 @@
+use serde::{Deserialize, Serialize};
+
+/// Position in a list of records. Only the records after the cursor are sent.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Cursor {
+    MessageId(i64),
+    /// A timestamped record. Records that share the timestamp are ordered by their ID so none of
+    /// them are skipped between two batches
+    TimestampId {
+        timestamp: i64,
+        id: i64,
+    },
+}
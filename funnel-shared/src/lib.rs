@@ -1,3 +1,4 @@
+pub mod cursor;
 pub mod guild_channel;
 pub mod member_activity;
 pub mod member_count;
@@ -5,7 +6,9 @@ pub mod message_user;
 pub mod request;
 pub mod response;
 pub mod user_details;
+pub mod wire;
 
+pub use cursor::*;
 pub use guild_channel::*;
 pub use member_activity::*;
 pub use member_count::*;
@@ -13,3 +16,4 @@ pub use message_user::*;
 pub use request::*;
 pub use response::*;
 pub use user_details::*;
+pub use wire::*;
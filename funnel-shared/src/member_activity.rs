@@ -5,4 +5,13 @@ pub struct MemberActivity {
     pub activity_timestamp: i64,
     pub guild_id: i64,
     pub join_activity: bool,
+    /// The member that joined or left. Missing on activities that were saved before it was tracked
+    #[serde(default)]
+    pub user_id: Option<i64>,
+    /// The username of the member at the time of the activity
+    #[serde(default)]
+    pub username: Option<String>,
+    /// Row ID of the activity on the server
+    #[serde(default)]
+    pub id: i64,
 }
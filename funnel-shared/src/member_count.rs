@@ -5,4 +5,7 @@ pub struct MemberCount {
     pub count_timestamp: i64,
     pub guild_id: i64,
     pub total_members: i64,
+    /// Row ID of the count on the server. Missing on counts that were saved before it was tracked
+    #[serde(default)]
+    pub id: i64,
 }
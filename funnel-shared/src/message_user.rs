@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Message {
@@ -10,6 +11,23 @@ pub struct Message {
     pub message_content: Option<String>,
     pub stripped_content: Option<String>,
     pub delete_timestamp: Option<i64>,
+    /// The message that was replied to. Missing on messages that were saved before it was tracked
+    #[serde(default)]
+    pub reply_to_message: Option<i64>,
+    /// The author of the message that was replied to
+    #[serde(default)]
+    pub reply_to_user: Option<i64>,
+    /// Every user that was mentioned in the message
+    #[serde(default)]
+    pub mentions: Vec<i64>,
+    /// Content type of every attachment such as image/png
+    #[serde(default)]
+    pub attachment_types: Vec<String>,
+    #[serde(default)]
+    pub embed_count: u32,
+    /// Name of every custom emoji that was used in the message
+    #[serde(default)]
+    pub custom_emojis: Vec<String>,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -31,3 +49,36 @@ impl MessageWithUser {
         Self { message, sender }
     }
 }
+
+/// Split the messages into the messages and their unique senders so every user is sent only once
+#[must_use]
+pub fn compact_messages(messages: Vec<MessageWithUser>) -> (Vec<Message>, Vec<User>) {
+    let mut users = HashMap::new();
+    let messages = messages
+        .into_iter()
+        .map(|m| {
+            users.entry(m.sender.user_id).or_insert(m.sender);
+            m.message
+        })
+        .collect();
+
+    (messages, users.into_values().collect())
+}
+
+/// Attach the senders back to messages created with `compact_messages`
+#[must_use]
+pub fn expand_messages(messages: Vec<Message>, users: Vec<User>) -> Vec<MessageWithUser> {
+    let users: HashMap<i64, User> = users.into_iter().map(|u| (u.user_id, u)).collect();
+
+    messages
+        .into_iter()
+        .map(|message| {
+            let sender = users.get(&message.sender_id).cloned().unwrap_or(User {
+                user_id: message.sender_id,
+                global_name: None,
+                username: message.sender_id.to_string(),
+            });
+            MessageWithUser::new(message, sender)
+        })
+        .collect()
+}
@@ -1,16 +1,47 @@
 use serde::{Deserialize, Serialize};
 use serde_json::error::Error;
 
+use crate::{decode_binary, encode_binary, Cursor, WireError, WireFormat};
+
 #[derive(Serialize, Deserialize, Debug)]
 pub enum Request {
     StartConnection,
     StartConnectionNoLogin,
     GetGuildNoLogin,
-    Session { id: String },
+    Session {
+        id: String,
+    },
     LogOut,
-    GetMessages { guild_id: i64, page: u64 },
-    GetGuildMemberCount { guild_id: i64, page: u64 },
-    GetGuildMemberActivity { guild_id: i64, page: u64 },
+    GetMessages {
+        guild_id: i64,
+        page: u64,
+    },
+    GetGuildMemberCount {
+        guild_id: i64,
+        page: u64,
+    },
+    GetGuildMemberActivity {
+        guild_id: i64,
+        page: u64,
+    },
+    GetMessagesSince {
+        guild_id: i64,
+        since: Option<Cursor>,
+    },
+    GetGuildMemberCountSince {
+        guild_id: i64,
+        since: Option<Cursor>,
+    },
+    GetGuildMemberActivitySince {
+        guild_id: i64,
+        since: Option<Cursor>,
+    },
+    Subscribe {
+        guild_id: i64,
+    },
+    SetWireFormat {
+        formats: Vec<WireFormat>,
+    },
 }
 
 impl Request {
@@ -49,6 +80,34 @@ impl Request {
         Request::GetGuildMemberActivity { guild_id, page }
     }
 
+    /// Request the messages after the cursor or from the oldest one if no cursor is given
+    #[must_use]
+    pub fn get_messages_since(guild_id: i64, since: Option<Cursor>) -> Self {
+        Request::GetMessagesSince { guild_id, since }
+    }
+
+    #[must_use]
+    pub fn get_member_counts_since(guild_id: i64, since: Option<Cursor>) -> Self {
+        Request::GetGuildMemberCountSince { guild_id, since }
+    }
+
+    #[must_use]
+    pub fn get_member_activity_since(guild_id: i64, since: Option<Cursor>) -> Self {
+        Request::GetGuildMemberActivitySince { guild_id, since }
+    }
+
+    /// Receive new messages, member counts and member activities of the guild as they happen
+    #[must_use]
+    pub fn subscribe(guild_id: i64) -> Self {
+        Request::Subscribe { guild_id }
+    }
+
+    /// Ask the server to use the first format in the list that it supports
+    #[must_use]
+    pub fn set_wire_format(formats: Vec<WireFormat>) -> Self {
+        Request::SetWireFormat { formats }
+    }
+
     #[must_use]
     pub fn to_json(self) -> String {
         serde_json::to_string(&self).unwrap()
@@ -57,4 +116,13 @@ impl Request {
     pub fn from_json(json: &str) -> Result<Self, Error> {
         serde_json::from_str(json)
     }
+
+    #[must_use]
+    pub fn to_binary(self, format: WireFormat) -> Vec<u8> {
+        encode_binary(&self, format).unwrap()
+    }
+
+    pub fn from_binary(data: &[u8]) -> Result<Self, WireError> {
+        decode_binary(data)
+    }
 }
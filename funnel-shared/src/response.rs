@@ -3,7 +3,10 @@ use serde_json::Error;
 
 pub const PAGE_VALUE: u64 = 5000;
 
-use crate::{GuildWithChannels, MemberActivity, MemberCount, MessageWithUser, UserDetails};
+use crate::{
+    compact_messages, decode_binary, encode_binary, Cursor, GuildWithChannels, MemberActivity,
+    MemberCount, Message, MessageWithUser, User, UserDetails, WireError, WireFormat,
+};
 
 #[derive(Serialize, Deserialize)]
 pub enum Response {
@@ -23,13 +26,31 @@ pub enum Response {
         guild_id: i64,
         activities: Vec<MemberActivity>,
     },
+    /// Messages where every sender is only included once in `users`
+    CompactMessages {
+        guild_id: i64,
+        messages: Vec<Message>,
+        users: Vec<User>,
+    },
+    WireFormat(WireFormat),
     LoggedOut,
     Error(ErrorType),
 }
 
 #[derive(Serialize, Deserialize)]
 pub enum Status {
-    Success { current_page: u64 },
+    /// Success of a page based request. Servers without cursor support answer every request
+    /// with it
+    Success {
+        current_page: u64,
+    },
+    /// Success of a cursor based request. `next_cursor` points at the last record that was sent
+    SuccessCursor {
+        next_cursor: Option<Cursor>,
+        has_more: bool,
+    },
+    /// Records pushed by the server to a subscribed client
+    Live,
     Error,
 }
 
@@ -39,6 +60,19 @@ impl Status {
         Self::Success { current_page }
     }
 
+    #[must_use]
+    pub fn success_cursor(next_cursor: Option<Cursor>, has_more: bool) -> Self {
+        Self::SuccessCursor {
+            next_cursor,
+            has_more,
+        }
+    }
+
+    #[must_use]
+    pub fn live() -> Self {
+        Self::Live
+    }
+
     #[must_use]
     pub fn error() -> Self {
         Self::Error
@@ -49,6 +83,11 @@ impl Status {
         matches!(self, Status::Error)
     }
 
+    #[must_use]
+    pub fn is_live(&self) -> bool {
+        matches!(self, Status::Live)
+    }
+
     #[must_use]
     pub fn page(&self) -> u64 {
         if let Status::Success { current_page } = self {
@@ -56,6 +95,21 @@ impl Status {
         }
         panic!("Should not be here");
     }
+
+    /// The cursor to request the next batch of records with
+    #[must_use]
+    pub fn next_cursor(&self) -> Option<Cursor> {
+        if let Status::SuccessCursor { next_cursor, .. } = self {
+            return *next_cursor;
+        }
+        None
+    }
+
+    /// Whether there are more records after the next cursor
+    #[must_use]
+    pub fn has_more(&self) -> bool {
+        matches!(self, Status::SuccessCursor { has_more: true, .. })
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -160,6 +214,107 @@ impl WsResponse {
         }
     }
 
+    #[must_use]
+    pub fn messages_since(
+        guild_id: i64,
+        messages: Vec<MessageWithUser>,
+        next_cursor: Option<Cursor>,
+        has_more: bool,
+    ) -> Self {
+        let status = Status::success_cursor(next_cursor, has_more);
+        Self {
+            status,
+            response: Response::Messages { guild_id, messages },
+        }
+    }
+
+    #[must_use]
+    pub fn member_counts_since(
+        guild_id: i64,
+        counts: Vec<MemberCount>,
+        next_cursor: Option<Cursor>,
+        has_more: bool,
+    ) -> Self {
+        let status = Status::success_cursor(next_cursor, has_more);
+        Self {
+            status,
+            response: Response::MemberCounts { guild_id, counts },
+        }
+    }
+
+    #[must_use]
+    pub fn member_activities_since(
+        guild_id: i64,
+        activities: Vec<MemberActivity>,
+        next_cursor: Option<Cursor>,
+        has_more: bool,
+    ) -> Self {
+        let status = Status::success_cursor(next_cursor, has_more);
+        Self {
+            status,
+            response: Response::MemberActivities {
+                guild_id,
+                activities,
+            },
+        }
+    }
+
+    #[must_use]
+    pub fn live_messages(guild_id: i64, messages: Vec<MessageWithUser>) -> Self {
+        let status = Status::live();
+        Self {
+            status,
+            response: Response::Messages { guild_id, messages },
+        }
+    }
+
+    #[must_use]
+    pub fn live_member_counts(guild_id: i64, counts: Vec<MemberCount>) -> Self {
+        let status = Status::live();
+        Self {
+            status,
+            response: Response::MemberCounts { guild_id, counts },
+        }
+    }
+
+    #[must_use]
+    pub fn live_member_activities(guild_id: i64, activities: Vec<MemberActivity>) -> Self {
+        let status = Status::live();
+        Self {
+            status,
+            response: Response::MemberActivities {
+                guild_id,
+                activities,
+            },
+        }
+    }
+
+    #[must_use]
+    pub fn wire_format(format: WireFormat) -> Self {
+        let status = Status::success(0);
+        Self {
+            status,
+            response: Response::WireFormat(format),
+        }
+    }
+
+    /// Send the messages with every sender included once instead of once per message
+    #[must_use]
+    pub fn compacted(self) -> Self {
+        if let Response::Messages { guild_id, messages } = self.response {
+            let (messages, users) = compact_messages(messages);
+            return Self {
+                status: self.status,
+                response: Response::CompactMessages {
+                    guild_id,
+                    messages,
+                    users,
+                },
+            };
+        }
+        self
+    }
+
     #[must_use]
     pub fn user_details(user_details: UserDetails) -> Self {
         let status = Status::success(1);
@@ -223,6 +378,15 @@ impl WsResponse {
         serde_json::to_string(&self).unwrap()
     }
 
+    pub fn from_binary(data: &[u8]) -> Result<Self, WireError> {
+        decode_binary(data)
+    }
+
+    #[must_use]
+    pub fn binary(self, format: WireFormat) -> Vec<u8> {
+        encode_binary(&self, format).unwrap()
+    }
+
     #[must_use]
     pub fn get_error(&self) -> ErrorType {
         if let Response::Error(e_type) = &self.response {
@@ -0,0 +1,92 @@
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::io::{Read, Write};
+
+/// Set in the header byte of a binary frame when the payload is deflate compressed
+const FLAG_COMPRESSED: u8 = 0b1;
+
+/// Encoding used for the websocket frames. Text frames are always JSON while binary frames start
+/// with a header byte followed by the MessagePack payload.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum WireFormat {
+    #[default]
+    Json,
+    MessagePack,
+    CompressedMessagePack,
+}
+
+impl WireFormat {
+    /// Every supported format in the order of preference
+    #[must_use]
+    pub fn supported() -> Vec<Self> {
+        vec![
+            WireFormat::CompressedMessagePack,
+            WireFormat::MessagePack,
+            WireFormat::Json,
+        ]
+    }
+
+    #[must_use]
+    pub fn is_binary(&self) -> bool {
+        !matches!(self, WireFormat::Json)
+    }
+}
+
+#[derive(Debug)]
+pub enum WireError {
+    Empty,
+    Encode(String),
+    Decode(String),
+}
+
+impl Display for WireError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            WireError::Empty => write!(f, "Binary frame has no header"),
+            WireError::Encode(e) => write!(f, "Failed to encode frame: {e}"),
+            WireError::Decode(e) => write!(f, "Failed to decode frame: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for WireError {}
+
+/// Encode the value as a binary frame. JSON is not a binary format and is encoded as
+/// uncompressed MessagePack.
+pub fn encode_binary<T: Serialize>(value: &T, format: WireFormat) -> Result<Vec<u8>, WireError> {
+    let payload = rmp_serde::to_vec(value).map_err(|e| WireError::Encode(e.to_string()))?;
+
+    if format != WireFormat::CompressedMessagePack {
+        let mut frame = Vec::with_capacity(payload.len() + 1);
+        frame.push(0);
+        frame.extend(payload);
+        return Ok(frame);
+    }
+
+    let mut encoder = DeflateEncoder::new(vec![FLAG_COMPRESSED], Compression::fast());
+    encoder
+        .write_all(&payload)
+        .map_err(|e| WireError::Encode(e.to_string()))?;
+    encoder
+        .finish()
+        .map_err(|e| WireError::Encode(e.to_string()))
+}
+
+/// Decode a binary frame created with `encode_binary`
+pub fn decode_binary<T: DeserializeOwned>(frame: &[u8]) -> Result<T, WireError> {
+    let (&header, payload) = frame.split_first().ok_or(WireError::Empty)?;
+
+    if header & FLAG_COMPRESSED == 0 {
+        return rmp_serde::from_slice(payload).map_err(|e| WireError::Decode(e.to_string()));
+    }
+
+    let mut decompressed = Vec::new();
+    DeflateDecoder::new(payload)
+        .read_to_end(&mut decompressed)
+        .map_err(|e| WireError::Decode(e.to_string()))?;
+    rmp_serde::from_slice(&decompressed).map_err(|e| WireError::Decode(e.to_string()))
+}
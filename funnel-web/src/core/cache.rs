@@ -0,0 +1,504 @@
+use eframe::egui::ahash::{HashMap, HashSet};
+use funnel_shared::{Cursor, MemberActivity, MemberCount, MessageWithUser, PAGE_VALUE};
+use log::{error, info, warn};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize, Serializer};
+use std::collections::VecDeque;
+use std::ops::Range;
+
+#[cfg(target_arch = "wasm32")]
+use web_sys::window;
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::fs;
+#[cfg(not(target_arch = "wasm32"))]
+use std::io::Write;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::PathBuf;
+
+use crate::MessageStore;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::core::get_target_path;
+
+/// Prefix of the localStorage keys that hold the guild caches
+#[cfg(target_arch = "wasm32")]
+const STORAGE_PREFIX: &str = "guild_cache_";
+/// Name of the entry that holds the member records and the number of message chunks of a guild
+const HEAD_ENTRY: &str = "head";
+/// Number of messages in each saved chunk. Only the chunks with a changed message are written
+/// again so a save does not serialize the whole guild
+const SAVE_CHUNK: usize = 5_000;
+
+/// The member counts and activities of a guild that have been received from the server. The
+/// messages are only kept in the message store of the guild
+#[derive(Default)]
+pub struct GuildCache {
+    counts: Vec<MemberCount>,
+    activities: Vec<MemberActivity>,
+    /// Whether member records were added after the cache was last saved
+    unsaved: bool,
+}
+
+/// The head of a guild cache as it is read from the storage
+#[derive(Deserialize, Default)]
+struct SavedGuild {
+    counts: Vec<MemberCount>,
+    activities: Vec<MemberActivity>,
+    chunks: usize,
+}
+
+/// The head of a guild cache as it is written to the storage
+#[derive(Serialize)]
+struct SavedGuildRef<'a> {
+    counts: &'a [MemberCount],
+    activities: &'a [MemberActivity],
+    chunks: usize,
+}
+
+/// A chunk of the messages of a store. The messages are rebuilt one at a time while serializing
+/// so they are never copied all at once
+struct StoredMessages<'a> {
+    store: &'a MessageStore,
+    rows: Range<usize>,
+}
+
+impl Serialize for StoredMessages<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.rows.clone().map(|index| self.store.message(index)))
+    }
+}
+
+/// A part of a guild cache that is waiting to be written
+#[derive(PartialEq)]
+enum PendingWrite {
+    Messages { guild_id: i64, chunk: usize },
+    Head { guild_id: i64, chunks: usize },
+}
+
+impl PendingWrite {
+    fn guild_id(&self) -> i64 {
+        match self {
+            Self::Messages { guild_id, .. } | Self::Head { guild_id, .. } => *guild_id,
+        }
+    }
+}
+
+impl GuildCache {
+    #[must_use]
+    pub fn counts(&self) -> &[MemberCount] {
+        &self.counts
+    }
+
+    #[must_use]
+    pub fn activities(&self) -> &[MemberActivity] {
+        &self.activities
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty() && self.activities.is_empty()
+    }
+
+    fn last_count_position(&self) -> Option<(i64, i64)> {
+        self.counts.iter().map(|c| (c.count_timestamp, c.id)).max()
+    }
+
+    fn last_activity_position(&self) -> Option<(i64, i64)> {
+        self.activities
+            .iter()
+            .map(|a| (a.activity_timestamp, a.id))
+            .max()
+    }
+}
+
+/// The page that holds the first record after the cached ones. Pages are filled in the order the
+/// records were saved on the server
+#[must_use]
+pub fn first_uncached_page(cached: usize) -> u64 {
+    cached as u64 / PAGE_VALUE + 1
+}
+
+/// Number of records of the page that are already cached
+fn cached_on_page(cached: usize, page: u64) -> usize {
+    let before_page = page.saturating_sub(1) * PAGE_VALUE;
+    (cached as u64).saturating_sub(before_page) as usize
+}
+
+fn timestamp_cursor((timestamp, id): (i64, i64)) -> Cursor {
+    Cursor::TimestampId { timestamp, id }
+}
+
+/// Local copy of the fetched guild data so only the newer records need to be requested
+#[derive(Default)]
+pub struct DataCache {
+    guilds: HashMap<i64, GuildCache>,
+    /// The parts of the guild caches that still have to be written, one per frame
+    pending: VecDeque<PendingWrite>,
+    /// Guilds whose cache did not fit in the storage
+    failed: HashSet<i64>,
+}
+
+impl DataCache {
+    #[must_use]
+    pub fn is_loaded(&self, guild_id: i64) -> bool {
+        self.guilds.contains_key(&guild_id)
+    }
+
+    #[must_use]
+    pub fn guild(&self, guild_id: i64) -> Option<&GuildCache> {
+        self.guilds.get(&guild_id)
+    }
+
+    /// Read the saved cache of the guild from the storage. An empty cache is used if nothing
+    /// was saved before. The saved messages are returned so they can be added to the message store
+    pub fn load_guild(&mut self, guild_id: i64) -> Vec<MessageWithUser> {
+        let saved: SavedGuild = read_entry(guild_id, HEAD_ENTRY).unwrap_or_default();
+        let messages = read_messages(guild_id, saved.chunks);
+        if !messages.is_empty() || !saved.counts.is_empty() || !saved.activities.is_empty() {
+            info!(
+                "Loaded {} messages, {} member counts and {} member activities from the cache of {guild_id}",
+                messages.len(),
+                saved.counts.len(),
+                saved.activities.len()
+            );
+        }
+        let cache = GuildCache {
+            counts: saved.counts,
+            activities: saved.activities,
+            unsaved: false,
+        };
+        self.guilds.insert(guild_id, cache);
+        messages
+    }
+
+    /// Drop the in-memory cache of the guild so it gets loaded again from the storage
+    pub fn unload_guild(&mut self, guild_id: i64) {
+        self.guilds.remove(&guild_id);
+        self.pending.retain(|write| write.guild_id() != guild_id);
+    }
+
+    /// The cursor after the newest cached member count. None if nothing is cached
+    #[must_use]
+    pub fn counts_cursor(&self, guild_id: i64) -> Option<Cursor> {
+        self.guilds
+            .get(&guild_id)?
+            .last_count_position()
+            .map(timestamp_cursor)
+    }
+
+    #[must_use]
+    pub fn activities_cursor(&self, guild_id: i64) -> Option<Cursor> {
+        self.guilds
+            .get(&guild_id)?
+            .last_activity_position()
+            .map(timestamp_cursor)
+    }
+
+    /// The page to start the member counts from when the server has no cursor support
+    #[must_use]
+    pub fn counts_page(&self, guild_id: i64) -> u64 {
+        first_uncached_page(self.guilds.get(&guild_id).map_or(0, |c| c.counts.len()))
+    }
+
+    #[must_use]
+    pub fn activities_page(&self, guild_id: i64) -> u64 {
+        first_uncached_page(self.guilds.get(&guild_id).map_or(0, |c| c.activities.len()))
+    }
+
+    /// Keep only the member counts after the last cached one and add them to the cache. Counts
+    /// that share a timestamp are ordered by their ID
+    pub fn new_counts(&mut self, guild_id: i64, counts: Vec<MemberCount>) -> Vec<MemberCount> {
+        let cache = self.guilds.entry(guild_id).or_default();
+        let last_position = cache.last_count_position();
+
+        let counts: Vec<MemberCount> = counts
+            .into_iter()
+            .filter(|c| last_position.is_none_or(|last| (c.count_timestamp, c.id) > last))
+            .collect();
+
+        cache.unsaved |= !counts.is_empty();
+        cache.counts.extend(counts.iter().cloned());
+        counts
+    }
+
+    /// Keep only the member counts of the page that are not cached yet and add them to the cache
+    pub fn new_counts_page(
+        &mut self,
+        guild_id: i64,
+        page: u64,
+        counts: Vec<MemberCount>,
+    ) -> Vec<MemberCount> {
+        let cache = self.guilds.entry(guild_id).or_default();
+        let cached = cached_on_page(cache.counts.len(), page);
+
+        let counts: Vec<MemberCount> = counts.into_iter().skip(cached).collect();
+        cache.unsaved |= !counts.is_empty();
+        cache.counts.extend(counts.iter().cloned());
+        counts
+    }
+
+    /// Keep only the member activities after the last cached one and add them to the cache.
+    /// Activities that share a timestamp are ordered by their ID
+    pub fn new_activities(
+        &mut self,
+        guild_id: i64,
+        activities: Vec<MemberActivity>,
+    ) -> Vec<MemberActivity> {
+        let cache = self.guilds.entry(guild_id).or_default();
+        let last_position = cache.last_activity_position();
+
+        let activities: Vec<MemberActivity> = activities
+            .into_iter()
+            .filter(|a| last_position.is_none_or(|last| (a.activity_timestamp, a.id) > last))
+            .collect();
+
+        cache.unsaved |= !activities.is_empty();
+        cache.activities.extend(activities.iter().cloned());
+        activities
+    }
+
+    /// Keep only the member activities of the page that are not cached yet and add them to the
+    /// cache
+    pub fn new_activities_page(
+        &mut self,
+        guild_id: i64,
+        page: u64,
+        activities: Vec<MemberActivity>,
+    ) -> Vec<MemberActivity> {
+        let cache = self.guilds.entry(guild_id).or_default();
+        let cached = cached_on_page(cache.activities.len(), page);
+
+        let activities: Vec<MemberActivity> = activities.into_iter().skip(cached).collect();
+        cache.unsaved |= !activities.is_empty();
+        cache.activities.extend(activities.iter().cloned());
+        activities
+    }
+
+    /// Queue the parts of the guild cache that changed since the last save. The message chunks
+    /// from the first changed row are written before the head that counts them
+    pub fn save_guild(&mut self, guild_id: i64, store: &mut MessageStore) {
+        if self.failed.contains(&guild_id) {
+            return;
+        }
+        let Some(cache) = self.guilds.get_mut(&guild_id) else {
+            return;
+        };
+        let unsaved_from = store.unsaved_from();
+        if unsaved_from.is_none() && !cache.unsaved {
+            return;
+        }
+        cache.unsaved = false;
+        store.mark_saved();
+
+        let chunks = store.len().div_ceil(SAVE_CHUNK);
+        if let Some(from) = unsaved_from {
+            for chunk in from / SAVE_CHUNK..chunks {
+                self.queue(PendingWrite::Messages { guild_id, chunk });
+            }
+        }
+        self.queue(PendingWrite::Head { guild_id, chunks });
+    }
+
+    fn queue(&mut self, write: PendingWrite) {
+        if !self.pending.contains(&write) {
+            self.pending.push_back(write);
+        }
+    }
+
+    #[must_use]
+    pub fn has_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// Write the oldest queued part of the guild caches. Returns the guild if its cache did not
+    /// fit in the storage. Its saved cache is removed so a partial cache is never loaded and it
+    /// is not saved again
+    pub fn write_next(&mut self, stores: &HashMap<i64, MessageStore>) -> Option<i64> {
+        let write = self.pending.pop_front()?;
+        let guild_id = write.guild_id();
+
+        let result = match write {
+            PendingWrite::Messages { chunk, .. } => {
+                let store = stores.get(&guild_id)?;
+                let start = chunk * SAVE_CHUNK;
+                let end = store.len().min(start + SAVE_CHUNK);
+                // The store was cleared after the chunk was queued
+                if start >= end {
+                    return None;
+                }
+                let messages = StoredMessages {
+                    store,
+                    rows: start..end,
+                };
+                write_entry(guild_id, &chunk.to_string(), &messages)
+            }
+            PendingWrite::Head { chunks, .. } => {
+                let cache = self.guilds.get(&guild_id)?;
+                let saved = SavedGuildRef {
+                    counts: &cache.counts,
+                    activities: &cache.activities,
+                    chunks,
+                };
+                write_entry(guild_id, HEAD_ENTRY, &saved)
+            }
+        };
+
+        if let Err(e) = result {
+            warn!("The cache of {guild_id} could not be saved and is removed. Reason: {e}");
+            self.pending.retain(|write| write.guild_id() != guild_id);
+            self.failed.insert(guild_id);
+            remove_guild_cache(guild_id);
+            return Some(guild_id);
+        }
+        None
+    }
+
+    /// Remove every saved guild cache from the storage
+    pub fn clear_storage() {
+        #[cfg(target_arch = "wasm32")]
+        {
+            if let Some(storage) = window().and_then(|w| w.local_storage().ok().flatten()) {
+                let length = storage.length().unwrap_or_default();
+                let keys: Vec<String> = (0..length)
+                    .filter_map(|index| storage.key(index).ok().flatten())
+                    .filter(|key| key.starts_with(STORAGE_PREFIX))
+                    .collect();
+
+                for key in keys {
+                    let _ = storage.remove_item(&key);
+                }
+            }
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            if let Some(path) = get_cache_dir() {
+                if let Err(e) = fs::remove_dir_all(&path) {
+                    error!("Failed to delete cache directory {path:?}: {e}");
+                } else {
+                    info!("Cache directory {path:?} deleted successfully");
+                }
+            }
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn get_cache_dir() -> Option<PathBuf> {
+    let mut path = get_target_path()?;
+    path.push("cache");
+    if let Err(e) = fs::create_dir_all(&path) {
+        error!("Failed to create cache directory {path:?}: {e}");
+        return None;
+    }
+    Some(path)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn get_guild_dir(guild_id: i64) -> Option<PathBuf> {
+    let mut path = get_cache_dir()?;
+    path.push(guild_id.to_string());
+    if let Err(e) = fs::create_dir_all(&path) {
+        error!("Failed to create cache directory {path:?}: {e}");
+        return None;
+    }
+    Some(path)
+}
+
+/// Write a single entry of the guild cache. Fails if the storage is full or cannot be written
+fn write_entry<T: Serialize>(guild_id: i64, name: &str, value: &T) -> Result<(), String> {
+    let json = serde_json::to_string(value).map_err(|e| e.to_string())?;
+
+    #[cfg(target_arch = "wasm32")]
+    {
+        let storage = window()
+            .and_then(|w| w.local_storage().ok().flatten())
+            .ok_or_else(|| String::from("The local storage is not available"))?;
+        // localStorage has a small quota so large guilds may not fit
+        storage
+            .set_item(&format!("{STORAGE_PREFIX}{guild_id}_{name}"), &json)
+            .map_err(|_| String::from("The local storage quota was exceeded"))
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let mut path =
+            get_guild_dir(guild_id).ok_or_else(|| String::from("No cache directory found"))?;
+        path.push(format!("{name}.json"));
+
+        let mut file = fs::File::create(&path)
+            .map_err(|e| format!("Failed to create cache file {path:?}: {e}"))?;
+        file.write_all(json.as_bytes())
+            .map_err(|e| format!("Failed to write to cache file {path:?}: {e}"))
+    }
+}
+
+fn read_entry<T: DeserializeOwned>(guild_id: i64, name: &str) -> Option<T> {
+    #[cfg(target_arch = "wasm32")]
+    let content = window()
+        .and_then(|w| w.local_storage().ok().flatten())
+        .and_then(|s| {
+            s.get_item(&format!("{STORAGE_PREFIX}{guild_id}_{name}"))
+                .ok()
+                .flatten()
+        })?;
+
+    #[cfg(not(target_arch = "wasm32"))]
+    let content = {
+        let mut path = get_cache_dir()?;
+        path.push(guild_id.to_string());
+        path.push(format!("{name}.json"));
+        fs::read_to_string(&path).ok()?
+    };
+
+    match serde_json::from_str::<T>(&content) {
+        Ok(saved) => Some(saved),
+        Err(e) => {
+            error!("Failed to deserialize the cache entry {name} of {guild_id}: {e}");
+            None
+        }
+    }
+}
+
+/// Every saved message chunk of the guild in order. Nothing is returned if a chunk is missing
+/// because the messages after the gap would never be fetched again
+fn read_messages(guild_id: i64, chunks: usize) -> Vec<MessageWithUser> {
+    let mut messages = Vec::new();
+    for chunk in 0..chunks {
+        let Some(saved) = read_entry::<Vec<MessageWithUser>>(guild_id, &chunk.to_string()) else {
+            error!("Message chunk {chunk} of the cache of {guild_id} is missing");
+            return Vec::new();
+        };
+        messages.extend(saved);
+    }
+    messages
+}
+
+/// Remove every saved entry of the guild cache
+fn remove_guild_cache(guild_id: i64) {
+    #[cfg(target_arch = "wasm32")]
+    {
+        if let Some(storage) = window().and_then(|w| w.local_storage().ok().flatten()) {
+            let prefix = format!("{STORAGE_PREFIX}{guild_id}_");
+            let length = storage.length().unwrap_or_default();
+            let keys: Vec<String> = (0..length)
+                .filter_map(|index| storage.key(index).ok().flatten())
+                .filter(|key| key.starts_with(&prefix))
+                .collect();
+
+            for key in keys {
+                let _ = storage.remove_item(&key);
+            }
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let Some(mut path) = get_cache_dir() else {
+            return;
+        };
+        path.push(guild_id.to_string());
+        if let Err(e) = fs::remove_dir_all(&path) {
+            error!("Failed to delete cache directory {path:?}: {e}");
+        }
+    }
+}
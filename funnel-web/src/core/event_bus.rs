@@ -1,4 +1,4 @@
-use ewebsock::Options;
+use eframe::egui::Context;
 use funnel_shared::Request;
 use log::{error, info};
 use std::collections::VecDeque;
@@ -7,11 +7,8 @@ use crate::core::MainWindow;
 use crate::ui::ReloadTab;
 use crate::{AppEvent, AppStatus};
 
-const WS_URL: &str = "wss://funnel-jyz9.shuttle.app/ws";
-// const WS_URL: &str = "ws://localhost:8000/ws";
-
 impl MainWindow {
-    pub fn check_event(&mut self) {
+    pub fn check_event(&mut self, ctx: &Context) {
         loop {
             let Some(event) = self.event_bus.get() else {
                 break;
@@ -22,18 +19,9 @@ impl MainWindow {
                     let guild_id = self.panels.selected_guild();
                     let date_handler = self.panels.current_date_handler();
                     self.tabs.set_date_handler(guild_id, date_handler);
-                    self.event_bus
-                        .publish_if_needed(AppEvent::OverviewNeedsReload(guild_id));
-                    self.event_bus
-                        .publish_if_needed(AppEvent::UserTableNeedsReload(guild_id));
-                    self.event_bus
-                        .publish_if_needed(AppEvent::ChannelTableNeedsReload(guild_id));
-                    self.event_bus
-                        .publish_if_needed(AppEvent::WordTableNeedsReload(guild_id));
-                    self.event_bus
-                        .publish_if_needed(AppEvent::MessageChartNeedsReload(guild_id));
-                    self.event_bus
-                        .publish_if_needed(AppEvent::UserChartNeedsReload(guild_id));
+                    self.event_bus.publish_reloads(guild_id);
+                    self.tabs.reload_user_profile();
+                    self.tabs.reload_channel_profile();
                 }
                 AppEvent::CompareDate => {
                     let guild_id = self.panels.selected_guild();
@@ -50,19 +38,7 @@ impl MainWindow {
                         }
                     } else {
                         info!("Starting connection to the websocket");
-                        self.panels.set_app_status(AppStatus::ConnectingToWs);
-                        let options = Options::default();
-                        let result = ewebsock::connect(WS_URL, options);
-                        match result {
-                            Ok((sender, receiver)) => {
-                                self.set_channels(sender, receiver);
-                            }
-                            Err(e) => {
-                                error!("Failed to connect to WS. Reason: {e}");
-                                self.connection.failed_connection();
-                                self.panels.set_app_status(AppStatus::FailedWs(e));
-                            }
-                        }
+                        self.connect_ws(ctx);
                     }
                 }
                 AppEvent::UpdateDate(date, guild_id) => {
@@ -110,27 +86,79 @@ impl MainWindow {
                     self.tabs
                         .add_reload(guild_id, ReloadTab::UserChart(guild_id));
                 }
+                AppEvent::HeatmapNeedsReload(guild_id) => {
+                    self.tabs.add_reload(guild_id, ReloadTab::Heatmap(guild_id));
+                }
+                AppEvent::MessageSearchNeedsReload(guild_id) => {
+                    self.tabs
+                        .add_reload(guild_id, ReloadTab::MessageSearch(guild_id));
+                }
+                AppEvent::DeletedMessagesNeedsReload(guild_id) => {
+                    self.tabs
+                        .add_reload(guild_id, ReloadTab::DeletedMessages(guild_id));
+                }
+                AppEvent::DashboardNeedsReload(guild_id) => {
+                    self.tabs
+                        .add_reload(guild_id, ReloadTab::Dashboard(guild_id));
+                }
+                AppEvent::CohortsNeedsReload(guild_id) => {
+                    self.tabs.add_reload(guild_id, ReloadTab::Cohorts(guild_id));
+                }
+                AppEvent::InteractionsNeedsReload(guild_id) => {
+                    self.tabs
+                        .add_reload(guild_id, ReloadTab::Interactions(guild_id));
+                }
+                AppEvent::MediaNeedsReload(guild_id) => {
+                    self.tabs.add_reload(guild_id, ReloadTab::Media(guild_id));
+                }
                 AppEvent::MessageChartTypeChanged(guild_id) => {
-                    self.tabs.reload_message_chart(guild_id);
+                    self.tabs
+                        .add_reload(guild_id, ReloadTab::MessageChart(guild_id));
                 }
                 AppEvent::UserChartTypeChanged(guild_id) => {
-                    self.tabs.reload_user_chart(guild_id);
+                    self.tabs
+                        .add_reload(guild_id, ReloadTab::UserChart(guild_id));
                 }
                 AppEvent::SelectedChannelsChanged => {
                     let current_guild = self.panels.selected_guild();
                     let selected_channels = self.panels.current_selected_channels();
                     self.tabs.set_selected_channels(&selected_channels);
+                    self.event_bus.publish_reloads(current_guild);
+                    self.tabs.reload_user_profile();
+                }
+                AppEvent::TimezoneChanged => self.change_timezone(),
+                AppEvent::ServerChanged => {
+                    info!("Server changed. Dropping the existing websocket connection");
+                    self.remove_channels();
+                }
+                AppEvent::ExportTab(format) => {
+                    let guild_id = self.panels.selected_guild();
+                    let tab_state = self.panels.tab_state();
+                    let data = self.tabs.export_data(guild_id, tab_state);
+
+                    if data.is_empty() {
+                        self.panels.set_app_status(AppStatus::FailedExport(
+                            "There is no data to export in this tab".to_string(),
+                        ));
+                        continue;
+                    }
 
-                    self.event_bus
-                        .publish_if_needed(AppEvent::OverviewNeedsReload(current_guild));
-                    self.event_bus
-                        .publish_if_needed(AppEvent::UserTableNeedsReload(current_guild));
-                    self.event_bus
-                        .publish_if_needed(AppEvent::MessageChartNeedsReload(current_guild));
-                    self.event_bus
-                        .publish_if_needed(AppEvent::UserChartNeedsReload(current_guild));
-                    self.event_bus
-                        .publish_if_needed(AppEvent::WordTableNeedsReload(current_guild));
+                    match data.save(format) {
+                        Ok(location) => {
+                            info!("Exported {tab_state} to {location}");
+                            self.panels.set_app_status(AppStatus::Exported(location));
+                        }
+                        Err(e) => {
+                            error!("Failed to export {tab_state}. Reason: {e}");
+                            self.panels.set_app_status(AppStatus::FailedExport(e));
+                        }
+                    }
+                }
+                AppEvent::OpenUserProfile(guild_id, user_id) => {
+                    self.tabs.open_user_profile(guild_id, user_id);
+                }
+                AppEvent::OpenChannelProfile(guild_id, channel_id) => {
+                    self.tabs.open_channel_profile(guild_id, channel_id);
                 }
                 AppEvent::LogOut => {
                     self.panels.set_app_status(AppStatus::AttemptLogOut);
@@ -160,4 +188,21 @@ impl EventBus {
             self.events.push_back(event);
         }
     }
+
+    /// Queue a reload of every tab of the guild
+    pub fn publish_reloads(&mut self, guild_id: i64) {
+        self.publish_if_needed(AppEvent::OverviewNeedsReload(guild_id));
+        self.publish_if_needed(AppEvent::UserTableNeedsReload(guild_id));
+        self.publish_if_needed(AppEvent::ChannelTableNeedsReload(guild_id));
+        self.publish_if_needed(AppEvent::MessageChartNeedsReload(guild_id));
+        self.publish_if_needed(AppEvent::UserChartNeedsReload(guild_id));
+        self.publish_if_needed(AppEvent::WordTableNeedsReload(guild_id));
+        self.publish_if_needed(AppEvent::HeatmapNeedsReload(guild_id));
+        self.publish_if_needed(AppEvent::MessageSearchNeedsReload(guild_id));
+        self.publish_if_needed(AppEvent::DeletedMessagesNeedsReload(guild_id));
+        self.publish_if_needed(AppEvent::DashboardNeedsReload(guild_id));
+        self.publish_if_needed(AppEvent::CohortsNeedsReload(guild_id));
+        self.publish_if_needed(AppEvent::InteractionsNeedsReload(guild_id));
+        self.publish_if_needed(AppEvent::MediaNeedsReload(guild_id));
+    }
 }
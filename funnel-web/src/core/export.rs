@@ -0,0 +1,194 @@
+use chrono::Local;
+use serde::ser::{Serialize, SerializeMap, Serializer};
+use std::fmt;
+use strum::IntoEnumIterator;
+use strum_macros::{Display, EnumIter};
+
+#[cfg(target_arch = "wasm32")]
+use web_sys::js_sys::Array;
+#[cfg(target_arch = "wasm32")]
+use web_sys::wasm_bindgen::{JsCast, JsValue};
+#[cfg(target_arch = "wasm32")]
+use web_sys::{Blob, BlobPropertyBag, HtmlAnchorElement, Url, window};
+
+#[cfg(not(target_arch = "wasm32"))]
+use dirs::download_dir;
+#[cfg(not(target_arch = "wasm32"))]
+use std::fs;
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::core::get_target_path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, EnumIter)]
+pub enum ExportFormat {
+    #[strum(to_string = "CSV")]
+    Csv,
+    #[strum(to_string = "JSON")]
+    Json,
+}
+
+impl ExportFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn mime_type(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv",
+            ExportFormat::Json => "application/json",
+        }
+    }
+}
+
+/// Rows of a tab as they are currently shown in the UI
+pub struct ExportData {
+    name: String,
+    headers: Vec<String>,
+    rows: Vec<Vec<String>>,
+}
+
+/// A single row serialized as a JSON object with the headers as the keys
+struct JsonRow<'a> {
+    headers: &'a [String],
+    row: &'a [String],
+}
+
+impl Serialize for JsonRow<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.headers.len()))?;
+        for (header, value) in self.headers.iter().zip(self.row) {
+            map.serialize_entry(header, value)?;
+        }
+        map.end()
+    }
+}
+
+impl ExportData {
+    #[must_use]
+    pub fn new(name: &str, headers: Vec<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            headers,
+            rows: Vec::new(),
+        }
+    }
+
+    pub fn add_row(&mut self, row: Vec<String>) {
+        self.rows.push(row);
+    }
+
+    /// Every row in the order it was added to the table. One column per variant of the column
+    /// enum
+    #[must_use]
+    pub fn from_rows<Row, F>(
+        name: &str,
+        rows: &[Row],
+        column_text: impl Fn(&F, &Row) -> String,
+    ) -> Self
+    where
+        F: IntoEnumIterator + fmt::Display,
+    {
+        let headers = F::iter().map(|column| column.to_string()).collect();
+        let mut data = Self::new(name, headers);
+
+        for row in rows {
+            let values = F::iter().map(|column| column_text(&column, row)).collect();
+            data.add_row(values);
+        }
+        data
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    fn to_csv(&self) -> String {
+        let mut content = String::new();
+        for line in std::iter::once(&self.headers).chain(&self.rows) {
+            let line: Vec<String> = line.iter().map(|value| escape_csv(value)).collect();
+            content.push_str(&line.join(","));
+            content.push('\n');
+        }
+        content
+    }
+
+    fn to_json(&self) -> Result<String, String> {
+        let rows: Vec<JsonRow> = self
+            .rows
+            .iter()
+            .map(|row| JsonRow {
+                headers: &self.headers,
+                row,
+            })
+            .collect();
+        serde_json::to_string_pretty(&rows).map_err(|e| e.to_string())
+    }
+
+    fn file_name(&self, format: ExportFormat) -> String {
+        let name = self.name.to_lowercase().replace(' ', "_");
+        let time = Local::now().format("%Y%m%d_%H%M%S");
+        format!("funnel_{name}_{time}.{}", format.extension())
+    }
+
+    /// Write the data to a file in the download directory or start a browser download on wasm.
+    /// Returns the location the data was saved to.
+    pub fn save(&self, format: ExportFormat) -> Result<String, String> {
+        let content = match format {
+            ExportFormat::Csv => self.to_csv(),
+            ExportFormat::Json => self.to_json()?,
+        };
+        let file_name = self.file_name(format);
+
+        #[cfg(target_arch = "wasm32")]
+        {
+            download_file(&file_name, &content, format.mime_type())
+                .map_err(|e| format!("{e:?}"))?;
+            Ok(file_name)
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let mut path = download_dir()
+                .or_else(get_target_path)
+                .ok_or("Failed to determine the download directory")?;
+            path.push(file_name);
+
+            fs::write(&path, content).map_err(|e| e.to_string())?;
+            Ok(path.display().to_string())
+        }
+    }
+}
+
+/// Quote the value if it contains a character that has a meaning in CSV
+fn escape_csv(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn download_file(file_name: &str, content: &str, mime_type: &str) -> Result<(), JsValue> {
+    let document = window()
+        .and_then(|w| w.document())
+        .ok_or("Failed to get the document")?;
+
+    let parts = Array::of1(&JsValue::from_str(content));
+    let options = BlobPropertyBag::new();
+    options.set_type(mime_type);
+    let blob = Blob::new_with_str_sequence_and_options(&parts, &options)?;
+    let url = Url::create_object_url_with_blob(&blob)?;
+
+    let anchor: HtmlAnchorElement = document.create_element("a")?.dyn_into()?;
+    anchor.set_href(&url);
+    anchor.set_download(file_name);
+    anchor.click();
+
+    Url::revoke_object_url(&url)
+}
@@ -1,8 +1,15 @@
+use funnel_shared::Cursor;
+
 #[derive(Clone, Default)]
 pub struct FetchStatus {
     messages: bool,
     counts: bool,
     activities: bool,
+    live: bool,
+    messages_cursor: Option<Cursor>,
+    counts_cursor: Option<Cursor>,
+    activities_cursor: Option<Cursor>,
+    /// The last received page when the server has no cursor support
     messages_page: u64,
     counts_page: u64,
     activities_page: u64,
@@ -21,6 +28,16 @@ impl FetchStatus {
         self.activities = true;
     }
 
+    /// The guild is subscribed to the live updates after the fetch has finished
+    pub fn set_live(&mut self) {
+        self.live = true;
+    }
+
+    #[must_use]
+    pub fn live(&self) -> bool {
+        self.live
+    }
+
     #[must_use]
     pub fn all_done(&self) -> bool {
         self.messages && self.counts && self.activities
@@ -41,6 +58,48 @@ impl FetchStatus {
         self.activities
     }
 
+    #[must_use]
+    pub fn messages_cursor(&self) -> Option<Cursor> {
+        self.messages_cursor
+    }
+
+    #[must_use]
+    pub fn counts_cursor(&self) -> Option<Cursor> {
+        self.counts_cursor
+    }
+
+    #[must_use]
+    pub fn activities_cursor(&self) -> Option<Cursor> {
+        self.activities_cursor
+    }
+
+    pub fn set_messages_cursor(&mut self, cursor: Option<Cursor>) {
+        self.messages_cursor = cursor;
+    }
+
+    pub fn set_counts_cursor(&mut self, cursor: Option<Cursor>) {
+        self.counts_cursor = cursor;
+    }
+
+    pub fn set_activities_cursor(&mut self, cursor: Option<Cursor>) {
+        self.activities_cursor = cursor;
+    }
+
+    #[must_use]
+    pub fn messages_page(&self) -> u64 {
+        self.messages_page
+    }
+
+    #[must_use]
+    pub fn counts_page(&self) -> u64 {
+        self.counts_page
+    }
+
+    #[must_use]
+    pub fn activities_page(&self) -> u64 {
+        self.activities_page
+    }
+
     pub fn set_messages_page(&mut self, page: u64) {
         self.messages_page = page;
     }
@@ -53,16 +112,26 @@ impl FetchStatus {
         self.activities_page = page;
     }
 
-    fn partial_messages(&self) -> bool {
-        !self.messages() && self.messages_page != 0
+    /// Whether any data was requested for this guild
+    #[must_use]
+    pub fn started(&self) -> bool {
+        self.messages || self.counts || self.partial_messages() || self.partial_counts()
     }
 
-    fn partial_counts(&self) -> bool {
-        !self.counts() && self.counts_page != 0
+    /// Whether some messages were received but not all of them
+    #[must_use]
+    pub fn partial_messages(&self) -> bool {
+        !self.messages() && (self.messages_cursor.is_some() || self.messages_page > 0)
     }
 
-    fn partial_activities(&self) -> bool {
-        !self.activities() && self.activities_page != 0
+    #[must_use]
+    pub fn partial_counts(&self) -> bool {
+        !self.counts() && (self.counts_cursor.is_some() || self.counts_page > 0)
+    }
+
+    #[must_use]
+    pub fn partial_activities(&self) -> bool {
+        !self.activities() && (self.activities_cursor.is_some() || self.activities_page > 0)
     }
 
     #[must_use]
@@ -1,18 +1,24 @@
-use eframe::{App, Frame, egui};
+use eframe::{App, Frame, Storage, egui};
 use egui::{Context, ThemePreference};
 use egui_extras::install_image_loaders;
 use ewebsock::WsMessage;
 use ewebsock::{WsReceiver, WsSender};
-use funnel_shared::Request;
+use funnel_shared::{Cursor, Request, WireFormat};
 use log::{error, info};
 
-use crate::core::add_font;
+use crate::core::{add_font, first_uncached_page};
 use crate::ui::{Connection, PanelStatus, TabHandler};
-use crate::{AppStatus, EventBus};
+use crate::{
+    AppEvent, AppStatus, DataCache, EventBus, FetchStatus, LiveUpdates, MessageStore, Reconnect,
+    UI_STATE_KEY, UiState,
+};
 
 pub const JET: &[u8] = include_bytes!("../../../fonts/jetbrains_nerd_propo_regular.ttf");
 pub const CHANGE: &[u8] = include_bytes!("../../../CHANGELOG.md");
 
+/// Storage key of the dashboard layout of every guild
+const DASHBOARD_KEY: &str = "dashboard_layouts";
+
 pub struct MainWindow {
     pub connection: Connection,
     pub panels: PanelStatus,
@@ -21,15 +27,38 @@ pub struct MainWindow {
     pub ws_sender: Option<WsSender>,
     pub ws_receiver: Option<WsReceiver>,
     pub conn_id: u64,
+    pub wire_format: WireFormat,
+    /// Whether the server accepts the cursor based requests. The page based requests are used
+    /// otherwise
+    pub cursor_requests: bool,
+    pub reconnect: Reconnect,
+    pub live_updates: LiveUpdates,
+    pub cache: DataCache,
+    /// The UI state that was saved on the last run
+    pub saved_ui_state: UiState,
 }
 
 impl App for MainWindow {
     fn update(&mut self, ctx: &Context, _: &mut Frame) {
-        self.check_event();
+        self.check_event(ctx);
+        self.check_reconnect(ctx);
+        self.check_live_updates(ctx);
+        self.check_cache_writes();
         self.check_ws_receiver(ctx);
         self.show_panels(ctx);
         ctx.request_repaint();
     }
+
+    fn save(&mut self, storage: &mut dyn Storage) {
+        for guild_id in self.live_updates.take_saves() {
+            self.save_guild_cache(guild_id);
+        }
+        while self.cache.has_pending() {
+            self.check_cache_writes();
+        }
+        eframe::set_value(storage, DASHBOARD_KEY, &self.tabs.dashboard_layouts());
+        eframe::set_value(storage, UI_STATE_KEY, &self.ui_state());
+    }
 }
 
 impl MainWindow {
@@ -43,14 +72,37 @@ impl MainWindow {
         let ctx = cc.egui_ctx.clone();
         add_font(&ctx);
 
+        let mut tabs = TabHandler::default();
+        if let Some(layouts) = cc
+            .storage
+            .and_then(|storage| eframe::get_value(storage, DASHBOARD_KEY))
+        {
+            tabs.set_saved_layouts(layouts);
+        }
+
+        let saved_ui_state: UiState = cc
+            .storage
+            .and_then(|storage| eframe::get_value(storage, UI_STATE_KEY))
+            .unwrap_or_default();
+        let mut panels = PanelStatus::default();
+        panels.restore_ui_state(&ctx, &saved_ui_state);
+        // Set before any cached data reaches the tabs so it is not processed twice
+        tabs.set_timezone(panels.timezone());
+
         Self {
             connection: Connection::default(),
-            panels: PanelStatus::default(),
-            tabs: TabHandler::default(),
+            panels,
+            tabs,
             event_bus: EventBus::default(),
             ws_sender: None,
             ws_receiver: None,
             conn_id: 0,
+            wire_format: WireFormat::default(),
+            cursor_requests: false,
+            reconnect: Reconnect::default(),
+            live_updates: LiveUpdates::default(),
+            cache: DataCache::default(),
+            saved_ui_state,
         }
     }
 
@@ -66,7 +118,11 @@ impl MainWindow {
 
     pub fn send_ws(&mut self, message: Request) {
         if let Some(sender) = self.ws_sender.as_mut() {
-            sender.send(WsMessage::Text(message.to_json()));
+            if self.wire_format.is_binary() {
+                sender.send(WsMessage::Binary(message.to_binary(self.wire_format)));
+            } else {
+                sender.send(WsMessage::Text(message.to_json()));
+            }
         } else {
             error!(
                 "Attempted to send a message to the websocket without a connection. {message:#?}"
@@ -84,26 +140,33 @@ impl MainWindow {
 
     pub fn fetch_guild_data(&mut self) {
         let guild_id = self.panels.selected_guild();
-        let fetch_status = self.panels.current_guild_status_m();
-        let messages_done = fetch_status.messages();
-        let counts_done = fetch_status.counts();
-        let activities_done = fetch_status.activities();
+        if !self.cache.is_loaded(guild_id) {
+            self.load_cached_data(guild_id);
+        }
 
+        let fetch_status = self.panels.current_guild_status_m().clone();
         let mut nothing_fetched = true;
 
-        if !messages_done {
+        // A partial fetch means a fetch is already ongoing for this guild
+        if !fetch_status.messages() {
             nothing_fetched = false;
-            self.send_ws(Request::get_messages(guild_id, 1));
+            if !fetch_status.partial_messages() {
+                self.request_messages(guild_id);
+            }
         }
 
-        if !counts_done {
+        if !fetch_status.counts() {
             nothing_fetched = false;
-            self.send_ws(Request::get_member_counts(guild_id, 1));
+            if !fetch_status.partial_counts() {
+                self.request_counts(guild_id);
+            }
         }
 
-        if counts_done && !activities_done {
+        if fetch_status.counts() && !fetch_status.activities() {
             nothing_fetched = false;
-            self.send_ws(Request::get_member_activity(guild_id, 1));
+            if !fetch_status.partial_activities() {
+                self.request_activities(guild_id);
+            }
         }
 
         if nothing_fetched {
@@ -111,8 +174,232 @@ impl MainWindow {
         }
     }
 
-    pub fn reset_all(&mut self) {
+    /// The cursor after the newest stored message of the guild
+    fn messages_cursor(&self, guild_id: i64) -> Option<Cursor> {
+        self.tabs
+            .message_store
+            .get(&guild_id)
+            .and_then(MessageStore::messages_cursor)
+    }
+
+    /// Request the messages after the last received batch or after the stored messages if nothing
+    /// was received yet
+    pub fn request_messages(&mut self, guild_id: i64) {
+        let fetch_status = self.panels.guild_status_m(guild_id).clone();
+        let request = if self.cursor_requests {
+            let since = fetch_status
+                .messages_cursor()
+                .or(self.messages_cursor(guild_id));
+            Request::get_messages_since(guild_id, since)
+        } else {
+            let page = match fetch_status.messages_page() {
+                0 => first_uncached_page(
+                    self.tabs
+                        .message_store
+                        .get(&guild_id)
+                        .map_or(0, MessageStore::len),
+                ),
+                page => page + 1,
+            };
+            Request::get_messages(guild_id, page)
+        };
+        self.send_ws(request);
+    }
+
+    pub fn request_counts(&mut self, guild_id: i64) {
+        let fetch_status = self.panels.guild_status_m(guild_id).clone();
+        let request = if self.cursor_requests {
+            let since = fetch_status
+                .counts_cursor()
+                .or(self.cache.counts_cursor(guild_id));
+            Request::get_member_counts_since(guild_id, since)
+        } else {
+            let page = match fetch_status.counts_page() {
+                0 => self.cache.counts_page(guild_id),
+                page => page + 1,
+            };
+            Request::get_member_counts(guild_id, page)
+        };
+        self.send_ws(request);
+    }
+
+    pub fn request_activities(&mut self, guild_id: i64) {
+        let fetch_status = self.panels.guild_status_m(guild_id).clone();
+        let request = if self.cursor_requests {
+            let since = fetch_status
+                .activities_cursor()
+                .or(self.cache.activities_cursor(guild_id));
+            Request::get_member_activity_since(guild_id, since)
+        } else {
+            let page = match fetch_status.activities_page() {
+                0 => self.cache.activities_page(guild_id),
+                page => page + 1,
+            };
+            Request::get_member_activity(guild_id, page)
+        };
+        self.send_ws(request);
+    }
+
+    /// Queue the changed parts of the guild cache with its stored messages to be written
+    pub fn save_guild_cache(&mut self, guild_id: i64) {
+        if let Some(store) = self.tabs.message_store.get_mut(&guild_id) {
+            self.cache.save_guild(guild_id, store);
+        }
+    }
+
+    /// Show the locally saved data of the guild before the newer records are requested
+    fn load_cached_data(&mut self, guild_id: i64) {
+        let messages = self.cache.load_guild(guild_id);
+        self.tabs.handle_messages(&messages, &mut self.event_bus);
+        // The loaded messages are already saved
+        if let Some(store) = self.tabs.message_store.get_mut(&guild_id) {
+            store.mark_saved();
+        }
+        self.show_cached_data(guild_id);
+    }
+
+    /// Pass the dates of the stored messages and every cached member record of the guild to the
+    /// tabs
+    fn show_cached_data(&mut self, guild_id: i64) {
+        let Some(cache) = self.cache.guild(guild_id) else {
+            return;
+        };
+        let date_range = self
+            .tabs
+            .message_store
+            .get(&guild_id)
+            .and_then(MessageStore::date_range);
+        if cache.is_empty() && date_range.is_none() {
+            return;
+        }
+
+        if let Some((first, last)) = date_range {
+            self.event_bus
+                .publish(AppEvent::UpdateDate(first, guild_id));
+            self.event_bus.publish(AppEvent::UpdateDate(last, guild_id));
+        }
+        self.tabs
+            .handle_member_counts(guild_id, cache.counts(), &mut self.event_bus);
+        self.tabs.fill_member_activity(guild_id);
+        self.tabs
+            .handle_member_activities(guild_id, cache.activities(), &mut self.event_bus);
+
+        self.event_bus.publish_reloads(guild_id);
+    }
+
+    /// Continue every guild that was being fetched from the last cursor that was received before
+    /// the connection was lost
+    pub fn resume_guild_data(&mut self) {
+        let selected_guild = self.panels.selected_guild();
+        let mut nothing_fetched = true;
+
+        for (guild_id, fetch_status) in self.panels.guild_statuses() {
+            // Live updates sent while disconnected were missed so fetch everything after the
+            // cache again. The guild subscribes again once it is done.
+            if fetch_status.live() {
+                info!("Refreshing live guild {guild_id}");
+                *self.panels.guild_status_m(guild_id) = FetchStatus::default();
+                nothing_fetched = false;
+
+                self.request_messages(guild_id);
+                self.request_counts(guild_id);
+                continue;
+            }
+
+            if !fetch_status.started() {
+                if guild_id == selected_guild {
+                    nothing_fetched = false;
+                    self.fetch_guild_data();
+                }
+                continue;
+            }
+
+            // Without a received batch the fetch starts after the cached records
+            if !fetch_status.messages() {
+                info!("Resuming messages of {guild_id}");
+                nothing_fetched = false;
+                self.request_messages(guild_id);
+            }
+
+            if !fetch_status.counts() {
+                info!("Resuming member counts of {guild_id}");
+                nothing_fetched = false;
+                self.request_counts(guild_id);
+            } else if !fetch_status.activities() {
+                info!("Resuming member activities of {guild_id}");
+                nothing_fetched = false;
+                self.request_activities(guild_id);
+            }
+        }
+
+        if nothing_fetched {
+            self.to_set_idle();
+        }
+    }
+
+    /// Convert the stored messages and process every cached member record again so the dates and
+    /// hours follow the selected timezone
+    pub fn change_timezone(&mut self) {
+        let timezone = self.panels.timezone();
+        info!("Changing the timezone to {timezone}");
+        self.tabs.set_timezone(timezone);
+
+        // Clearing the guild data closes the profiles so open them again afterwards
+        let user_profile = self
+            .tabs
+            .user_profile
+            .as_ref()
+            .map(|profile| (profile.guild_id(), profile.user_id()));
+        let channel_profile = self
+            .tabs
+            .channel_profile
+            .as_ref()
+            .map(|profile| (profile.guild_id(), profile.channel_id()));
+
+        // Clearing the guild data resets the chart settings and the picked dates
+        let ui_state = self.ui_state();
+
+        for (guild_id, channels) in self.panels.guild_channel_list() {
+            if !self.cache.is_loaded(guild_id) {
+                continue;
+            }
+
+            self.tabs.clear_tab_data(guild_id);
+            self.tabs
+                .set_overview_channel_map(guild_id, channels.clone());
+            self.tabs.set_channel_table_channel_map(guild_id, channels);
+            self.panels.reset_date_handler(guild_id);
+            if let Some(guild_state) = ui_state.guilds.get(&guild_id) {
+                self.panels.restore_guild_ui_state(guild_id, guild_state);
+                self.tabs.restore_guild_ui_state(guild_id, guild_state);
+            }
+
+            self.show_cached_data(guild_id);
+        }
+
+        let guild_channels = self.panels.current_guild_channels();
+        self.tabs.set_channels(guild_channels);
+        let selected_channels = self.panels.current_selected_channels();
+        self.tabs.set_selected_channels(&selected_channels);
+
+        // Queued so the profiles are built after the new date ranges reach the tabs
+        if let Some((guild_id, user_id)) = user_profile {
+            self.event_bus
+                .publish(AppEvent::OpenUserProfile(guild_id, user_id));
+        }
+        if let Some((guild_id, channel_id)) = channel_profile {
+            self.event_bus
+                .publish(AppEvent::OpenChannelProfile(guild_id, channel_id));
+        }
+    }
+
+    /// Remove every fetched data. The dashboard layouts and the UI state are kept so the next save
+    /// does not overwrite them with the defaults
+    pub fn reset_all(&mut self, ctx: &Context) {
         info!("Resetting all data");
+        let saved_layouts = self.tabs.dashboard_layouts();
+        let saved_ui_state = self.ui_state();
+
         *self = Self {
             connection: Connection::default(),
             panels: PanelStatus::default(),
@@ -121,7 +408,15 @@ impl MainWindow {
             ws_sender: None,
             ws_receiver: None,
             conn_id: 0,
-        }
+            wire_format: WireFormat::default(),
+            cursor_requests: false,
+            reconnect: Reconnect::default(),
+            live_updates: LiveUpdates::default(),
+            cache: DataCache::default(),
+            saved_ui_state,
+        };
+        self.tabs.set_saved_layouts(saved_layouts);
+        self.panels.restore_ui_state(ctx, &self.saved_ui_state);
     }
 
     #[must_use]
@@ -0,0 +1,53 @@
+use eframe::egui::ahash::HashMap;
+
+/// Seconds to wait after a live batch before the tabs of its guild are reloaded
+const RELOAD_DELAY: f64 = 5.0;
+/// Seconds to wait after a live batch before the cache of its guild is saved
+const SAVE_DELAY: f64 = 60.0;
+
+/// Guilds with live updates that were not reloaded or saved yet. Batches that arrive close to each
+/// other share a single reload and save
+#[derive(Default)]
+pub struct LiveUpdates {
+    /// Key: Guild ID. Value: The egui time at which the tabs should be reloaded
+    reloads: HashMap<i64, f64>,
+    /// Key: Guild ID. Value: The egui time at which the cache should be saved
+    saves: HashMap<i64, f64>,
+}
+
+impl LiveUpdates {
+    /// Schedule a reload and a save of the guild. An earlier schedule is kept so a busy guild is
+    /// still reloaded and saved regularly
+    pub fn add(&mut self, guild_id: i64, now: f64) {
+        self.reloads.entry(guild_id).or_insert(now + RELOAD_DELAY);
+        self.saves.entry(guild_id).or_insert(now + SAVE_DELAY);
+    }
+
+    /// Every guild whose reload is due. The schedules are consumed
+    pub fn due_reloads(&mut self, now: f64) -> Vec<i64> {
+        take_due(&mut self.reloads, now)
+    }
+
+    /// Every guild whose save is due. The schedules are consumed
+    pub fn due_saves(&mut self, now: f64) -> Vec<i64> {
+        take_due(&mut self.saves, now)
+    }
+
+    /// Every guild with a scheduled save, whether it is due or not
+    pub fn take_saves(&mut self) -> Vec<i64> {
+        self.saves.drain().map(|(guild_id, _)| guild_id).collect()
+    }
+}
+
+fn take_due(schedules: &mut HashMap<i64, f64>, now: f64) -> Vec<i64> {
+    let due: Vec<i64> = schedules
+        .iter()
+        .filter(|(_, time)| **time <= now)
+        .map(|(guild_id, _)| *guild_id)
+        .collect();
+
+    for guild_id in &due {
+        schedules.remove(guild_id);
+    }
+    due
+}
@@ -0,0 +1,627 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use eframe::egui::ahash::{HashMap, HashSet};
+use funnel_shared::{Cursor, MemberActivity, Message, MessageWithUser, User};
+use std::collections::BTreeMap;
+
+use crate::Timezone;
+
+/// Names of a user that sent at least one message
+pub struct StoredUser {
+    /// The global name of the user or the username if there is none
+    pub name: String,
+    pub username: String,
+    global_name: Option<String>,
+}
+
+impl StoredUser {
+    fn new(user: &User) -> Self {
+        Self {
+            name: user.global_name.clone().unwrap_or(user.username.clone()),
+            username: user.username.clone(),
+            global_name: user.global_name.clone(),
+        }
+    }
+
+    fn to_user(&self, user_id: i64) -> User {
+        User {
+            user_id,
+            global_name: self.global_name.clone(),
+            username: self.username.clone(),
+        }
+    }
+}
+
+/// A join or leave of a member
+pub struct StoredActivity {
+    pub user_id: Option<i64>,
+    pub username: Option<String>,
+    pub time: NaiveDateTime,
+    pub is_join: bool,
+}
+
+/// Every message of a single guild with one column per field. The tabs are built by querying it
+/// instead of keeping their own copy of the messages. It is the only copy of the messages in
+/// memory and the disk cache is written from it
+#[derive(Default)]
+pub struct MessageStore {
+    timezone: Timezone,
+    guild_id: i64,
+    message_ids: Vec<i64>,
+    channel_ids: Vec<i64>,
+    user_ids: Vec<i64>,
+    message_timestamps: Vec<i64>,
+    delete_timestamps: Vec<Option<i64>>,
+    /// The message timestamps in the timezone of the store
+    sent_times: Vec<NaiveDateTime>,
+    delete_times: Vec<Option<NaiveDateTime>>,
+    contents: Vec<Option<String>>,
+    stripped_contents: Vec<Option<String>>,
+    reply_messages: Vec<Option<i64>>,
+    reply_users: Vec<Option<i64>>,
+    mentions: Vec<Vec<i64>>,
+    attachment_types: Vec<Vec<String>>,
+    embed_counts: Vec<u32>,
+    custom_emojis: Vec<Vec<String>>,
+    /// Key: User ID
+    users: HashMap<i64, StoredUser>,
+    /// Key: Message ID. Value: Row index
+    message_index: HashMap<i64, usize>,
+    /// Key: The date the message was sent on or deleted on if it was deleted. Value: Row indexes
+    date_index: BTreeMap<NaiveDate, Vec<usize>>,
+    /// Key: Channel ID. Value: Row indexes
+    channel_index: HashMap<i64, Vec<usize>>,
+    /// Key: User ID. Value: Row indexes
+    user_index: HashMap<i64, Vec<usize>>,
+    /// Every join and leave in the order they were received
+    activities: Vec<StoredActivity>,
+    /// Key: User ID. Value: Index of the newest activity of the member
+    last_activity: HashMap<i64, usize>,
+    last_message_id: Option<i64>,
+    /// The first row that was added or replaced after the store was last saved
+    unsaved_from: Option<usize>,
+}
+
+impl MessageStore {
+    /// Convert every message to the timezone. The activities are removed so they can be added
+    /// again with the new timezone. Nothing changes if the store already uses the timezone
+    pub fn set_timezone(&mut self, timezone: Timezone) {
+        if self.timezone == timezone {
+            return;
+        }
+        self.timezone = timezone;
+        self.sent_times = self
+            .message_timestamps
+            .iter()
+            .map(|timestamp| timezone.naive_time(*timestamp))
+            .collect();
+        self.delete_times = self
+            .delete_timestamps
+            .iter()
+            .map(|timestamp| timestamp.map(|timestamp| timezone.naive_time(timestamp)))
+            .collect();
+
+        self.date_index.clear();
+        for index in 0..self.len() {
+            let date = self.time(index).date();
+            self.date_index.entry(date).or_default().push(index);
+        }
+
+        self.activities.clear();
+        self.last_activity.clear();
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.message_ids.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.message_ids.is_empty()
+    }
+
+    /// The first and the last date of the messages. None if the store is empty
+    #[must_use]
+    pub fn date_range(&self) -> Option<(NaiveDate, NaiveDate)> {
+        let (first, _) = self.date_index.first_key_value()?;
+        let (last, _) = self.date_index.last_key_value()?;
+        Some((*first, *last))
+    }
+
+    /// The cursor after the newest message. None if the store is empty
+    #[must_use]
+    pub fn messages_cursor(&self) -> Option<Cursor> {
+        self.last_message_id.map(Cursor::MessageId)
+    }
+
+    /// Add the message or replace the stored message with the same ID. Returns its date if it is
+    /// outside of every date that was seen before
+    pub fn add_message(&mut self, message: &MessageWithUser) -> Option<NaiveDate> {
+        let sender = &message.sender;
+        let message = &message.message;
+
+        let sent_time = self.timezone.naive_time(message.message_timestamp);
+        let delete_time = message
+            .delete_timestamp
+            .map(|timestamp| self.timezone.naive_time(timestamp));
+        let date = delete_time.unwrap_or(sent_time).date();
+
+        let new_date = self
+            .date_index
+            .first_key_value()
+            .is_none_or(|(first, _)| *first > date)
+            || self
+                .date_index
+                .last_key_value()
+                .is_none_or(|(last, _)| *last < date);
+
+        if let Some(index) = self.message_index.get(&message.message_id).copied() {
+            self.mark_unsaved(index);
+            self.replace_message(index, message, sent_time, delete_time);
+            return new_date.then_some(date);
+        }
+
+        let index = self.len();
+        self.mark_unsaved(index);
+        self.message_index.insert(message.message_id, index);
+        self.guild_id = message.guild_id;
+        self.last_message_id = self.last_message_id.max(Some(message.message_id));
+        self.message_ids.push(message.message_id);
+        self.channel_ids.push(message.channel_id);
+        self.user_ids.push(sender.user_id);
+        self.message_timestamps.push(message.message_timestamp);
+        self.delete_timestamps.push(message.delete_timestamp);
+        self.sent_times.push(sent_time);
+        self.delete_times.push(delete_time);
+        self.contents.push(message.message_content.clone());
+        self.stripped_contents
+            .push(message.stripped_content.clone());
+        self.reply_messages.push(message.reply_to_message);
+        self.reply_users.push(message.reply_to_user);
+        self.mentions.push(message.mentions.clone());
+        self.attachment_types.push(message.attachment_types.clone());
+        self.embed_counts.push(message.embed_count);
+        self.custom_emojis.push(message.custom_emojis.clone());
+
+        self.users
+            .entry(sender.user_id)
+            .or_insert_with(|| StoredUser::new(sender));
+
+        self.date_index.entry(date).or_default().push(index);
+        self.channel_index
+            .entry(message.channel_id)
+            .or_default()
+            .push(index);
+        self.user_index
+            .entry(sender.user_id)
+            .or_default()
+            .push(index);
+
+        new_date.then_some(date)
+    }
+
+    fn mark_unsaved(&mut self, index: usize) {
+        self.unsaved_from = Some(self.unsaved_from.map_or(index, |from| from.min(index)));
+    }
+
+    /// The first row that changed since the store was last saved. None if nothing changed
+    #[must_use]
+    pub fn unsaved_from(&self) -> Option<usize> {
+        self.unsaved_from
+    }
+
+    /// Mark every row as saved
+    pub fn mark_saved(&mut self) {
+        self.unsaved_from = None;
+    }
+
+    /// Replace every field of the row that can change after the message was sent and move the row
+    /// to its new date
+    fn replace_message(
+        &mut self,
+        index: usize,
+        message: &Message,
+        sent_time: NaiveDateTime,
+        delete_time: Option<NaiveDateTime>,
+    ) {
+        let old_date = self.time(index).date();
+
+        self.message_timestamps[index] = message.message_timestamp;
+        self.delete_timestamps[index] = message.delete_timestamp;
+        self.sent_times[index] = sent_time;
+        self.delete_times[index] = delete_time;
+        self.contents[index].clone_from(&message.message_content);
+        self.stripped_contents[index].clone_from(&message.stripped_content);
+        self.reply_messages[index] = message.reply_to_message;
+        self.reply_users[index] = message.reply_to_user;
+        self.mentions[index].clone_from(&message.mentions);
+        self.attachment_types[index].clone_from(&message.attachment_types);
+        self.embed_counts[index] = message.embed_count;
+        self.custom_emojis[index].clone_from(&message.custom_emojis);
+
+        let date = self.time(index).date();
+        if date == old_date {
+            return;
+        }
+        if let Some(rows) = self.date_index.get_mut(&old_date) {
+            rows.retain(|row| *row != index);
+            if rows.is_empty() {
+                self.date_index.remove(&old_date);
+            }
+        }
+        self.date_index.entry(date).or_default().push(index);
+    }
+
+    /// Add the join or leave with the timezone of the store
+    pub fn add_activity(&mut self, activity: &MemberActivity) {
+        let index = self.activities.len();
+        let time = self.timezone.naive_time(activity.activity_timestamp);
+
+        if let Some(user_id) = activity.user_id {
+            let is_newest = self
+                .last_activity
+                .get(&user_id)
+                .is_none_or(|last| self.activities[*last].time <= time);
+            if is_newest {
+                self.last_activity.insert(user_id, index);
+            }
+        }
+
+        self.activities.push(StoredActivity {
+            user_id: activity.user_id,
+            username: activity.username.clone(),
+            time,
+            is_join: activity.join_activity,
+        });
+    }
+
+    /// Every join and leave in the order they were received
+    #[must_use]
+    pub fn activities(&self) -> &[StoredActivity] {
+        &self.activities
+    }
+
+    /// Whether the newest activity of the user is a leave
+    #[must_use]
+    pub fn has_left(&self, user_id: i64) -> bool {
+        self.last_activity
+            .get(&user_id)
+            .is_some_and(|index| !self.activities[*index].is_join)
+    }
+
+    /// Name of the member of the activity. Falls back to the name of their messages and then to
+    /// the user ID
+    #[must_use]
+    pub fn activity_name(&self, activity: &StoredActivity) -> String {
+        if let Some(username) = &activity.username {
+            return username.clone();
+        }
+        match activity.user_id {
+            Some(user_id) => self
+                .user(user_id)
+                .map_or_else(|| user_id.to_string(), |user| user.username.clone()),
+            None => "Unknown".to_string(),
+        }
+    }
+
+    #[must_use]
+    pub fn user(&self, user_id: i64) -> Option<&StoredUser> {
+        self.users.get(&user_id)
+    }
+
+    /// Every user that sent at least one message
+    pub fn users(&self) -> impl Iterator<Item = &StoredUser> {
+        self.users.values()
+    }
+
+    /// Every message that matches the query
+    pub fn query(&self, query: MessageQuery) -> impl Iterator<Item = MessageRow<'_>> {
+        self.row_indexes(query).map(move |index| self.row(index))
+    }
+
+    /// Row index of every message that the most selective index of the query finds. The other
+    /// filters are not checked yet so they can be checked with `MessageQuery::matches` a chunk at
+    /// a time. The indexes stay valid as messages are only ever added to the store or replaced in
+    /// place
+    #[must_use]
+    pub fn candidate_rows(&self, query: &MessageQuery) -> Vec<usize> {
+        let rows = self.indexed_rows(query);
+        if query.newest_first {
+            rows.rev().collect()
+        } else {
+            rows.collect()
+        }
+    }
+
+    #[must_use]
+    pub fn row(&self, index: usize) -> MessageRow<'_> {
+        MessageRow { store: self, index }
+    }
+
+    /// The message of the row as it was received from the server
+    #[must_use]
+    pub fn message(&self, index: usize) -> MessageWithUser {
+        let user_id = self.user_ids[index];
+        let message = Message {
+            guild_id: self.guild_id,
+            channel_id: self.channel_ids[index],
+            message_id: self.message_ids[index],
+            message_timestamp: self.message_timestamps[index],
+            sender_id: user_id,
+            message_content: self.contents[index].clone(),
+            stripped_content: self.stripped_contents[index].clone(),
+            delete_timestamp: self.delete_timestamps[index],
+            reply_to_message: self.reply_messages[index],
+            reply_to_user: self.reply_users[index],
+            mentions: self.mentions[index].clone(),
+            attachment_types: self.attachment_types[index].clone(),
+            embed_count: self.embed_counts[index],
+            custom_emojis: self.custom_emojis[index].clone(),
+        };
+        MessageWithUser::new(message, self.users[&user_id].to_user(user_id))
+    }
+
+    /// The most selective index of the query is used to find the messages before the remaining
+    /// filters are checked
+    fn row_indexes(&self, query: MessageQuery) -> impl Iterator<Item = usize> + '_ {
+        let rows = self.indexed_rows(&query);
+        let rows: Box<dyn Iterator<Item = usize> + '_> = if query.newest_first {
+            Box::new(rows.rev())
+        } else {
+            rows
+        };
+
+        rows.filter(move |index| query.matches(self, *index))
+    }
+
+    /// Every row of the most selective index of the query
+    fn indexed_rows(
+        &self,
+        query: &MessageQuery,
+    ) -> Box<dyn DoubleEndedIterator<Item = usize> + '_> {
+        if let Some(user_id) = query.user {
+            Box::new(self.user_index.get(&user_id).into_iter().flatten().copied())
+        } else if let Some(channel_id) = query.channel {
+            Box::new(
+                self.channel_index
+                    .get(&channel_id)
+                    .into_iter()
+                    .flatten()
+                    .copied(),
+            )
+        } else if let Some((from, to)) = query.dates {
+            if from > to {
+                Box::new(std::iter::empty())
+            } else {
+                Box::new(
+                    self.date_index
+                        .range(from..=to)
+                        .flat_map(|(_, rows)| rows.iter().copied()),
+                )
+            }
+        } else {
+            Box::new(0..self.len())
+        }
+    }
+
+    fn time(&self, index: usize) -> NaiveDateTime {
+        self.delete_times[index].unwrap_or(self.sent_times[index])
+    }
+}
+
+/// Filters of a store query. A filter that is not set matches every message
+#[derive(Default, Clone)]
+pub struct MessageQuery {
+    dates: Option<(NaiveDate, NaiveDate)>,
+    channels: Option<HashSet<i64>>,
+    channel: Option<i64>,
+    user: Option<i64>,
+    skip_deleted: bool,
+    only_deleted: bool,
+    newest_first: bool,
+}
+
+impl MessageQuery {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only the messages that were sent or deleted from `from` to `to`
+    #[must_use]
+    pub fn dates(mut self, from: NaiveDate, to: NaiveDate) -> Self {
+        self.dates = Some((from, to));
+        self
+    }
+
+    /// Only the messages of the given channels
+    #[must_use]
+    pub fn channels(mut self, channels: HashSet<i64>) -> Self {
+        self.channels = Some(channels);
+        self
+    }
+
+    /// Only the messages of a single channel
+    #[must_use]
+    pub fn channel(mut self, channel_id: i64) -> Self {
+        self.channel = Some(channel_id);
+        self
+    }
+
+    /// Only the messages of a single user
+    #[must_use]
+    pub fn user(mut self, user_id: i64) -> Self {
+        self.user = Some(user_id);
+        self
+    }
+
+    /// Leave out the messages that were deleted
+    #[must_use]
+    pub fn skip_deleted(mut self) -> Self {
+        self.skip_deleted = true;
+        self
+    }
+
+    /// Leave out the messages that were not deleted
+    #[must_use]
+    pub fn only_deleted(mut self) -> Self {
+        self.only_deleted = true;
+        self
+    }
+
+    /// Go through the messages from the newest to the oldest one
+    #[must_use]
+    pub fn newest_first(mut self) -> Self {
+        self.newest_first = true;
+        self
+    }
+
+    /// Whether the message of the row passes every filter
+    #[must_use]
+    pub fn matches(&self, store: &MessageStore, index: usize) -> bool {
+        if let Some((from, to)) = self.dates {
+            let date = store.time(index).date();
+            if date < from || date > to {
+                return false;
+            }
+        }
+
+        let channel_id = store.channel_ids[index];
+        if self
+            .channels
+            .as_ref()
+            .is_some_and(|channels| !channels.contains(&channel_id))
+        {
+            return false;
+        }
+        if self.channel.is_some_and(|target| target != channel_id) {
+            return false;
+        }
+        if self
+            .user
+            .is_some_and(|target| target != store.user_ids[index])
+        {
+            return false;
+        }
+        let deleted = store.delete_times[index].is_some();
+        !(self.skip_deleted && deleted || self.only_deleted && !deleted)
+    }
+}
+
+/// A single message of a store
+#[derive(Clone, Copy)]
+pub struct MessageRow<'a> {
+    store: &'a MessageStore,
+    index: usize,
+}
+
+impl<'a> MessageRow<'a> {
+    #[must_use]
+    pub fn message_id(&self) -> i64 {
+        self.store.message_ids[self.index]
+    }
+
+    #[must_use]
+    pub fn channel_id(&self) -> i64 {
+        self.store.channel_ids[self.index]
+    }
+
+    #[must_use]
+    pub fn user_id(&self) -> i64 {
+        self.store.user_ids[self.index]
+    }
+
+    /// The user that sent the message
+    #[must_use]
+    pub fn user(&self) -> &'a StoredUser {
+        self.store.users.get(&self.user_id()).unwrap()
+    }
+
+    #[must_use]
+    pub fn sent_time(&self) -> NaiveDateTime {
+        self.store.sent_times[self.index]
+    }
+
+    #[must_use]
+    pub fn delete_time(&self) -> Option<NaiveDateTime> {
+        self.store.delete_times[self.index]
+    }
+
+    #[must_use]
+    pub fn is_deleted(&self) -> bool {
+        self.delete_time().is_some()
+    }
+
+    /// The time the message was deleted if it was deleted, otherwise the time it was sent
+    #[must_use]
+    pub fn time(&self) -> NaiveDateTime {
+        self.store.time(self.index)
+    }
+
+    #[must_use]
+    pub fn content(&self) -> Option<&'a str> {
+        self.store.contents[self.index].as_deref()
+    }
+
+    #[must_use]
+    pub fn stripped_content(&self) -> Option<&'a str> {
+        self.store.stripped_contents[self.index].as_deref()
+    }
+
+    /// The author of the message that was replied to
+    #[must_use]
+    pub fn reply_to_user(&self) -> Option<i64> {
+        self.store.reply_users[self.index]
+    }
+
+    /// Every user that was mentioned in the message
+    #[must_use]
+    pub fn mentions(&self) -> &'a [i64] {
+        &self.store.mentions[self.index]
+    }
+
+    /// Content type of every attachment of the message
+    #[must_use]
+    pub fn attachment_types(&self) -> &'a [String] {
+        &self.store.attachment_types[self.index]
+    }
+
+    #[must_use]
+    pub fn attachment_count(&self) -> u32 {
+        self.attachment_types().len() as u32
+    }
+
+    #[must_use]
+    pub fn embed_count(&self) -> u32 {
+        self.store.embed_counts[self.index]
+    }
+
+    /// Whether the message has at least one attachment or embed
+    #[must_use]
+    pub fn has_media(&self) -> bool {
+        self.attachment_count() > 0 || self.embed_count() > 0
+    }
+
+    /// Name of every custom emoji that was used in the message
+    #[must_use]
+    pub fn custom_emojis(&self) -> &'a [String] {
+        &self.store.custom_emojis[self.index]
+    }
+
+    /// Domain of every link in the content without the www prefix
+    #[must_use]
+    pub fn link_domains(&self) -> Vec<&'a str> {
+        self.content()
+            .into_iter()
+            .flat_map(str::split_whitespace)
+            .filter_map(|word| {
+                let word = word.trim_start_matches('<');
+                word.strip_prefix("https://")
+                    .or_else(|| word.strip_prefix("http://"))
+            })
+            .filter_map(|link| link.split(['/', '?', '#', '>']).next())
+            .map(|domain| domain.strip_prefix("www.").unwrap_or(domain))
+            .filter(|domain| !domain.is_empty())
+            .collect()
+    }
+}
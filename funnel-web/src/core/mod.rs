@@ -1,11 +1,27 @@
+mod cache;
 mod event_bus;
+mod export;
 mod fetch_status;
 mod initializer;
+mod live;
+mod message_store;
+mod reconnect;
+mod server;
 mod state;
+mod timezone;
+mod ui_state;
 mod utils;
 
+pub use cache::*;
 pub use event_bus::*;
+pub use export::*;
 pub use fetch_status::*;
 pub use initializer::*;
+pub use live::*;
+pub use message_store::*;
+pub use reconnect::*;
+pub use server::*;
 pub use state::*;
+pub use timezone::*;
+pub use ui_state::*;
 pub use utils::*;
@@ -0,0 +1,57 @@
+/// Delay before the first reconnect attempt in seconds
+const BASE_DELAY: f64 = 1.0;
+/// The delay between attempts will never go above this many seconds
+const MAX_DELAY: f64 = 30.0;
+/// Stop reconnecting after this many failed attempts in a row
+const MAX_ATTEMPTS: u32 = 8;
+
+#[derive(Default)]
+pub struct Reconnect {
+    /// Number of attempts made since the connection was lost
+    attempt: u32,
+    /// The egui time at which the next attempt should be made
+    next_attempt: Option<f64>,
+    /// Whether the connection was lost and is being recreated
+    resuming: bool,
+}
+
+impl Reconnect {
+    /// Schedule the next reconnect attempt with exponential backoff. Returns the delay in seconds
+    /// or None if the maximum number of attempts has been reached
+    pub fn schedule(&mut self, now: f64) -> Option<u64> {
+        if self.attempt >= MAX_ATTEMPTS {
+            return None;
+        }
+
+        let delay = (BASE_DELAY * 2_f64.powi(self.attempt as i32)).min(MAX_DELAY);
+        self.attempt += 1;
+        self.resuming = true;
+        self.next_attempt = Some(now + delay);
+
+        Some(delay as u64)
+    }
+
+    /// Whether the scheduled attempt is due. The schedule is consumed if it is
+    pub fn is_due(&mut self, now: f64) -> bool {
+        if self.next_attempt.is_some_and(|time| time <= now) {
+            self.next_attempt = None;
+            return true;
+        }
+        false
+    }
+
+    #[must_use]
+    pub fn attempt(&self) -> u32 {
+        self.attempt
+    }
+
+    #[must_use]
+    pub fn is_resuming(&self) -> bool {
+        self.resuming
+    }
+
+    /// Clear the attempt count once the session has been recreated
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
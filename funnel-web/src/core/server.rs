@@ -0,0 +1,359 @@
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+
+#[cfg(target_arch = "wasm32")]
+use web_sys::window;
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::fs;
+#[cfg(not(target_arch = "wasm32"))]
+use std::io::Write;
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::core::get_target_path;
+
+const DEFAULT_NAME: &str = "Funnel";
+const DEFAULT_WS_URL: &str = "wss://funnel-jyz9.shuttle.app/ws";
+const DEFAULT_REDIRECT_URL: &str = "https://funnel-jyz9.shuttle.app/auth/redirect/";
+const DEFAULT_CLIENT_ID: &str = "1324028221066576017";
+
+/// Name of the profile that is created from the runtime configuration source
+const RUNTIME_NAME: &str = "Launch Config";
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ServerProfile {
+    pub name: String,
+    pub ws_url: String,
+    pub redirect_url: String,
+    pub client_id: String,
+}
+
+impl Default for ServerProfile {
+    fn default() -> Self {
+        Self {
+            name: DEFAULT_NAME.to_string(),
+            ws_url: DEFAULT_WS_URL.to_string(),
+            redirect_url: DEFAULT_REDIRECT_URL.to_string(),
+            client_id: DEFAULT_CLIENT_ID.to_string(),
+        }
+    }
+}
+
+impl ServerProfile {
+    /// Create a profile from only the websocket URL. The OAuth redirect is derived from the same
+    /// host as the websocket server.
+    #[must_use]
+    pub fn from_ws_url(name: &str, ws_url: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            ws_url: ws_url.to_string(),
+            redirect_url: redirect_from_ws(ws_url),
+            client_id: DEFAULT_CLIENT_ID.to_string(),
+        }
+    }
+
+    /// Derive the OAuth redirect URL from the current websocket URL
+    pub fn sync_redirect(&mut self) {
+        self.redirect_url = redirect_from_ws(&self.ws_url);
+    }
+
+    /// The Discord OAuth URL that redirects back to this server
+    #[must_use]
+    pub fn login_url(&self, conn_id: u64) -> String {
+        format!(
+            "https://discord.com/oauth2/authorize?client_id={}&response_type=code&redirect_uri={}&scope=identify+guilds&state={conn_id}",
+            self.client_id,
+            encode_url(&self.redirect_url)
+        )
+    }
+
+    /// The Discord URL for adding the bot of this server to a guild
+    #[must_use]
+    pub fn invite_url(&self) -> String {
+        format!(
+            "https://discord.com/oauth2/authorize?client_id={}&permissions=66560&integration_type=0&scope=bot",
+            self.client_id
+        )
+    }
+
+    #[must_use]
+    pub fn is_valid(&self) -> bool {
+        !self.name.trim().is_empty()
+            && (self.ws_url.starts_with("ws://") || self.ws_url.starts_with("wss://"))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ServerList {
+    profiles: Vec<ServerProfile>,
+    selected: usize,
+}
+
+impl ServerList {
+    /// Load the saved server profiles and apply the runtime configuration on top of it
+    #[must_use]
+    pub fn load() -> Self {
+        let mut list = get_server_list().unwrap_or(Self {
+            profiles: vec![ServerProfile::default()],
+            selected: 0,
+        });
+
+        if list.profiles.is_empty() {
+            list.profiles.push(ServerProfile::default());
+        }
+
+        if list.selected >= list.profiles.len() {
+            list.selected = 0;
+        }
+
+        if let Some(runtime_profile) = get_runtime_profile() {
+            info!(
+                "Using server {} from the launch configuration",
+                runtime_profile.ws_url
+            );
+            list.profiles.retain(|p| p.name != RUNTIME_NAME);
+            list.profiles.push(runtime_profile);
+            list.selected = list.profiles.len() - 1;
+        }
+
+        list
+    }
+
+    #[must_use]
+    pub fn current(&self) -> &ServerProfile {
+        &self.profiles[self.selected]
+    }
+
+    #[must_use]
+    pub fn profiles(&self) -> &[ServerProfile] {
+        &self.profiles
+    }
+
+    #[must_use]
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    pub fn select(&mut self, index: usize) {
+        if index < self.profiles.len() {
+            self.selected = index;
+            self.save();
+        }
+    }
+
+    /// Add a new profile or replace the existing one with the same name and select it
+    pub fn add_profile(&mut self, profile: ServerProfile) {
+        if let Some(index) = self.profiles.iter().position(|p| p.name == profile.name) {
+            self.profiles[index] = profile;
+            self.selected = index;
+        } else {
+            self.profiles.push(profile);
+            self.selected = self.profiles.len() - 1;
+        }
+        self.save();
+    }
+
+    /// Remove the profile at the given index. The last remaining profile cannot be removed.
+    pub fn remove_profile(&mut self, index: usize) {
+        if self.profiles.len() <= 1 || index >= self.profiles.len() {
+            return;
+        }
+
+        self.profiles.remove(index);
+        if self.selected >= index && self.selected != 0 {
+            self.selected -= 1;
+        }
+        self.save();
+    }
+
+    fn save(&self) {
+        save_server_list(self);
+    }
+}
+
+/// Convert a websocket URL to the OAuth redirect URL of the same server.
+/// `wss://example.com/ws` becomes `https://example.com/auth/redirect/`
+fn redirect_from_ws(ws_url: &str) -> String {
+    let http_url = if let Some(rest) = ws_url.strip_prefix("wss://") {
+        format!("https://{rest}")
+    } else if let Some(rest) = ws_url.strip_prefix("ws://") {
+        format!("http://{rest}")
+    } else {
+        ws_url.to_string()
+    };
+
+    let base = http_url.trim_end_matches('/');
+    let base = base.strip_suffix("/ws").unwrap_or(base);
+    format!("{base}/auth/redirect/")
+}
+
+/// Percent encode everything except the unreserved characters
+fn encode_url(url: &str) -> String {
+    let mut encoded = String::new();
+    for byte in url.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char);
+            }
+            _ => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
+}
+
+/// Get the server passed in at launch.
+///
+/// Native: `--server <ws url>` and optionally `--redirect <url>` and `--client-id <id>`
+///
+/// Wasm: `?server=<ws url>&redirect=<url>&client_id=<id>` query parameters or
+/// `<meta name="funnel-server" content="<ws url>">` along with `funnel-redirect` and
+/// `funnel-client-id` meta tags in `index.html`
+fn get_runtime_profile() -> Option<ServerProfile> {
+    let (ws_url, redirect_url, client_id) = get_runtime_values();
+
+    let mut profile = ServerProfile::from_ws_url(RUNTIME_NAME, &ws_url?);
+    if let Some(redirect_url) = redirect_url {
+        profile.redirect_url = redirect_url;
+    }
+    if let Some(client_id) = client_id {
+        profile.client_id = client_id;
+    }
+    Some(profile)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn get_runtime_values() -> (Option<String>, Option<String>, Option<String>) {
+    let mut ws_url = None;
+    let mut redirect_url = None;
+    let mut client_id = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let (key, value) = if let Some((key, value)) = arg.split_once('=') {
+            (key.to_string(), Some(value.to_string()))
+        } else {
+            (arg, args.next())
+        };
+
+        match key.as_str() {
+            "--server" => ws_url = value,
+            "--redirect" => redirect_url = value,
+            "--client-id" => client_id = value,
+            _ => {}
+        }
+    }
+
+    (ws_url, redirect_url, client_id)
+}
+
+#[cfg(target_arch = "wasm32")]
+fn get_runtime_values() -> (Option<String>, Option<String>, Option<String>) {
+    let Some(window) = window() else {
+        return (None, None, None);
+    };
+
+    let query = window.location().search().unwrap_or_default();
+    let query_value = |name: &str| {
+        query
+            .trim_start_matches('?')
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| decode_url(value))
+    };
+
+    let meta_value = |name: &str| {
+        window
+            .document()?
+            .query_selector(&format!("meta[name=\"{name}\"]"))
+            .ok()
+            .flatten()?
+            .get_attribute("content")
+            .filter(|content| !content.is_empty())
+    };
+
+    let ws_url = query_value("server").or_else(|| meta_value("funnel-server"));
+    let redirect_url = query_value("redirect").or_else(|| meta_value("funnel-redirect"));
+    let client_id = query_value("client_id").or_else(|| meta_value("funnel-client-id"));
+
+    (ws_url, redirect_url, client_id)
+}
+
+#[cfg(target_arch = "wasm32")]
+fn decode_url(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+
+    while index < bytes.len() {
+        let hex_byte = bytes
+            .get(index + 1..index + 3)
+            .filter(|_| bytes[index] == b'%')
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+        if let Some(byte) = hex_byte {
+            decoded.push(byte);
+            index += 3;
+            continue;
+        }
+        decoded.push(bytes[index]);
+        index += 1;
+    }
+
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+fn save_server_list(list: &ServerList) {
+    let json = match serde_json::to_string(list) {
+        Ok(json) => json,
+        Err(e) => {
+            error!("Failed to serialize server profiles: {e}");
+            return;
+        }
+    };
+
+    #[cfg(target_arch = "wasm32")]
+    {
+        if let Some(storage) = window().and_then(|w| w.local_storage().ok().flatten()) {
+            let _ = storage.set_item("server_profiles", &json);
+        }
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        if let Some(mut path) = get_target_path() {
+            path.push("servers.json");
+
+            match fs::File::create(&path) {
+                Ok(mut file) => {
+                    if let Err(e) = file.write_all(json.as_bytes()) {
+                        error!("Failed to write to server file {path:?}: {e}");
+                    }
+                }
+                Err(e) => error!("Failed to create server file {path:?}: {e}"),
+            }
+        }
+    }
+}
+
+fn get_server_list() -> Option<ServerList> {
+    #[cfg(target_arch = "wasm32")]
+    let content = window()
+        .and_then(|w| w.local_storage().ok().flatten())
+        .and_then(|s| s.get_item("server_profiles").ok().flatten())?;
+
+    #[cfg(not(target_arch = "wasm32"))]
+    let content = {
+        let mut path = get_target_path()?;
+        path.push("servers.json");
+        fs::read_to_string(&path).ok()?
+    };
+
+    match serde_json::from_str::<ServerList>(&content) {
+        Ok(list) => Some(list),
+        Err(e) => {
+            error!("Failed to deserialize saved server profiles: {e}");
+            None
+        }
+    }
+}
@@ -1,7 +1,11 @@
-use chrono::NaiveDate;
+use chrono::{Datelike, Duration, Months, NaiveDate, NaiveDateTime, Timelike, Weekday};
+
+use serde::{Deserialize, Serialize};
 use strum_macros::{Display, EnumIter};
 
-#[derive(Default, Eq, PartialEq, Display, EnumIter, Clone, Copy)]
+use crate::ExportFormat;
+
+#[derive(Default, Eq, PartialEq, Display, EnumIter, Clone, Copy, Serialize, Deserialize)]
 pub enum TabState {
     #[default]
     Overview,
@@ -15,12 +19,21 @@ pub enum TabState {
     UserChart,
     #[strum(to_string = "Common Words")]
     CommonWords,
+    Heatmap,
+    #[strum(to_string = "Message Search")]
+    MessageSearch,
+    #[strum(to_string = "Deleted Messages")]
+    DeletedMessages,
+    Dashboard,
+    Cohorts,
+    Interactions,
+    Media,
 }
 
 impl TabState {
     #[must_use]
     pub fn last_value() -> Self {
-        TabState::CommonWords
+        TabState::Media
     }
 
     #[must_use]
@@ -50,12 +63,24 @@ pub enum AppEvent {
     WordTableNeedsReload(i64),
     MessageChartNeedsReload(i64),
     UserChartNeedsReload(i64),
+    HeatmapNeedsReload(i64),
+    MessageSearchNeedsReload(i64),
+    DeletedMessagesNeedsReload(i64),
+    DashboardNeedsReload(i64),
+    CohortsNeedsReload(i64),
+    InteractionsNeedsReload(i64),
+    MediaNeedsReload(i64),
     CellsCopied,
     GuildChanged,
     StopCompareOverview,
     MessageChartTypeChanged(i64),
     UserChartTypeChanged(i64),
     SelectedChannelsChanged,
+    TimezoneChanged,
+    ServerChanged,
+    ExportTab(ExportFormat),
+    OpenUserProfile(i64, i64),
+    OpenChannelProfile(i64, i64),
     LogOut,
 }
 
@@ -67,10 +92,16 @@ pub enum AppStatus {
     ConnectingToWs,
     #[strum(to_string = "Failed to connect to the websocket server. Reason: {0}")]
     FailedWs(String),
+    #[strum(to_string = "Connection lost. Reconnecting in {1}s, attempt {0}")]
+    Reconnecting(u32, u64),
     #[strum(to_string = "Fetching data from the server")]
     Fetching,
     #[strum(to_string = "Copied selected cells to clipboard")]
     CellsCopied,
+    #[strum(to_string = "Exported data to {0}")]
+    Exported(String),
+    #[strum(to_string = "Failed to export data. Reason: {0}")]
+    FailedExport(String),
     #[strum(to_string = "Waiting to login to discord..")]
     LoggingIn,
     #[strum(
@@ -87,6 +118,8 @@ pub enum AppStatus {
     AttemptLogOut,
     #[strum(to_string = "Faiiled to log out of Discord. Reason: {0}")]
     FailedLogOut(String),
+    #[strum(to_string = "Not enough storage to cache the guild. Its data will be fetched again")]
+    FailedCache,
 }
 
 impl AppStatus {
@@ -94,17 +127,21 @@ impl AppStatus {
     pub fn show_spinner(&self) -> bool {
         match self {
             AppStatus::ConnectingToWs
+            | AppStatus::Reconnecting(_, _)
             | AppStatus::Fetching
             | AppStatus::LoggingIn
             | AppStatus::AttemptLogOut => true,
             AppStatus::Idle
             | AppStatus::FailedWs(_)
             | AppStatus::CellsCopied
+            | AppStatus::Exported(_)
+            | AppStatus::FailedExport(_)
             | AppStatus::NoValidGuild
             | AppStatus::FailedAuth
             | AppStatus::UnexpectedError(_)
             | AppStatus::LoggedOut
-            | AppStatus::FailedLogOut(_) => false,
+            | AppStatus::FailedLogOut(_)
+            | AppStatus::FailedCache => false,
         }
     }
 }
@@ -134,6 +171,9 @@ pub enum UserColumn {
     LastMessageSeen,
     #[strum(to_string = "Unique Channels")]
     UniqueChannels,
+    #[strum(to_string = "Left Guild")]
+    LeftGuild,
+    Attachments,
 }
 
 #[derive(EnumIter, Display, Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Default, Copy)]
@@ -152,6 +192,9 @@ pub enum ChannelColumn {
     LastMessage,
     #[strum(to_string = "Unique Users")]
     UniqueUsers,
+    Attachments,
+    #[strum(to_string = "Media Share")]
+    MediaShare,
 }
 
 #[derive(EnumIter, Display, Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Default, Copy)]
@@ -159,9 +202,39 @@ pub enum WordColumn {
     #[default]
     Phrase,
     Hits,
+    #[strum(to_string = "Unique Users")]
+    UniqueUsers,
+    #[strum(to_string = "Top User")]
+    TopUser,
 }
 
-#[derive(Default, Copy, Clone, Eq, PartialEq, Display, EnumIter)]
+#[derive(EnumIter, Display, Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Default, Copy)]
+pub enum SearchColumn {
+    #[default]
+    Time,
+    Author,
+    Username,
+    Channel,
+    Content,
+    Link,
+}
+
+#[derive(EnumIter, Display, Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Default, Copy)]
+pub enum DeletedColumn {
+    #[default]
+    #[strum(to_string = "Deleted At")]
+    DeleteTime,
+    #[strum(to_string = "Sent At")]
+    SentTime,
+    #[strum(to_string = "Time To Deletion")]
+    TimeToDeletion,
+    Author,
+    Username,
+    Channel,
+    Content,
+}
+
+#[derive(Default, Copy, Clone, Eq, PartialEq, Hash, Display, EnumIter, Serialize, Deserialize)]
 pub enum ChartType {
     Hourly,
     #[default]
@@ -169,3 +242,84 @@ pub enum ChartType {
     Weekly,
     Monthly,
 }
+
+impl ChartType {
+    /// The start of the hour, day, week or month the time is in
+    #[must_use]
+    pub fn bucket_start(self, time: NaiveDateTime) -> NaiveDateTime {
+        let day_start = time.date().and_hms_opt(0, 0, 0).unwrap();
+        match self {
+            ChartType::Hourly => time.with_minute(0).unwrap().with_second(0).unwrap(),
+            ChartType::Daily => day_start,
+            // We only care about the week number for this. Set it as Monday to keep a common ground
+            ChartType::Weekly => {
+                let week = time.iso_week();
+                NaiveDate::from_isoywd_opt(week.year(), week.week(), Weekday::Mon)
+                    .unwrap()
+                    .and_hms_opt(0, 0, 0)
+                    .unwrap()
+            }
+            ChartType::Monthly => day_start.with_day(1).unwrap(),
+        }
+    }
+
+    /// Start of every bucket from the bucket of the `from` date to the bucket of the `to` date
+    #[must_use]
+    pub fn buckets(self, from: NaiveDate, to: NaiveDate) -> Vec<NaiveDateTime> {
+        let mut buckets = Vec::new();
+        let mut bucket = self.bucket_start(from.and_hms_opt(0, 0, 0).unwrap());
+        let last_bucket = self.bucket_start(to.and_hms_opt(23, 59, 59).unwrap());
+
+        while bucket <= last_bucket {
+            buckets.push(bucket);
+            bucket = match self {
+                ChartType::Hourly => bucket + Duration::hours(1),
+                ChartType::Daily => bucket + Duration::days(1),
+                ChartType::Weekly => bucket + Duration::weeks(1),
+                ChartType::Monthly => bucket.checked_add_months(Months::new(1)).unwrap(),
+            };
+        }
+        buckets
+    }
+}
+
+#[derive(Default, Copy, Clone, Eq, PartialEq, Display, EnumIter)]
+pub enum HeatmapType {
+    #[default]
+    Messages,
+    #[strum(to_string = "Active Users")]
+    ActiveUsers,
+}
+
+/// A single value of the Overview that can be pinned to a dashboard
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Display, EnumIter, Serialize, Deserialize)]
+pub enum OverviewCard {
+    #[strum(to_string = "Total Messages")]
+    TotalMessage,
+    #[strum(to_string = "Deleted Messages")]
+    DeletedMessage,
+    #[strum(to_string = "Unique Users")]
+    UniqueUser,
+    #[strum(to_string = "Member Count")]
+    MemberCount,
+    #[strum(to_string = "Member Joins")]
+    MemberJoin,
+    #[strum(to_string = "Member Leaves")]
+    MemberLeave,
+    #[strum(to_string = "Most Active Member")]
+    MostActiveMember,
+    #[strum(to_string = "Most Active Channel")]
+    MostActiveChannel,
+    #[strum(to_string = "Joined and Posted")]
+    JoinedPosted,
+    #[strum(to_string = "Median Time to First Message")]
+    MedianFirstMessage,
+}
+
+/// The target of a top-N table that can be pinned to a dashboard
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Display, EnumIter, Serialize, Deserialize)]
+pub enum TopTarget {
+    Users,
+    Channels,
+    Phrases,
+}
@@ -0,0 +1,50 @@
+use chrono::{DateTime, Local, NaiveDateTime};
+use chrono_tz::{TZ_VARIANTS, Tz};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// The timezone every timestamp is converted to before it is placed in a date or an hour
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Timezone {
+    #[default]
+    Local,
+    /// An IANA zone such as Europe/Berlin. Daylight saving time follows the rules of the zone
+    Zone(Tz),
+}
+
+impl fmt::Display for Timezone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Timezone::Local => write!(f, "Local"),
+            Timezone::Zone(zone) => write!(f, "{}", zone.name()),
+        }
+    }
+}
+
+impl Timezone {
+    /// Every timezone that can be selected in the UI. Local and UTC come first, then every zone
+    /// by name
+    #[must_use]
+    pub fn options() -> Vec<Self> {
+        let mut zones: Vec<Tz> = TZ_VARIANTS
+            .iter()
+            .copied()
+            .filter(|zone| *zone != Tz::UTC)
+            .collect();
+        zones.sort_by_key(|zone| zone.name());
+
+        let mut options = vec![Timezone::Local, Timezone::Zone(Tz::UTC)];
+        options.extend(zones.into_iter().map(Timezone::Zone));
+        options
+    }
+
+    /// Convert a unix timestamp to the date and time in this timezone
+    #[must_use]
+    pub fn naive_time(self, timestamp: i64) -> NaiveDateTime {
+        let datetime = DateTime::from_timestamp(timestamp, 0).unwrap();
+        match self {
+            Timezone::Local => datetime.with_timezone(&Local).naive_local(),
+            Timezone::Zone(zone) => datetime.with_timezone(&zone).naive_local(),
+        }
+    }
+}
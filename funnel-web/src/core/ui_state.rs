@@ -0,0 +1,107 @@
+use chrono::NaiveDate;
+use eframe::egui::ahash::HashMap;
+use serde::{Deserialize, Serialize};
+
+use crate::ui::WordFilter;
+use crate::{ChartType, MainWindow, TabState, Timezone};
+
+/// Storage key of the UI state
+pub const UI_STATE_KEY: &str = "ui_state";
+
+/// The UI state that is restored on the next start
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct UiState {
+    pub tab_state: TabState,
+    pub selected_guild: Option<i64>,
+    pub dark_theme: bool,
+    pub show_guild: bool,
+    pub show_channel: bool,
+    pub timezone: Timezone,
+    /// Key: Guild ID
+    pub guilds: HashMap<i64, GuildUiState>,
+}
+
+impl Default for UiState {
+    fn default() -> Self {
+        Self {
+            tab_state: TabState::default(),
+            selected_guild: None,
+            dark_theme: false,
+            show_guild: true,
+            show_channel: true,
+            timezone: Timezone::default(),
+            guilds: HashMap::default(),
+        }
+    }
+}
+
+/// The UI state of a single guild
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct GuildUiState {
+    /// ID of every selected channel
+    pub selected_channels: Vec<i64>,
+    /// Whether All Channels was selected
+    pub all_channels: bool,
+    /// From and To date if they were picked instead of following the data
+    pub date_range: Option<(NaiveDate, NaiveDate)>,
+    pub overview_chart: ChartType,
+    pub message_chart: ChartType,
+    pub user_chart: ChartType,
+    /// Name of every series that is shown in the Message Chart
+    pub message_series: Vec<String>,
+    pub window_size: usize,
+    /// Words and phrases that are counted in Common Words
+    pub word_filter: WordFilter,
+    /// Phrases that are plotted over time in Common Words
+    pub tracked_phrases: Vec<String>,
+}
+
+impl Default for GuildUiState {
+    fn default() -> Self {
+        Self {
+            selected_channels: Vec::new(),
+            all_channels: false,
+            date_range: None,
+            overview_chart: ChartType::default(),
+            message_chart: ChartType::default(),
+            user_chart: ChartType::default(),
+            message_series: vec!["All Messages".to_string(), "Deleted Messages".to_string()],
+            window_size: 1,
+            word_filter: WordFilter::default(),
+            tracked_phrases: Vec::new(),
+        }
+    }
+}
+
+impl MainWindow {
+    /// The current UI state including the saved state of guilds that are not loaded
+    #[must_use]
+    pub fn ui_state(&self) -> UiState {
+        let mut state = self.saved_ui_state.clone();
+        self.panels.save_ui_state(&mut state);
+
+        for (guild_id, _) in self.panels.guild_channel_list() {
+            let guild_state = state.guilds.entry(guild_id).or_default();
+            self.panels.save_guild_ui_state(guild_id, guild_state);
+            self.tabs.save_guild_ui_state(guild_id, guild_state);
+        }
+        state
+    }
+
+    /// Apply the saved state of every loaded guild and select the guild that was open last
+    pub fn restore_guild_ui_states(&mut self) {
+        for (guild_id, _) in self.panels.guild_channel_list() {
+            let Some(guild_state) = self.saved_ui_state.guilds.get(&guild_id) else {
+                continue;
+            };
+            self.panels.restore_guild_ui_state(guild_id, guild_state);
+            self.tabs.restore_guild_ui_state(guild_id, guild_state);
+        }
+
+        if let Some(guild_id) = self.saved_ui_state.selected_guild {
+            self.panels.select_guild(guild_id);
+        }
+    }
+}
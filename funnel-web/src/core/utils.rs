@@ -1,4 +1,6 @@
+use eframe::egui::ahash::HashSet;
 use eframe::egui::{Context, FontData, FontDefinitions, FontFamily, Id, RichText, Ui};
+use funnel_shared::Channel;
 use serde::{Deserialize, Serialize};
 use std::fmt::Display;
 use std::sync::Arc;
@@ -54,6 +56,7 @@ impl CardData {
             CardType::MemberCount => String::from("Member Count"),
             CardType::MemberJoin => String::from("Member Joins"),
             CardType::MemberLeave => String::from("Member Leaves"),
+            CardType::JoinedPosted => String::from("Joined and Posted"),
         };
         let mut hover_text = match card_type {
             CardType::TotalMessage => {
@@ -74,6 +77,11 @@ impl CardData {
             CardType::MemberLeave => {
                 format!("The number of member leaves within the selected date: {number}")
             }
+            CardType::JoinedPosted => {
+                format!(
+                    "The number of new members within the selected date that sent a message after joining: {number}"
+                )
+            }
         };
 
         let content_text = ui.ctx().animate_value_with_time(id, number as f32, 1.0) as u32;
@@ -108,6 +116,9 @@ impl CardData {
                         "\nThe number of members leaves within the compare date: {compare_with}"
                     )
                 }
+                CardType::JoinedPosted => format!(
+                    "\nThe number of new members within the compare date that sent a message after joining: {compare_with}"
+                ),
             };
 
             hover_text += &compare_hover_text;
@@ -129,6 +140,7 @@ pub enum CardType {
     MemberCount,
     MemberJoin,
     MemberLeave,
+    JoinedPosted,
 }
 
 impl ChangeLog {
@@ -259,6 +271,21 @@ pub fn get_stripped_windows(content: Vec<&str>, window_size: usize) -> Vec<Strin
     valid_windows
 }
 
+/// IDs of the selected channels. Index 0 of the selection is every channel. Every channel if
+/// nothing is selected
+#[must_use]
+pub fn selected_channel_ids(channels: &[Channel], selected: &HashSet<usize>) -> HashSet<i64> {
+    if selected.is_empty() || selected.contains(&0) {
+        return channels.iter().map(|channel| channel.channel_id).collect();
+    }
+
+    selected
+        .iter()
+        .filter_map(|index| channels.get(index - 1))
+        .map(|channel| channel.channel_id)
+        .collect()
+}
+
 #[derive(Serialize, Deserialize)]
 struct StringSession {
     id: String,
@@ -343,7 +370,7 @@ pub fn delete_session() {
 }
 
 #[cfg(not(target_arch = "wasm32"))]
-fn get_target_path() -> Option<PathBuf> {
+pub fn get_target_path() -> Option<PathBuf> {
     if let Some(mut path) = data_local_dir() {
         path.push("Funnel");
         if let Err(e) = fs::create_dir_all(&path) {
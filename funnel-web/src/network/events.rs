@@ -1,6 +1,6 @@
 use eframe::egui::Context;
-use ewebsock::{WsEvent, WsMessage};
-use funnel_shared::{Request, WsResponse};
+use ewebsock::{Options, WsEvent, WsMessage};
+use funnel_shared::{Request, WireFormat, WsResponse};
 use log::{error, info};
 
 use crate::AppStatus;
@@ -14,20 +14,19 @@ impl MainWindow {
                 match event {
                     WsEvent::Closed => {
                         info!("Connection to websocket has been closed");
-                        self.remove_channels();
-                        self.connection.failed_connection();
-                        self.panels.set_app_status(AppStatus::FailedWs(
+                        self.connection_lost(
                             "The websocket connection was closed".to_string(),
-                        ));
+                            ctx,
+                        );
                     }
                     WsEvent::Error(e) => {
                         error!("Error in websocket. Reason: {e}");
-                        self.panels.set_app_status(AppStatus::FailedWs(e));
-                        self.remove_channels();
-                        self.connection.failed_connection();
+                        self.connection_lost(e, ctx);
                     }
                     WsEvent::Opened => {
                         info!("Connection to WS has been opened");
+                        self.send_ws(Request::set_wire_format(WireFormat::supported()));
+
                         let no_login = self.connection.no_login();
                         if no_login {
                             self.send_ws(Request::start_no_login());
@@ -36,29 +35,113 @@ impl MainWindow {
                         }
                     }
                     WsEvent::Message(message) => {
-                        if let WsMessage::Text(text) = message {
-                            self.panels.next_dot();
-                            let response = WsResponse::from_json(&text);
-
-                            if let Err(e) = response {
-                                error!(
-                                    "Failed to serialize message. Reason: {e}. Message gotten: {text}"
-                                );
+                        let response = match message {
+                            WsMessage::Text(text) => {
+                                WsResponse::from_json(&text).map_err(|e| {
+                                    format!("Failed to serialize message. Reason: {e}. Message gotten: {text}")
+                                })
+                            }
+                            WsMessage::Binary(data) => WsResponse::from_binary(&data)
+                                .map_err(|e| format!("Failed to decode binary message. Reason: {e}")),
+                            _ => {
+                                let message_text = format!("{message:?}");
+                                if !message_text.starts_with("Ping") {
+                                    error!("Unknown response gotten from server: {message:#?}");
+                                }
                                 return;
                             }
+                        };
 
-                            if let Some(reply) = handle_ws_message(self, response.unwrap(), ctx) {
-                                self.send_ws(reply);
-                            }
-                        } else {
-                            let message_text = format!("{message:?}");
-                            if !message_text.starts_with("Ping") {
-                                error!("Unknown response gotten from server: {message:#?}");
+                        self.panels.next_dot();
+                        match response {
+                            Ok(response) => {
+                                if let Some(reply) = handle_ws_message(self, response, ctx) {
+                                    self.send_ws(reply);
+                                }
                             }
+                            Err(e) => error!("{e}"),
                         }
                     }
                 }
             }
         }
     }
+
+    /// Reload the tabs and save the cache of the guilds with live updates once they are due
+    pub fn check_live_updates(&mut self, ctx: &Context) {
+        let now = ctx.input(|i| i.time);
+        for guild_id in self.live_updates.due_reloads(now) {
+            self.event_bus.publish_reloads(guild_id);
+        }
+        for guild_id in self.live_updates.due_saves(now) {
+            self.save_guild_cache(guild_id);
+        }
+    }
+
+    /// Write the next queued part of the guild caches so a large save is spread over frames
+    pub fn check_cache_writes(&mut self) {
+        if self.cache.write_next(&self.tabs.message_store).is_some() {
+            self.panels.set_app_status(AppStatus::FailedCache);
+        }
+    }
+
+    /// Start the connection again if a reconnect attempt is due
+    pub fn check_reconnect(&mut self, ctx: &Context) {
+        let now = ctx.input(|i| i.time);
+        if self.reconnect.is_due(now) {
+            info!(
+                "Attempting to reconnect to the websocket. Attempt {}",
+                self.reconnect.attempt()
+            );
+            self.connect_ws(ctx);
+        }
+    }
+
+    /// Schedule a reconnect if the session was already established, otherwise fail the connection
+    pub fn connection_lost(&mut self, reason: String, ctx: &Context) {
+        self.remove_channels();
+
+        if self.connection.connected() {
+            let now = ctx.input(|i| i.time);
+            if let Some(delay) = self.reconnect.schedule(now) {
+                info!("Reconnecting to the websocket in {delay} seconds");
+                self.panels
+                    .set_app_status(AppStatus::Reconnecting(self.reconnect.attempt(), delay));
+                return;
+            }
+            error!(
+                "Failed to reconnect after {} attempts",
+                self.reconnect.attempt()
+            );
+        }
+
+        self.reconnect.reset();
+        self.connection.failed_connection();
+        self.panels.set_app_status(AppStatus::FailedWs(reason));
+    }
+
+    /// Open a new websocket connection to the selected server
+    pub fn connect_ws(&mut self, ctx: &Context) {
+        if !self.reconnect.is_resuming() {
+            self.panels.set_app_status(AppStatus::ConnectingToWs);
+        }
+
+        // Every new connection starts with JSON and page requests until a format is negotiated
+        self.wire_format = WireFormat::default();
+        self.cursor_requests = false;
+
+        let options = Options::default();
+        let ws_url = &self.connection.server().ws_url;
+        info!("Connecting to {ws_url}");
+        let result = ewebsock::connect(ws_url, options);
+        match result {
+            Ok((sender, receiver)) => {
+                self.set_channels(sender, receiver);
+            }
+            Err(e) => {
+                error!("Failed to connect to WS. Reason: {e}");
+                self.connection_lost(e, ctx);
+            }
+        }
+    }
 }
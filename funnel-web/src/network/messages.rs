@@ -1,14 +1,14 @@
 use eframe::egui::{Context, OpenUrl};
-use funnel_shared::{ErrorType, PAGE_VALUE, Request, Response, WsResponse};
+use funnel_shared::{
+    ErrorType, PAGE_VALUE, Request, Response, Status, WsResponse, expand_messages,
+};
 use log::{error, info};
 
 use crate::{
-    AppEvent, AppStatus, FetchStatus, MainWindow, delete_session, get_session, save_session,
+    AppEvent, AppStatus, DataCache, FetchStatus, MainWindow, delete_session, get_session,
+    save_session,
 };
 
-const LOGIN_URL: &str = "https://discord.com/oauth2/authorize?client_id=1324028221066576017&response_type=code&redirect_uri=https%3A%2F%2Ffunnel-jyz9.shuttle.app%2Fauth%2Fredirect%2F&scope=identify+guilds";
-// const LOGIN_URL: &str = "https://discord.com/oauth2/authorize?client_id=1324028221066576017&response_type=code&redirect_uri=http%3A%2F%2Flocalhost%3A8000%2Fauth%2Fredirect%2F&scope=guilds+identify";
-
 pub fn handle_ws_message(
     window: &mut MainWindow,
     response: WsResponse,
@@ -33,7 +33,7 @@ pub fn handle_ws_message(
                     window.send_ws(Request::session(token));
                 } else {
                     info!("Opening auth url in a new tab");
-                    let full_url = format!("{LOGIN_URL}&state={conn_id}");
+                    let full_url = window.connection.server().login_url(conn_id);
 
                     let open_url = OpenUrl {
                         url: full_url,
@@ -46,12 +46,32 @@ pub fn handle_ws_message(
             }
         }
         Response::Guilds(guilds) => {
+            let resuming = window.reconnect.is_resuming();
+            window.reconnect.reset();
             window.connection.set_connected();
             window.panels.set_app_status(AppStatus::Fetching);
 
+            if resuming && window.panels.has_same_guilds(&guilds) {
+                info!("Connection recreated. Resuming the previous fetch");
+                window.resume_guild_data();
+                return None;
+            }
+
+            // Keep the state of the current guilds so it can be restored on the new list
+            window.saved_ui_state = window.ui_state();
+
             for guild in &guilds {
                 let guild_id = guild.guild.guild_id;
                 window.tabs.set_data(guild_id);
+
+                let fetch_status = window.panels.guild_status_m(guild_id);
+                if !fetch_status.no_partial() {
+                    info!("Partial fetch status found. Resetting fetch status for {guild_id}");
+                    window.tabs.clear_key_data(guild_id);
+                    window.cache.unload_guild(guild_id);
+                    *fetch_status = FetchStatus::default();
+                }
+
                 window
                     .tabs
                     .set_overview_channel_map(guild.guild.guild_id, guild.channels.clone());
@@ -60,176 +80,131 @@ pub fn handle_ws_message(
                     .set_channel_table_channel_map(guild.guild.guild_id, guild.channels.clone());
             }
             window.panels.set_guild_channels(guilds);
-
-            let guild_id = window.panels.selected_guild();
-            let fetch_status = window.panels.current_guild_status_m();
-            let no_partial = fetch_status.no_partial();
-
-            if !no_partial {
-                info!("Partial fetch status found. Resetting fetch status for {guild_id}");
-                window.tabs.clear_key_data(guild_id);
-                *fetch_status = FetchStatus::default();
-            }
+            window.restore_guild_ui_states();
 
             window.event_bus.publish(AppEvent::GuildChanged);
         }
         Response::Messages { guild_id, messages } => {
-            let current_page = response.status.page();
-            window
-                .panels
-                .current_guild_status_m()
-                .set_messages_page(current_page);
-
-            if messages.is_empty() {
-                window.panels.current_guild_status_m().messages_done();
-                window.to_set_idle();
-
-                window
-                    .event_bus
-                    .publish_if_needed(AppEvent::UserTableNeedsReload(guild_id));
-                window
-                    .event_bus
-                    .publish_if_needed(AppEvent::ChannelTableNeedsReload(guild_id));
-                window
-                    .event_bus
-                    .publish_if_needed(AppEvent::OverviewNeedsReload(guild_id));
+            // Live batches also carry edits and deletions which replace the stored messages
+            if response.status.is_live() {
                 window
-                    .event_bus
-                    .publish_if_needed(AppEvent::MessageChartNeedsReload(guild_id));
-                window
-                    .event_bus
-                    .publish_if_needed(AppEvent::UserChartNeedsReload(guild_id));
-                window
-                    .event_bus
-                    .publish_if_needed(AppEvent::WordTableNeedsReload(guild_id));
+                    .tabs
+                    .handle_messages(&messages, &mut window.event_bus);
+                window.live_updates.add(guild_id, ctx.input(|i| i.time));
                 return None;
             }
 
-            let do_new_page = messages.len() as u64 == PAGE_VALUE;
-
-            if do_new_page {
-                window.send_ws(Request::get_messages(guild_id, current_page + 1));
+            let has_more = has_more(&response.status, messages.len());
+            let fetch_status = window.panels.guild_status_m(guild_id);
+            if let Status::Success { current_page } = response.status {
+                fetch_status.set_messages_page(current_page);
+                window.cursor_requests = false;
+            } else {
+                fetch_status.set_messages_cursor(response.status.next_cursor());
             }
-            for message in messages {
-                window
-                    .tabs
-                    .handle_message_user_table(&message, &mut window.event_bus);
-                window
-                    .tabs
-                    .handle_message_channel_table(&message, &mut window.event_bus);
-                window
-                    .tabs
-                    .handle_message_overview(&message, &mut window.event_bus);
-                window
-                    .tabs
-                    .handle_message_message_chart(&message, &mut window.event_bus);
-                window
-                    .tabs
-                    .handle_message_user_chart(&message, &mut window.event_bus);
-                window
-                    .tabs
-                    .handle_message_word_table(&message, &mut window.event_bus);
+
+            if has_more {
+                window.request_messages(guild_id);
             }
 
-            if !do_new_page {
-                window.panels.current_guild_status_m().messages_done();
-                window.to_set_idle();
+            window
+                .tabs
+                .handle_messages(&messages, &mut window.event_bus);
 
-                window
-                    .event_bus
-                    .publish_if_needed(AppEvent::OverviewNeedsReload(guild_id));
-                window
-                    .event_bus
-                    .publish_if_needed(AppEvent::UserTableNeedsReload(guild_id));
-                window
-                    .event_bus
-                    .publish_if_needed(AppEvent::ChannelTableNeedsReload(guild_id));
-                window
-                    .event_bus
-                    .publish_if_needed(AppEvent::MessageChartNeedsReload(guild_id));
-                window
-                    .event_bus
-                    .publish_if_needed(AppEvent::UserChartNeedsReload(guild_id));
-                window
-                    .event_bus
-                    .publish_if_needed(AppEvent::WordTableNeedsReload(guild_id));
+            if !has_more {
+                messages_completed(window, guild_id);
             }
         }
         Response::MemberCounts { guild_id, counts } => {
-            let current_page = response.status.page();
-            window
-                .panels
-                .current_guild_status_m()
-                .set_counts_page(current_page);
-
-            if counts.is_empty() {
-                window.panels.current_guild_status_m().counts_done();
-                window.to_set_idle();
-
-                return None;
-            }
-
-            let do_new_page = counts.len() as u64 == PAGE_VALUE;
-
-            if do_new_page {
-                window.send_ws(Request::get_member_counts(guild_id, current_page + 1));
-            }
-
-            for count in counts {
+            if response.status.is_live() {
+                let counts = window.cache.new_counts(guild_id, counts);
                 window
                     .tabs
-                    .handle_member_count(guild_id, count, &mut window.event_bus);
+                    .handle_member_counts(guild_id, &counts, &mut window.event_bus);
+                window.tabs.fill_member_activity(guild_id);
+                window.live_updates.add(guild_id, ctx.input(|i| i.time));
+                return None;
             }
 
-            window.tabs.clear_chart_labels(guild_id);
+            let has_more = has_more(&response.status, counts.len());
+            let fetch_status = window.panels.guild_status_m(guild_id);
+            let counts = if let Status::Success { current_page } = response.status {
+                fetch_status.set_counts_page(current_page);
+                window.cursor_requests = false;
+                window.cache.new_counts_page(guild_id, current_page, counts)
+            } else {
+                fetch_status.set_counts_cursor(response.status.next_cursor());
+                window.cache.new_counts(guild_id, counts)
+            };
 
-            if !do_new_page {
-                window.panels.current_guild_status_m().counts_done();
-                window.to_set_idle();
+            if has_more {
+                window.request_counts(guild_id);
+            }
 
-                window.tabs.fill_member_activity(guild_id);
+            window
+                .tabs
+                .handle_member_counts(guild_id, &counts, &mut window.event_bus);
 
-                if !window.panels.current_guild_status().activities() {
-                    window.send_ws(Request::get_member_activity(guild_id, 1));
-                }
+            if !has_more {
+                counts_completed(window, guild_id);
             }
         }
         Response::MemberActivities {
             guild_id,
             activities,
         } => {
-            let current_page = response.status.page();
-            window
-                .panels
-                .current_guild_status_m()
-                .set_activities_page(current_page);
-
-            if activities.is_empty() {
-                window.panels.current_guild_status_m().activities_done();
-                window.to_set_idle();
+            if response.status.is_live() {
+                let activities = window.cache.new_activities(guild_id, activities);
+                window
+                    .tabs
+                    .handle_member_activities(guild_id, &activities, &mut window.event_bus);
+                window.live_updates.add(guild_id, ctx.input(|i| i.time));
                 return None;
             }
 
-            let do_new_page = activities.len() as u64 == PAGE_VALUE;
-
-            if do_new_page {
-                let current_page = response.status.page();
-                window.send_ws(Request::get_member_counts(guild_id, current_page + 1));
-            }
-
-            for activity in activities {
+            let has_more = has_more(&response.status, activities.len());
+            let fetch_status = window.panels.guild_status_m(guild_id);
+            let activities = if let Status::Success { current_page } = response.status {
+                fetch_status.set_activities_page(current_page);
+                window.cursor_requests = false;
                 window
-                    .tabs
-                    .handle_member_activity(guild_id, activity, &mut window.event_bus);
+                    .cache
+                    .new_activities_page(guild_id, current_page, activities)
+            } else {
+                fetch_status.set_activities_cursor(response.status.next_cursor());
+                window.cache.new_activities(guild_id, activities)
+            };
+
+            if has_more {
+                window.request_activities(guild_id);
             }
 
-            window.tabs.clear_chart_labels(guild_id);
+            window
+                .tabs
+                .handle_member_activities(guild_id, &activities, &mut window.event_bus);
 
-            if !do_new_page {
-                window.panels.current_guild_status_m().activities_done();
-                window.to_set_idle();
+            if !has_more {
+                activities_completed(window, guild_id);
             }
         }
+        Response::CompactMessages {
+            guild_id,
+            messages,
+            users,
+        } => {
+            let messages = expand_messages(messages, users);
+            let response = WsResponse {
+                status: response.status,
+                response: Response::Messages { guild_id, messages },
+            };
+            return handle_ws_message(window, response, ctx);
+        }
+        Response::WireFormat(format) => {
+            info!("Server is using the {format:?} wire format");
+            window.wire_format = format;
+            // Servers that negotiate the wire format also accept the cursor requests
+            window.cursor_requests = true;
+        }
         Response::UserDetails(user_details) => {
             window.panels.set_user_details(user_details);
         }
@@ -237,15 +212,69 @@ pub fn handle_ws_message(
             save_session(id);
         }
         Response::LoggedOut => {
-            window.reset_all();
+            window.reset_all(ctx);
             window.panels.set_app_status(AppStatus::LoggedOut);
             delete_session();
+            DataCache::clear_storage();
         }
         Response::Error(_) => unreachable!(),
     }
     None
 }
 
+/// Whether more records follow the batch. A page of a server without cursor support is only
+/// full when more records follow
+fn has_more(status: &Status, received: usize) -> bool {
+    match status {
+        Status::Success { .. } => received as u64 == PAGE_VALUE,
+        status => status.has_more(),
+    }
+}
+
+fn messages_completed(window: &mut MainWindow, guild_id: i64) {
+    window.panels.guild_status_m(guild_id).messages_done();
+    window.to_set_idle();
+
+    window.event_bus.publish_reloads(guild_id);
+    subscribe_if_done(window, guild_id);
+}
+
+fn counts_completed(window: &mut MainWindow, guild_id: i64) {
+    window.panels.guild_status_m(guild_id).counts_done();
+    window.to_set_idle();
+
+    window.tabs.fill_member_activity(guild_id);
+
+    if !window.panels.guild_status_m(guild_id).activities() {
+        window.request_activities(guild_id);
+    }
+}
+
+fn activities_completed(window: &mut MainWindow, guild_id: i64) {
+    window.panels.guild_status_m(guild_id).activities_done();
+    window.to_set_idle();
+
+    for event in [
+        AppEvent::OverviewNeedsReload(guild_id),
+        AppEvent::UserTableNeedsReload(guild_id),
+        AppEvent::CohortsNeedsReload(guild_id),
+    ] {
+        window.event_bus.publish_if_needed(event);
+    }
+    subscribe_if_done(window, guild_id);
+}
+
+/// Save the cache and start receiving live updates once everything of the guild has been fetched
+fn subscribe_if_done(window: &mut MainWindow, guild_id: i64) {
+    let fetch_status = window.panels.guild_status_m(guild_id);
+    if fetch_status.all_done() && !fetch_status.live() {
+        info!("Subscribing to live updates of {guild_id}");
+        fetch_status.set_live();
+        window.send_ws(Request::subscribe(guild_id));
+        window.save_guild_cache(guild_id);
+    }
+}
+
 fn handle_errors(window: &mut MainWindow, error: ErrorType, ctx: &Context) {
     match error {
         ErrorType::ClientNotConnected => {
@@ -289,7 +318,7 @@ fn handle_errors(window: &mut MainWindow, error: ErrorType, ctx: &Context) {
             error!("Session is invalid. Opening discord auth");
             delete_session();
 
-            let full_url = format!("{LOGIN_URL}&state={}", window.conn_id);
+            let full_url = window.connection.server().login_url(window.conn_id);
 
             let open_url = OpenUrl {
                 url: full_url,
@@ -14,6 +14,10 @@ pub struct DateHandler {
     start: Option<NaiveDate>,
     /// The newest date with at least 1 data point
     end: Option<NaiveDate>,
+    /// Whether From and To were picked instead of following the oldest and the newest date
+    custom_range: bool,
+    /// Whether From and To were picked in the UI instead of restored. Only a picked range is saved
+    picked_range: bool,
 }
 
 impl DateHandler {
@@ -32,6 +36,8 @@ impl DateHandler {
                 }
 
                 self.last_from = Some(self.from);
+                self.custom_range = true;
+                self.picked_range = true;
                 return true;
             }
         }
@@ -42,6 +48,8 @@ impl DateHandler {
                 }
 
                 self.last_to = Some(self.to);
+                self.custom_range = true;
+                self.picked_range = true;
                 return true;
             }
         }
@@ -54,23 +62,45 @@ impl DateHandler {
         self.to = self.end.unwrap_or_default();
         self.last_from = Some(self.from);
         self.last_to = Some(self.to);
+        self.custom_range = false;
+        self.picked_range = false;
+    }
+
+    /// Select a From and To date that was picked in an earlier session. New data no longer moves
+    /// them but they are not saved again unless they are picked once more
+    pub fn restore_range(&mut self, from: NaiveDate, to: NaiveDate) {
+        self.from = from;
+        self.to = to;
+        self.last_from = Some(from);
+        self.last_to = Some(to);
+        self.custom_range = true;
+        self.picked_range = false;
+    }
+
+    /// The From and To date if they were picked in the UI instead of following the data
+    pub fn picked_range(&self) -> Option<(NaiveDate, NaiveDate)> {
+        self.picked_range.then_some((self.from, self.to))
     }
 
     /// Compare the given date with the current Start and End date
-    /// to find the oldest and the newest date
+    /// to find the oldest and the newest date. A picked range stays as it is
     pub fn update_dates(&mut self, date: NaiveDate) -> bool {
         let mut needs_update = false;
         if self.start.is_none_or(|current| current > date) {
-            self.from = date;
             self.start = Some(date);
-            self.last_from = Some(date);
+            if !self.custom_range {
+                self.from = date;
+                self.last_from = Some(date);
+            }
             needs_update = true;
         }
 
         if self.end.is_none_or(|current_date| current_date < date) {
-            self.to = date;
             self.end = Some(date);
-            self.last_to = Some(date);
+            if !self.custom_range {
+                self.to = date;
+                self.last_to = Some(date);
+            }
             needs_update = true;
         }
         needs_update
@@ -81,6 +111,12 @@ impl DateHandler {
         date >= self.from && date <= self.to
     }
 
+    /// Every date from the current From date to the current To date
+    pub fn range_dates(&self) -> impl Iterator<Item = NaiveDate> {
+        let to = self.to;
+        self.from.iter_days().take_while(move |date| *date <= to)
+    }
+
     /// Whether the given date is before the current To range
     pub fn before_to_range(&self, date: NaiveDate) -> bool {
         date < self.to
@@ -174,6 +174,10 @@ impl DateNavigator {
         &mut self.handler
     }
 
+    pub fn reset_handler(&mut self) {
+        self.handler = DateHandler::default();
+    }
+
     /// Handler and not mutable
     pub fn handler_i(&self) -> DateHandler {
         self.handler
@@ -2,16 +2,16 @@ use chrono::NaiveDate;
 use eframe::egui::ahash::{HashMap, HashSet, HashSetExt};
 use eframe::egui::scroll_area::ScrollSource;
 use eframe::egui::{
-    Align, CentralPanel, Context, CornerRadius, Image, ImageButton, Layout, MenuBar, ScrollArea,
-    SidePanel, Spinner, TopBottomPanel, Visuals,
+    Align, CentralPanel, ComboBox, Context, CornerRadius, Image, ImageButton, Layout, MenuBar,
+    ScrollArea, SidePanel, Spinner, TopBottomPanel, Visuals,
 };
 use egui_theme_lerp::ThemeAnimator;
 use funnel_shared::{Channel, GuildWithChannels, UserDetails};
 use strum::IntoEnumIterator;
 
-use crate::core::{FetchStatus, MainWindow, TabState};
+use crate::core::{FetchStatus, GuildUiState, MainWindow, TabState, UiState};
 use crate::ui::{AnimatedLabel, AnimatedMenuLabel, DateHandler, DateNavigator};
-use crate::{AppEvent, AppStatus, EventBus};
+use crate::{AppEvent, AppStatus, EventBus, ExportFormat, Timezone};
 
 pub struct PanelStatus {
     tab_state: TabState,
@@ -30,6 +30,7 @@ pub struct PanelStatus {
     theme_animator: ThemeAnimator,
     fetch_status: HashMap<i64, FetchStatus>,
     user_details: Option<UserDetails>,
+    timezone: Timezone,
 }
 
 impl Default for PanelStatus {
@@ -51,6 +52,7 @@ impl Default for PanelStatus {
             theme_animator: ThemeAnimator::new(Visuals::light(), Visuals::dark()),
             fetch_status: HashMap::default(),
             user_details: None,
+            timezone: Timezone::default(),
         }
     }
 }
@@ -94,6 +96,47 @@ impl PanelStatus {
                 }
                 ui.separator();
                 self.date_nav[self.selected_guild].show_ui(ui, connected, event_bus);
+                ui.separator();
+
+                ui.add_enabled_ui(connected, |ui| {
+                    ui.label("Timezone:");
+                    ComboBox::from_id_salt("timezone_selector")
+                        .selected_text(self.timezone.to_string())
+                        .show_ui(ui, |ui| {
+                            for timezone in Timezone::options() {
+                                if ui
+                                    .selectable_value(
+                                        &mut self.timezone,
+                                        timezone,
+                                        timezone.to_string(),
+                                    )
+                                    .changed()
+                                {
+                                    event_bus.publish(AppEvent::TimezoneChanged);
+                                }
+                            }
+                        })
+                        .response
+                        .on_hover_text(
+                            "The timezone the dates and hours of every tab are shown in",
+                        );
+                });
+
+                if connected {
+                    ui.separator();
+                    ui.menu_button("Export", |ui| {
+                        for format in ExportFormat::iter() {
+                            if ui
+                                .button(format!("Export as {format}"))
+                                .on_hover_text("Save the data shown in the current tab")
+                                .clicked()
+                            {
+                                event_bus.publish(AppEvent::ExportTab(format));
+                                ui.close();
+                            }
+                        }
+                    });
+                }
 
                 if let Some(details) = self.user_details.as_ref() {
                     ui.separator();
@@ -413,6 +456,11 @@ impl PanelStatus {
         });
     }
 
+    #[must_use]
+    pub fn tab_state(&self) -> TabState {
+        self.tab_state
+    }
+
     pub fn set_app_status(&mut self, status: AppStatus) {
         self.app_status = status;
     }
@@ -455,6 +503,29 @@ impl PanelStatus {
         self.date_nav[target_index].handler_i()
     }
 
+    /// Forget the date range of the guild so it gets rebuilt from the data
+    pub fn reset_date_handler(&mut self, guild_id: i64) {
+        let target_index = self
+            .guild_channels
+            .iter()
+            .position(|g| g.guild.guild_id == guild_id)
+            .unwrap();
+        self.date_nav[target_index].reset_handler();
+    }
+
+    #[must_use]
+    pub fn timezone(&self) -> Timezone {
+        self.timezone
+    }
+
+    /// ID and channels of every guild
+    pub fn guild_channel_list(&self) -> Vec<(i64, Vec<Channel>)> {
+        self.guild_channels
+            .iter()
+            .map(|g| (g.guild.guild_id, g.channels.clone()))
+            .collect()
+    }
+
     pub fn selected_guild(&self) -> i64 {
         let index = self.selected_guild;
         self.guild_channels[index].guild.guild_id
@@ -489,6 +560,35 @@ impl PanelStatus {
         self.fetch_status.get_mut(&self.selected_guild()).unwrap()
     }
 
+    pub fn guild_status_m(&mut self, guild_id: i64) -> &mut FetchStatus {
+        self.fetch_status.entry(guild_id).or_default()
+    }
+
+    pub fn guild_statuses(&self) -> Vec<(i64, FetchStatus)> {
+        self.guild_channels
+            .iter()
+            .map(|g| {
+                let guild_id = g.guild.guild_id;
+                let status = self
+                    .fetch_status
+                    .get(&guild_id)
+                    .cloned()
+                    .unwrap_or_default();
+                (guild_id, status)
+            })
+            .collect()
+    }
+
+    /// Whether the given guild list contains the exact same guilds that are currently loaded
+    pub fn has_same_guilds(&self, list: &[GuildWithChannels]) -> bool {
+        self.guild_channels.len() == list.len()
+            && self
+                .guild_channels
+                .iter()
+                .zip(list)
+                .all(|(a, b)| a.guild.guild_id == b.guild.guild_id)
+    }
+
     pub fn set_user_details(&mut self, user_details: UserDetails) {
         self.user_details = Some(user_details);
     }
@@ -496,6 +596,85 @@ impl PanelStatus {
     pub fn has_user_details(&self) -> bool {
         self.user_details.is_some()
     }
+
+    fn guild_index(&self, guild_id: i64) -> Option<usize> {
+        self.guild_channels
+            .iter()
+            .position(|g| g.guild.guild_id == guild_id)
+    }
+
+    /// Select the guild if it is in the guild list
+    pub fn select_guild(&mut self, guild_id: i64) {
+        if let Some(index) = self.guild_index(guild_id) {
+            self.selected_guild = index;
+        }
+    }
+
+    pub fn save_ui_state(&self, state: &mut UiState) {
+        state.tab_state = self.tab_state;
+        state.dark_theme = !self.theme_animator.theme_1_to_2;
+        state.show_guild = self.show_guild;
+        state.show_channel = self.show_channel;
+        state.timezone = self.timezone;
+        if !self.guild_channels.is_empty() {
+            state.selected_guild = Some(self.selected_guild());
+        }
+    }
+
+    pub fn restore_ui_state(&mut self, ctx: &Context, state: &UiState) {
+        self.tab_state = state.tab_state;
+        self.show_guild = state.show_guild;
+        self.show_channel = state.show_channel;
+        self.timezone = state.timezone;
+        if state.dark_theme {
+            self.theme_animator.theme_1_to_2 = false;
+            ctx.set_visuals(Visuals::dark());
+        }
+    }
+
+    pub fn save_guild_ui_state(&self, guild_id: i64, state: &mut GuildUiState) {
+        let Some(index) = self.guild_index(guild_id) else {
+            return;
+        };
+        let channels = &self.guild_channels[index].channels;
+        let selected = &self.selected_channel[index];
+
+        // Index 0 is All Channels and the rest follow the channel list
+        state.all_channels = selected.contains(&0);
+        state.selected_channels = channels
+            .iter()
+            .enumerate()
+            .filter(|(channel_index, _)| selected.contains(&(channel_index + 1)))
+            .map(|(_, channel)| channel.channel_id)
+            .collect();
+        state.date_range = self.date_nav[index].handler_i().picked_range();
+    }
+
+    /// Select the saved channels and dates of the guild. Channels that no longer exist are skipped
+    pub fn restore_guild_ui_state(&mut self, guild_id: i64, state: &GuildUiState) {
+        let Some(index) = self.guild_index(guild_id) else {
+            return;
+        };
+        let channels = &self.guild_channels[index].channels;
+
+        let mut selected = HashSet::new();
+        if state.all_channels {
+            selected.insert(0);
+        } else {
+            for (channel_index, channel) in channels.iter().enumerate() {
+                if state.selected_channels.contains(&channel.channel_id) {
+                    selected.insert(channel_index + 1);
+                }
+            }
+        }
+        self.selected_channel[index] = selected;
+
+        // A range restored earlier follows the data again once it is no longer saved
+        match state.date_range {
+            Some((from, to)) => self.date_nav[index].handler().restore_range(from, to),
+            None => self.date_nav[index].handler().reset_dates(),
+        }
+    }
 }
 
 impl MainWindow {
@@ -524,5 +703,10 @@ impl MainWindow {
                 self.connection.show_start_ui(ui, &mut self.event_bus);
             }
         });
+
+        if self.connection.connected() {
+            self.tabs.show_user_profile(ctx, &mut self.event_bus);
+            self.tabs.show_channel_profile(ctx, &mut self.event_bus);
+        }
     }
 }
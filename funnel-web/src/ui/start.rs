@@ -1,19 +1,43 @@
 use eframe::egui::{
-    Button, ScrollArea, TextEdit, TopBottomPanel, Ui, Vec2, scroll_area::ScrollSource,
+    Button, ComboBox, Grid, ScrollArea, TextEdit, TopBottomPanel, Ui, Vec2,
+    scroll_area::ScrollSource,
 };
 
-use crate::{AppEvent, EventBus, get_change_log};
+use crate::{AppEvent, EventBus, ServerList, ServerProfile, get_change_log};
 
-#[derive(Default)]
 pub struct Connection {
     connected: bool,
     connecting: bool,
     space_taken: f32,
     button_space_taken: f32,
+    server_space_taken: f32,
     no_login: bool,
+    servers: ServerList,
+    show_add_server: bool,
+    new_server: ServerProfile,
+}
+
+impl Default for Connection {
+    fn default() -> Self {
+        Self {
+            connected: false,
+            connecting: false,
+            space_taken: 0.0,
+            button_space_taken: 0.0,
+            server_space_taken: 0.0,
+            no_login: false,
+            servers: ServerList::load(),
+            show_add_server: false,
+            new_server: ServerProfile::from_ws_url("", "ws://localhost:8000/ws"),
+        }
+    }
 }
 
 impl Connection {
+    pub fn server(&self) -> &ServerProfile {
+        self.servers.current()
+    }
+
     pub fn no_login(&self) -> bool {
         self.no_login
     }
@@ -74,10 +98,127 @@ impl Connection {
         clicked
     }
 
+    fn add_server_selection(&mut self, ui: &mut Ui, event_bus: &mut EventBus) {
+        ui.add_space(10.0);
+
+        ui.horizontal(|ui| {
+            let spacing_size = ui.available_width() - self.server_space_taken;
+            let spacing_size = ui.painter().round_to_pixel_center(spacing_size / 2.0);
+            if spacing_size > 0.0 {
+                ui.add_space(spacing_size);
+            }
+
+            let max_width = ui.available_width();
+
+            ui.label("Server:");
+
+            let mut selected = self.servers.selected();
+            let server_names: Vec<String> = self
+                .servers
+                .profiles()
+                .iter()
+                .map(|p| p.name.clone())
+                .collect();
+
+            ui.add_enabled_ui(!self.connecting, |ui| {
+                ComboBox::from_id_salt("server_selection")
+                    .width(150.0)
+                    .show_index(ui, &mut selected, server_names.len(), |i| {
+                        server_names[i].clone()
+                    })
+                    .on_hover_text("Select the Funnel server to connect to");
+            });
+
+            if selected != self.servers.selected() {
+                self.servers.select(selected);
+                event_bus.publish(AppEvent::ServerChanged);
+            }
+
+            ui.label(&self.server().ws_url);
+
+            if ui
+                .add_enabled(!self.connecting, Button::new("Add Server"))
+                .on_hover_text("Save a new server profile")
+                .clicked()
+            {
+                self.show_add_server = !self.show_add_server;
+            }
+
+            let can_remove = self.servers.profiles().len() > 1;
+            if ui
+                .add_enabled(!self.connecting && can_remove, Button::new("Remove"))
+                .on_hover_text("Remove the selected server profile")
+                .clicked()
+            {
+                self.servers.remove_profile(selected);
+                event_bus.publish(AppEvent::ServerChanged);
+            }
+
+            let consumed = max_width - ui.available_width();
+            self.server_space_taken = consumed;
+        });
+
+        if self.show_add_server {
+            ui.add_space(5.0);
+            ui.vertical_centered(|ui| {
+                Grid::new("add_server_grid")
+                    .num_columns(2)
+                    .spacing([10.0, 5.0])
+                    .show(ui, |ui| {
+                        ui.label("Name:");
+                        ui.add(
+                            TextEdit::singleline(&mut self.new_server.name).desired_width(300.0),
+                        );
+                        ui.end_row();
+
+                        ui.label("Websocket URL:");
+                        let ws_edit = ui.add(
+                            TextEdit::singleline(&mut self.new_server.ws_url)
+                                .hint_text("wss://example.com/ws")
+                                .desired_width(300.0),
+                        );
+                        if ws_edit.changed() {
+                            self.new_server.sync_redirect();
+                        }
+                        ui.end_row();
+
+                        ui.label("OAuth Redirect URL:");
+                        ui.add(
+                            TextEdit::singleline(&mut self.new_server.redirect_url)
+                                .hint_text("https://example.com/auth/redirect/")
+                                .desired_width(300.0),
+                        );
+                        ui.end_row();
+
+                        ui.label("Discord Client ID:");
+                        ui.add(
+                            TextEdit::singleline(&mut self.new_server.client_id)
+                                .desired_width(300.0),
+                        );
+                        ui.end_row();
+                    });
+
+                let valid = self.new_server.is_valid();
+                if ui
+                    .add_enabled(valid, Button::new("Save Server"))
+                    .on_hover_text(
+                        "The name cannot be empty and the URL must start with ws:// or wss://",
+                    )
+                    .clicked()
+                {
+                    let profile = self.new_server.clone();
+                    self.servers.add_profile(profile);
+                    self.show_add_server = false;
+                    event_bus.publish(AppEvent::ServerChanged);
+                }
+            });
+        }
+    }
+
     fn add_info_text(&mut self, ui: &mut Ui) {
         ui.add_space(20.0);
 
-        let mut text_edit_text = "https://discord.com/oauth2/authorize?client_id=1324028221066576017&permissions=66560&integration_type=0&scope=bot".to_string();
+        let mut text_edit_text = self.server().invite_url();
 
         ui.vertical_centered(|ui| {
             ui.label("Add this bot to your Discord server and run `/sync_all` to view analytics");
@@ -121,6 +262,7 @@ impl Connection {
             event_bus.publish(AppEvent::StartWebsocket);
             self.connecting = true;
         }
+        self.add_server_selection(ui, event_bus);
         self.add_info_text(ui);
 
         TopBottomPanel::bottom("change_log")
@@ -0,0 +1,273 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use eframe::egui::ahash::HashSet;
+use eframe::egui::{Align, Context, Grid, Id, Layout, RichText, ScrollArea, Ui, Window};
+
+use crate::ui::{DateCounts, TabHandler, show_count_grid, show_date_plot};
+use crate::{AppEvent, EventBus};
+
+/// Everything that is known about a single channel within the selected dates
+pub struct ChannelProfile {
+    guild_id: i64,
+    channel_id: i64,
+    name: String,
+    total_message: u32,
+    deleted_message: u32,
+    first_message: Option<NaiveDateTime>,
+    last_message: Option<NaiveDateTime>,
+    unique_users: HashSet<i64>,
+    /// Key: The date. Value: Non-deleted messages sent on that date
+    timeline: DateCounts,
+    deleted_timeline: DateCounts,
+    /// Key: The date. Value: Users that sent at least one message on that date
+    user_timeline: DateCounts,
+    top_posters: Vec<(String, u32)>,
+    phrase_size: usize,
+    phrases: Vec<(String, u32)>,
+}
+
+impl ChannelProfile {
+    #[must_use]
+    pub fn new(guild_id: i64, channel_id: i64, name: &str) -> Self {
+        Self {
+            guild_id,
+            channel_id,
+            name: name.to_string(),
+            total_message: 0,
+            deleted_message: 0,
+            first_message: None,
+            last_message: None,
+            unique_users: HashSet::default(),
+            timeline: DateCounts::new(),
+            deleted_timeline: DateCounts::new(),
+            user_timeline: DateCounts::new(),
+            top_posters: Vec::new(),
+            phrase_size: 1,
+            phrases: Vec::new(),
+        }
+    }
+
+    #[must_use]
+    pub fn guild_id(&self) -> i64 {
+        self.guild_id
+    }
+
+    #[must_use]
+    pub fn channel_id(&self) -> i64 {
+        self.channel_id
+    }
+
+    /// Add the messages and the users of the channel on a single date
+    pub fn add_date(
+        &mut self,
+        date: NaiveDate,
+        total_message: u32,
+        deleted_message: u32,
+        users: &HashSet<i64>,
+    ) {
+        self.total_message += total_message;
+        self.deleted_message += deleted_message;
+        self.unique_users.extend(users);
+        self.user_timeline.insert(date, users.len() as u32);
+    }
+
+    /// Update the first and last message time if the given time is outside of them
+    pub fn add_seen(&mut self, first_message: NaiveDateTime, last_message: NaiveDateTime) {
+        if self.first_message.is_none_or(|seen| seen > first_message) {
+            self.first_message = Some(first_message);
+        }
+        if self.last_message.is_none_or(|seen| seen < last_message) {
+            self.last_message = Some(last_message);
+        }
+    }
+
+    pub fn set_activity(
+        &mut self,
+        timeline: DateCounts,
+        deleted_timeline: DateCounts,
+        top_posters: Vec<(String, u32)>,
+    ) {
+        // Dates without any message are only in the timeline so fill them in as well
+        for date in timeline.keys() {
+            self.user_timeline.entry(*date).or_default();
+        }
+
+        self.timeline = timeline;
+        self.deleted_timeline = deleted_timeline;
+        self.top_posters = top_posters;
+    }
+
+    pub fn set_phrases(&mut self, phrase_size: usize, phrases: Vec<(String, u32)>) {
+        self.phrase_size = phrase_size;
+        self.phrases = phrases;
+    }
+
+    /// Percentage of the messages of this channel that were deleted
+    fn deleted_rate(&self) -> f64 {
+        let all_message = self.total_message + self.deleted_message;
+        if all_message == 0 {
+            return 0.0;
+        }
+        f64::from(self.deleted_message) / f64::from(all_message) * 100.0
+    }
+
+    /// Show the profile in a window. `open` is set to false once the window is closed
+    pub fn show_ui(&self, ctx: &Context, open: &mut bool, event_bus: &mut EventBus) {
+        Window::new(format!("#{} Profile", self.name))
+            .id(Id::new("channel_profile"))
+            .open(open)
+            .default_size([600.0, 700.0])
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.heading(format!("#{}", self.name));
+                    ui.separator();
+                    ui.label(format!("Channel ID: {}", self.channel_id));
+                    ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                        if ui
+                            .button("Refresh")
+                            .on_hover_text("Rebuild the profile with the latest data")
+                            .clicked()
+                        {
+                            event_bus.publish(AppEvent::OpenChannelProfile(
+                                self.guild_id,
+                                self.channel_id,
+                            ));
+                        }
+                    });
+                });
+                ui.separator();
+
+                ScrollArea::vertical().show(ui, |ui| {
+                    self.show_summary(ui);
+                    ui.separator();
+
+                    ui.label(RichText::new("Messages").strong());
+                    show_date_plot(
+                        ui,
+                        "channel_profile_messages",
+                        &[
+                            ("Messages", &self.timeline),
+                            ("Deleted Messages", &self.deleted_timeline),
+                        ],
+                    );
+                    ui.separator();
+
+                    ui.label(RichText::new("Unique Users").strong());
+                    show_date_plot(
+                        ui,
+                        "channel_profile_users",
+                        &[("Unique Users", &self.user_timeline)],
+                    );
+                    ui.separator();
+
+                    ui.label(RichText::new("Top Posters").strong());
+                    show_count_grid(
+                        ui,
+                        "channel_profile_posters",
+                        ["Username", "Total Message"],
+                        &self.top_posters,
+                        "No messages found",
+                    );
+                    ui.separator();
+
+                    ui.label(
+                        RichText::new(format!(
+                            "Common Phrases (Phrase Size: {})",
+                            self.phrase_size
+                        ))
+                        .strong(),
+                    );
+                    show_count_grid(
+                        ui,
+                        "channel_profile_phrases",
+                        ["Phrase", "Hits"],
+                        &self.phrases,
+                        "No phrases found",
+                    );
+                });
+            });
+    }
+
+    fn show_summary(&self, ui: &mut Ui) {
+        let seen_text = |seen: Option<NaiveDateTime>| {
+            seen.map_or_else(|| "-".to_string(), |seen| seen.to_string())
+        };
+
+        Grid::new("channel_profile_summary")
+            .num_columns(2)
+            .striped(true)
+            .show(ui, |ui| {
+                ui.label("Total Message");
+                ui.label(self.total_message.to_string());
+                ui.end_row();
+
+                ui.label("Deleted Message");
+                ui.label(self.deleted_message.to_string());
+                ui.end_row();
+
+                ui.label("Deleted Rate");
+                ui.label(format!("{:.2}%", self.deleted_rate()));
+                ui.end_row();
+
+                ui.label("Unique Users");
+                ui.label(self.unique_users.len().to_string());
+                ui.end_row();
+
+                ui.label("First Message Seen");
+                ui.label(seen_text(self.first_message));
+                ui.end_row();
+
+                ui.label("Last Message Seen");
+                ui.label(seen_text(self.last_message));
+                ui.end_row();
+            });
+    }
+}
+
+impl TabHandler {
+    /// Build the profile of the channel from the messages of the guild and show it
+    pub fn open_channel_profile(&mut self, guild_id: i64, channel_id: i64) {
+        let Some(store) = self.message_store.get(&guild_id) else {
+            return;
+        };
+        let Some(mut profile) = self
+            .channel_table
+            .get(&guild_id)
+            .and_then(|table| table.channel_profile(store, guild_id, channel_id))
+        else {
+            return;
+        };
+
+        if let Some(table) = self.word_table.get(&guild_id) {
+            let (phrase_size, phrases) = table.channel_phrases(store, channel_id);
+            profile.set_phrases(phrase_size, phrases);
+        }
+
+        self.channel_profile = Some(profile);
+    }
+
+    /// Build the open profile again so it follows the selected dates
+    pub fn reload_channel_profile(&mut self) {
+        if let Some(profile) = &self.channel_profile {
+            let guild_id = profile.guild_id();
+            let channel_id = profile.channel_id();
+            self.open_channel_profile(guild_id, channel_id);
+        }
+    }
+
+    pub fn show_channel_profile(&mut self, ctx: &Context, event_bus: &mut EventBus) {
+        let Some(profile) = &self.channel_profile else {
+            return;
+        };
+
+        if profile.guild_id() != self.current_guild {
+            return;
+        }
+
+        let mut open = true;
+        profile.show_ui(ctx, &mut open, event_bus);
+
+        if !open {
+            self.channel_profile = None;
+        }
+    }
+}
@@ -1,21 +1,25 @@
-use chrono::{DateTime, Local, NaiveDate, NaiveDateTime};
+use chrono::{NaiveDate, NaiveDateTime};
 use eframe::egui::ahash::{HashMap, HashMapExt, HashSet, HashSetExt};
 use eframe::egui::{Align, Button, Layout, Response, RichText, Ui};
 use egui_extras::Column;
 use egui_selectable_table::{
     ColumnOperations, ColumnOrdering, SelectableRow, SelectableTable, SortOrder,
 };
-use funnel_shared::{Channel, MessageWithUser, PAGE_VALUE};
+use funnel_shared::Channel;
 use std::cmp::Ordering;
 use strum::IntoEnumIterator;
 
-use crate::core::ChannelColumn;
-use crate::ui::{DateHandler, ShowUI, TabHandler};
-use crate::{AppEvent, EventBus};
+use crate::core::{ChannelColumn, MessageQuery, MessageRow, MessageStore};
+use crate::ui::{ChannelProfile, DateCounts, DateHandler, ShowUI, StoreView, TabHandler};
+use crate::{AppEvent, EventBus, ExportData};
+
+/// Number of users that are shown as the top posters of a channel profile
+const PROFILE_POSTERS: usize = 10;
 
 #[derive(Default)]
 pub struct Config {
     copy_selected: bool,
+    view_profile: Option<i64>,
 }
 
 impl ColumnOperations<ChannelRowData, ChannelColumn, Config> for ChannelColumn {
@@ -28,6 +32,8 @@ impl ColumnOperations<ChannelRowData, ChannelColumn, Config> for ChannelColumn {
             ChannelColumn::FirstMessage => row.first_message.to_string(),
             ChannelColumn::LastMessage => row.last_message.to_string(),
             ChannelColumn::UniqueUsers => row.unique_users.len().to_string(),
+            ChannelColumn::Attachments => row.attachments.to_string(),
+            ChannelColumn::MediaShare => row.media_share_text(),
         }
     }
     fn create_header(
@@ -57,6 +63,14 @@ impl ColumnOperations<ChannelRowData, ChannelColumn, Config> for ChannelColumn {
                 "The number of unique users seen in this channel. Click to sort by unique users"
                     .to_string()
             }
+            ChannelColumn::Attachments => {
+                "Total files attached to the messages in this channel. Click to sort by attachments"
+                    .to_string()
+            }
+            ChannelColumn::MediaShare => {
+                "Share of the messages with an attachment or embed. Click to sort by media share"
+                    .to_string()
+            }
         };
 
         let is_selected = if let Some(direction) = sort_order {
@@ -100,6 +114,8 @@ impl ColumnOperations<ChannelRowData, ChannelColumn, Config> for ChannelColumn {
             ChannelColumn::FirstMessage => row_data.first_message.to_string(),
             ChannelColumn::LastMessage => row_data.last_message.to_string(),
             ChannelColumn::UniqueUsers => row_data.unique_users.len().to_string(),
+            ChannelColumn::Attachments => row_data.attachments.to_string(),
+            ChannelColumn::MediaShare => row_data.media_share_text(),
         };
         let is_selected = column_selected;
 
@@ -112,11 +128,19 @@ impl ColumnOperations<ChannelRowData, ChannelColumn, Config> for ChannelColumn {
             label = label.on_hover_text(row_text);
         }
 
+        if label.double_clicked() {
+            table.config.view_profile = Some(row_data.id);
+        }
+
         label.context_menu(|ui| {
             if ui.button("Copy selected rows").clicked() {
                 table.config.copy_selected = true;
                 ui.close();
             }
+            if ui.button("View profile").clicked() {
+                table.config.view_profile = Some(row_data.id);
+                ui.close();
+            }
         });
         label
     }
@@ -132,6 +156,8 @@ impl ColumnOrdering<ChannelRowData> for ChannelColumn {
             ChannelColumn::FirstMessage => row_1.first_message.cmp(&row_2.first_message),
             ChannelColumn::LastMessage => row_1.last_message.cmp(&row_2.last_message),
             ChannelColumn::UniqueUsers => row_1.unique_users.len().cmp(&row_2.unique_users.len()),
+            ChannelColumn::Attachments => row_1.attachments.cmp(&row_2.attachments),
+            ChannelColumn::MediaShare => row_1.media_share().total_cmp(&row_2.media_share()),
         }
     }
 }
@@ -145,6 +171,9 @@ struct ChannelRowData {
     first_message: NaiveDateTime,
     last_message: NaiveDateTime,
     unique_users: HashSet<i64>,
+    attachments: u32,
+    /// Non-deleted messages with an attachment or embed
+    media_message: u32,
 }
 
 impl ChannelRowData {
@@ -157,53 +186,57 @@ impl ChannelRowData {
             first_message: date,
             last_message: date,
             unique_users: HashSet::new(),
+            attachments: 0,
+            media_message: 0,
         }
     }
 
-    fn add_user(&mut self, user_id: i64) {
-        self.unique_users.insert(user_id);
+    /// Percentage of the non-deleted messages that have an attachment or embed
+    fn media_share(&self) -> f64 {
+        if self.total_message == 0 {
+            return 0.0;
+        }
+        f64::from(self.media_message) / f64::from(self.total_message) * 100.0
     }
 
-    fn extend_user(&mut self, list: &HashSet<i64>) {
-        self.unique_users.extend(list);
+    fn media_share_text(&self) -> String {
+        format!("{:.1}%", self.media_share())
     }
 
-    /// Increment total message count by 1
-    fn increment_total_message(&mut self) {
-        self.total_message += 1;
-    }
+    /// Add a message of the channel to the row
+    fn add_message(&mut self, message: &MessageRow) {
+        let time = message.time();
 
-    /// Increment deleted message count by 1
-    fn increment_deleted_message(&mut self) {
-        self.deleted_message += 1;
-    }
-
-    fn increase_deleted_by(&mut self, amount: u32) {
-        self.deleted_message += amount;
-    }
+        if self.first_message > time {
+            self.first_message = time;
+        }
 
-    /// Increment total message count by `amount`
-    fn increase_message_by(&mut self, amount: u32) {
-        self.total_message += amount;
-    }
+        if self.last_message < time {
+            self.last_message = time;
+        }
 
-    /// Update the date this user was first seen in the chat
-    fn set_first_message(&mut self, date: NaiveDateTime) {
-        self.first_message = date;
-    }
+        if message.is_deleted() {
+            self.deleted_message += 1;
+        } else {
+            self.total_message += 1;
+            self.attachments += message.attachment_count();
+            if message.has_media() {
+                self.media_message += 1;
+            }
+        }
 
-    /// Update the date this user was last seen in the chat
-    fn set_last_message(&mut self, date: NaiveDateTime) {
-        self.last_message = date;
+        self.unique_users.insert(message.user_id());
     }
 }
 
 pub struct ChannelTable {
-    channel_data: HashMap<NaiveDate, HashMap<i64, ChannelRowData>>,
     table: SelectableTable<ChannelRowData, ChannelColumn, Config>,
+    /// The rows that are shown in the table
+    rows: Vec<ChannelRowData>,
     /// Read only currently selected dates in the UI
     date_handler: DateHandler,
-    reload_count: u64,
+    /// The rows of a reload that is still running
+    reload_rows: HashMap<i64, ChannelRowData>,
     channel_map: HashMap<i64, String>,
 }
 
@@ -214,17 +247,17 @@ impl Default for ChannelTable {
             .horizontal_scroll()
             .serial_column();
         Self {
-            channel_data: HashMap::new(),
             table,
+            rows: Vec::new(),
             date_handler: DateHandler::default(),
-            reload_count: 0,
+            reload_rows: HashMap::new(),
             channel_map: HashMap::new(),
         }
     }
 }
 
 impl ShowUI for ChannelTable {
-    fn show_ui(&mut self, ui: &mut Ui, _guild_id: i64, event_bus: &mut EventBus) {
+    fn show_ui(&mut self, ui: &mut Ui, guild_id: i64, event_bus: &mut EventBus) {
         let to_copy = self.table.config.copy_selected;
         if to_copy {
             self.table.config.copy_selected = false;
@@ -232,6 +265,10 @@ impl ShowUI for ChannelTable {
             event_bus.publish(AppEvent::CellsCopied);
         }
 
+        if let Some(channel_id) = self.table.config.view_profile.take() {
+            event_bus.publish(AppEvent::OpenChannelProfile(guild_id, channel_id));
+        }
+
         let mut clip_added = false;
 
         self.table.show_ui(ui, |builder| {
@@ -256,113 +293,103 @@ impl ShowUI for ChannelTable {
     }
 }
 
-impl ChannelTable {
-    fn handle_message(&mut self, message: &MessageWithUser, event_bus: &mut EventBus) {
-        self.reload_count += 1;
-
-        let user_id = message.sender.user_id;
-
-        let guild_id = message.message.guild_id;
-        let channel_id = message.message.channel_id;
-
-        let name = self.channel_map.get(&channel_id).unwrap();
+impl StoreView for ChannelTable {
+    fn start_reload(&mut self, _store: &MessageStore) -> Option<MessageQuery> {
+        self.reload_rows.clear();
 
-        let mut deleted_message = false;
-
-        let timestamp = if let Some(time) = message.message.delete_timestamp {
-            deleted_message = true;
-            time
-        } else {
-            message.message.message_timestamp
-        };
-
-        let datetime = DateTime::from_timestamp(timestamp, 0).unwrap();
-        let local_time = datetime.with_timezone(&Local).naive_local();
-        let local_date = local_time.date();
-
-        let channel_row = ChannelRowData::new(name, channel_id, local_time);
+        let query = MessageQuery::new().dates(self.date_handler.from, self.date_handler.to);
+        Some(query)
+    }
 
-        let entry = self.channel_data.entry(local_date).or_default();
-        entry.entry(channel_id).or_insert(channel_row);
+    fn add_message(&mut self, message: &MessageRow) {
+        let channel_id = message.channel_id();
+        if !self.reload_rows.contains_key(&channel_id) {
+            let row =
+                ChannelRowData::new(&self.channel_name(channel_id), channel_id, message.time());
+            self.reload_rows.insert(channel_id, row);
+        }
 
-        let target_data = self.channel_data.get_mut(&local_date).unwrap();
-        let user_row_data = target_data.get_mut(&channel_id).unwrap();
+        self.reload_rows
+            .get_mut(&channel_id)
+            .unwrap()
+            .add_message(message);
+    }
 
-        if user_row_data.first_message > local_time {
-            user_row_data.set_first_message(local_time);
-        }
+    /// Create the rows that will be shown in the UI.
+    fn finish_reload(&mut self, _store: &MessageStore) {
+        self.table.clear_all_rows();
 
-        if user_row_data.last_message < local_time {
-            user_row_data.set_last_message(local_time);
+        self.rows = self.reload_rows.drain().map(|(_, row)| row).collect();
+        for row in &self.rows {
+            self.table.add_modify_row(|_| Some(row.clone()));
         }
+        self.table.recreate_rows();
+    }
 
-        // Channel table has a copy of the handler only. Modifying here doesn't impact the UI. Check
-        // here if update is necessary in the main UI, if yes, send an event for processing
-        let needs_update = self.date_handler.update_dates(local_date);
-        if needs_update {
-            event_bus.publish(AppEvent::UpdateDate(local_date, guild_id));
-        }
+    fn set_date_handler(&mut self, handler: DateHandler) {
+        self.date_handler = handler;
+    }
+}
 
-        if deleted_message {
-            user_row_data.increment_deleted_message();
-        } else {
-            user_row_data.increment_total_message();
+impl ChannelTable {
+    /// Profile of the channel within the selected dates. None if the channel is unknown
+    pub fn channel_profile(
+        &self,
+        store: &MessageStore,
+        guild_id: i64,
+        channel_id: i64,
+    ) -> Option<ChannelProfile> {
+        let name = self.channel_map.get(&channel_id)?;
+        let mut profile = ChannelProfile::new(guild_id, channel_id, name);
+
+        let query = MessageQuery::new()
+            .dates(self.date_handler.from, self.date_handler.to)
+            .channel(channel_id);
+
+        let mut timeline = DateCounts::new();
+        let mut deleted_timeline = DateCounts::new();
+        let mut date_users: HashMap<NaiveDate, (u32, u32, HashSet<i64>)> = HashMap::new();
+        let mut posters: HashMap<String, u32> = HashMap::new();
+
+        for date in self.date_handler.range_dates() {
+            timeline.insert(date, 0);
+            deleted_timeline.insert(date, 0);
         }
 
-        user_row_data.add_user(user_id);
+        for message in store.query(query) {
+            let date = message.time().date();
+            let entry = date_users.entry(date).or_default();
+
+            if message.is_deleted() {
+                entry.1 += 1;
+                *deleted_timeline.entry(date).or_default() += 1;
+            } else {
+                entry.0 += 1;
+                *timeline.entry(date).or_default() += 1;
+                *posters.entry(message.user().username.clone()).or_default() += 1;
+            }
 
-        if self.reload_count == PAGE_VALUE * 5 {
-            event_bus.publish_if_needed(AppEvent::ChannelTableNeedsReload(guild_id));
+            entry.2.insert(message.user_id());
+            profile.add_seen(message.sent_time(), message.sent_time());
         }
-    }
 
-    /// Create the rows that will be shown in the UI.
-    fn create_rows(&mut self) {
-        self.reload_count = 0;
-        self.table.clear_all_rows();
+        for (date, (total_message, deleted_message, users)) in &date_users {
+            profile.add_date(*date, *total_message, *deleted_message, users);
+        }
 
-        let mut id_map = HashMap::new();
+        let mut posters: Vec<(String, u32)> = posters.into_iter().collect();
+        posters.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        posters.truncate(PROFILE_POSTERS);
 
-        // Go by all the data that are within the range and join them together
-        for (date, data) in &self.channel_data {
-            if !self.date_handler.within_range(*date) {
-                continue;
-            }
-
-            for (id, row) in data {
-                if let Some(row_id) = id_map.get(id) {
-                    self.table.add_modify_row(|rows| {
-                        let target_row = rows.get_mut(row_id).unwrap();
-                        let user_row_data = &mut target_row.row_data;
-
-                        if user_row_data.first_message > row.first_message {
-                            user_row_data.set_first_message(row.first_message);
-                        }
-
-                        if user_row_data.last_message < row.last_message {
-                            user_row_data.set_last_message(row.last_message);
-                        }
-
-                        let total_message = row.total_message;
-                        let deleted_message = row.deleted_message;
-                        let user_list = &row.unique_users;
-
-                        user_row_data.increase_message_by(total_message);
-                        user_row_data.increase_deleted_by(deleted_message);
-                        user_row_data.extend_user(user_list);
-                        None
-                    });
-                } else {
-                    let new_id = self.table.add_modify_row(|_| Some(row.clone()));
-                    id_map.insert(row.id, new_id.unwrap());
-                }
-            }
-        }
-        self.table.recreate_rows();
+        profile.set_activity(timeline, deleted_timeline, posters);
+        Some(profile)
     }
 
-    pub fn set_date_handler(&mut self, handler: DateHandler) {
-        self.date_handler = handler;
+    /// Name of the channel or its ID if the channel is unknown
+    fn channel_name(&self, channel_id: i64) -> String {
+        self.channel_map
+            .get(&channel_id)
+            .map_or_else(|| channel_id.to_string(), String::clone)
     }
 
     fn set_channel_id_map(&mut self, channel_list: Vec<Channel>) {
@@ -374,29 +401,26 @@ impl ChannelTable {
                 .or_insert(channel_name.to_string());
         }
     }
-}
 
-impl TabHandler {
-    pub fn handle_message_channel_table(
-        &mut self,
-        message: &MessageWithUser,
-        event_bus: &mut EventBus,
-    ) {
-        let guild_id = message.message.guild_id;
-        self.channel_table
-            .get_mut(&guild_id)
-            .unwrap()
-            .handle_message(message, event_bus);
-    }
-
-    pub fn channel_table_recreate_rows(&mut self, guild_id: i64) {
-        self.channel_table.get_mut(&guild_id).unwrap().create_rows();
+    /// The rows that are currently shown in the table
+    fn export_data(&self) -> ExportData {
+        ExportData::from_rows(
+            "Channel Table",
+            &self.rows,
+            |column: &ChannelColumn, row| column.column_text(row),
+        )
     }
+}
 
+impl TabHandler {
     pub fn set_channel_table_channel_map(&mut self, guild_id: i64, channels: Vec<Channel>) {
         self.channel_table
             .get_mut(&guild_id)
             .unwrap()
             .set_channel_id_map(channels);
     }
+
+    pub fn channel_table_export_data(&self, guild_id: i64) -> ExportData {
+        self.channel_table.get(&guild_id).unwrap().export_data()
+    }
 }
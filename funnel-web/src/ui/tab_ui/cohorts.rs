@@ -0,0 +1,366 @@
+use chrono::{Duration, Months, NaiveDate, NaiveDateTime};
+use eframe::egui::ahash::{HashMap, HashMapExt, HashSet};
+use eframe::egui::{Grid, RichText, ScrollArea, Ui};
+use funnel_shared::Channel;
+use std::collections::BTreeMap;
+use strum::IntoEnumIterator;
+use strum_macros::{Display, EnumIter};
+
+use crate::core::{MessageQuery, MessageRow, MessageStore, selected_channel_ids};
+use crate::ui::{DateHandler, ShowUI, StoreView, TabHandler};
+use crate::{AppEvent, ChartType, EventBus, ExportData};
+
+/// Number of weeks or months after joining that are checked for every cohort
+const COHORT_PERIODS: u32 = 8;
+
+/// How the members are grouped by the time they joined
+#[derive(Default, Clone, Copy, PartialEq, Eq, Display, EnumIter)]
+pub enum CohortType {
+    #[default]
+    Weekly,
+    Monthly,
+}
+
+impl CohortType {
+    fn chart_type(self) -> ChartType {
+        match self {
+            CohortType::Weekly => ChartType::Weekly,
+            CohortType::Monthly => ChartType::Monthly,
+        }
+    }
+
+    /// The time that is the given number of periods after the join
+    fn checkpoint(self, joined: NaiveDateTime, periods: u32) -> NaiveDateTime {
+        match self {
+            CohortType::Weekly => joined + Duration::weeks(i64::from(periods)),
+            CohortType::Monthly => joined
+                .checked_add_months(Months::new(periods))
+                .unwrap_or(NaiveDateTime::MAX),
+        }
+    }
+
+    fn period_name(self, period: u32) -> String {
+        match self {
+            CohortType::Weekly => format!("Week {period}"),
+            CohortType::Monthly => format!("Month {period}"),
+        }
+    }
+
+    fn cohort_name(self, start: NaiveDateTime) -> String {
+        match self {
+            CohortType::Weekly => start.format("%Y-%m-%d").to_string(),
+            CohortType::Monthly => start.format("%Y-%m").to_string(),
+        }
+    }
+}
+
+/// The value that is shown for every period of a cohort
+#[derive(Default, Clone, Copy, PartialEq, Eq, Display, EnumIter)]
+pub enum CohortMetric {
+    #[default]
+    #[strum(to_string = "Still Present")]
+    Present,
+    #[strum(to_string = "Posted")]
+    Posted,
+}
+
+/// A membership that started with a join activity
+struct Membership {
+    user_id: i64,
+    joined: NaiveDateTime,
+    left: Option<NaiveDateTime>,
+    /// The first message that was sent while being a member
+    first_post: Option<NaiveDateTime>,
+}
+
+/// Members of a cohort whose period has already passed
+#[derive(Default, Clone, Copy)]
+struct CohortPeriod {
+    members: u32,
+    present: u32,
+    posted: u32,
+}
+
+impl CohortPeriod {
+    fn share(self, metric: CohortMetric) -> Option<f64> {
+        if self.members == 0 {
+            return None;
+        }
+        let count = match metric {
+            CohortMetric::Present => self.present,
+            CohortMetric::Posted => self.posted,
+        };
+        Some(f64::from(count) / f64::from(self.members) * 100.0)
+    }
+
+    fn share_text(self, metric: CohortMetric) -> String {
+        self.share(metric)
+            .map_or_else(|| "-".to_string(), |share| format!("{share:.0}%"))
+    }
+}
+
+struct CohortRow {
+    start: NaiveDateTime,
+    members: u32,
+    periods: Vec<CohortPeriod>,
+}
+
+/// Memberships of a reload that is still running
+#[derive(Default)]
+struct CohortReload {
+    memberships: Vec<Membership>,
+    /// Key: User ID. Value: Index of every membership of the user
+    user_memberships: HashMap<i64, Vec<usize>>,
+    /// The newest time within the data. Periods after it have not passed yet
+    latest: Option<NaiveDateTime>,
+}
+
+#[derive(Default)]
+pub struct Cohorts {
+    /// Read only currently selected dates in the UI
+    date_handler: DateHandler,
+    cohort_type: CohortType,
+    metric: CohortMetric,
+    /// Member activities that can not be placed in a cohort because they have no user
+    missing_users: u32,
+    rows: Vec<CohortRow>,
+    /// The cohort type the rows were built with
+    shown_type: CohortType,
+    reload_type: CohortType,
+    reload: CohortReload,
+    channels: Vec<Channel>,
+    selected_channels: HashSet<usize>,
+}
+
+impl ShowUI for Cohorts {
+    fn show_ui(&mut self, ui: &mut Ui, guild_id: i64, event_bus: &mut EventBus) {
+        ui.horizontal(|ui| {
+            for cohort_type in CohortType::iter() {
+                if ui
+                    .selectable_value(&mut self.cohort_type, cohort_type, cohort_type.to_string())
+                    .changed()
+                {
+                    event_bus.publish(AppEvent::CohortsNeedsReload(guild_id));
+                }
+            }
+            ui.separator();
+
+            for metric in CohortMetric::iter() {
+                ui.selectable_value(&mut self.metric, metric, metric.to_string());
+            }
+        });
+
+        let explanation = match self.metric {
+            CohortMetric::Present => "Share of the members that were still in the guild",
+            CohortMetric::Posted => {
+                "Share of the members that sent at least one message in the selected channels"
+            }
+        };
+        ui.label(format!(
+            "{explanation} by the end of each period after joining. Members are grouped by the date they joined within the selected dates"
+        ));
+        if self.missing_users > 0 {
+            ui.label(format!(
+                "{} member activities have no user and are not part of any cohort",
+                self.missing_users
+            ));
+        }
+        ui.separator();
+        ui.add_space(5.0);
+
+        if self.rows.is_empty() {
+            ui.label("No member joined within the selected dates");
+            return;
+        }
+
+        ScrollArea::both().show(ui, |ui| {
+            Grid::new("cohort_grid")
+                .striped(true)
+                .min_col_width(70.0)
+                .show(ui, |ui| {
+                    ui.label(RichText::new("Cohort").strong());
+                    ui.label(RichText::new("Members").strong());
+                    for period in 1..=COHORT_PERIODS {
+                        ui.label(RichText::new(self.shown_type.period_name(period)).strong());
+                    }
+                    ui.end_row();
+
+                    for row in &self.rows {
+                        ui.label(self.shown_type.cohort_name(row.start));
+                        ui.label(row.members.to_string());
+                        for period in &row.periods {
+                            ui.label(period.share_text(self.metric))
+                                .on_hover_text(format!(
+                                    "Present: {}\nPosted: {}\nMembers: {}",
+                                    period.present, period.posted, period.members
+                                ));
+                        }
+                        ui.end_row();
+                    }
+                });
+        });
+    }
+}
+
+impl StoreView for Cohorts {
+    fn start_reload(&mut self, store: &MessageStore) -> Option<MessageQuery> {
+        self.reload = CohortReload::default();
+        self.reload_type = self.cohort_type;
+
+        let mut activities: Vec<(i64, NaiveDateTime, bool)> = Vec::new();
+        let mut missing_users = 0;
+        for activity in store.activities() {
+            let time = activity.time;
+            self.reload.latest = self.reload.latest.max(Some(time));
+
+            if let Some(user_id) = activity.user_id {
+                activities.push((user_id, time, activity.is_join));
+            } else {
+                missing_users += 1;
+            }
+        }
+        self.missing_users = missing_users;
+        activities.sort_by_key(|(_, time, _)| *time);
+
+        // Key: User ID. Value: Index of the membership that has not ended yet
+        let mut open_memberships: HashMap<i64, usize> = HashMap::new();
+        for (user_id, time, is_join) in activities {
+            if is_join {
+                if open_memberships.contains_key(&user_id) {
+                    continue;
+                }
+                open_memberships.insert(user_id, self.reload.memberships.len());
+                self.reload.memberships.push(Membership {
+                    user_id,
+                    joined: time,
+                    left: None,
+                    first_post: None,
+                });
+            } else if let Some(index) = open_memberships.remove(&user_id) {
+                self.reload.memberships[index].left = Some(time);
+            }
+        }
+
+        self.reload
+            .memberships
+            .retain(|membership| self.date_handler.within_range(membership.joined.date()));
+        for (index, membership) in self.reload.memberships.iter().enumerate() {
+            self.reload
+                .user_memberships
+                .entry(membership.user_id)
+                .or_default()
+                .push(index);
+        }
+
+        if self.reload.memberships.is_empty() {
+            return None;
+        }
+
+        // Messages sent after the selected dates still count for the members that joined in them
+        let selected_channels = selected_channel_ids(&self.channels, &self.selected_channels);
+        let query = MessageQuery::new()
+            .dates(self.date_handler.from, NaiveDate::MAX)
+            .channels(selected_channels);
+        Some(query)
+    }
+
+    fn add_message(&mut self, message: &MessageRow) {
+        let sent_time = message.sent_time();
+        self.reload.latest = self.reload.latest.max(Some(sent_time));
+
+        let Some(indexes) = self.reload.user_memberships.get(&message.user_id()) else {
+            return;
+        };
+        for index in indexes {
+            let membership = &mut self.reload.memberships[*index];
+            let is_member = sent_time >= membership.joined
+                && membership.left.is_none_or(|left| sent_time <= left);
+
+            if is_member && membership.first_post.is_none_or(|first| first > sent_time) {
+                membership.first_post = Some(sent_time);
+            }
+        }
+    }
+
+    fn finish_reload(&mut self, _store: &MessageStore) {
+        let reload = std::mem::take(&mut self.reload);
+        let cohort_type = self.reload_type;
+        let latest = reload.latest.unwrap_or_default();
+
+        let mut cohorts: BTreeMap<NaiveDateTime, CohortRow> = BTreeMap::new();
+        for membership in reload.memberships {
+            let start = cohort_type.chart_type().bucket_start(membership.joined);
+            let row = cohorts.entry(start).or_insert_with(|| CohortRow {
+                start,
+                members: 0,
+                periods: vec![CohortPeriod::default(); COHORT_PERIODS as usize],
+            });
+            row.members += 1;
+
+            for (index, period) in row.periods.iter_mut().enumerate() {
+                let checkpoint = cohort_type.checkpoint(membership.joined, index as u32 + 1);
+                if checkpoint > latest {
+                    break;
+                }
+
+                period.members += 1;
+                if membership.left.is_none_or(|left| left > checkpoint) {
+                    period.present += 1;
+                }
+                if membership
+                    .first_post
+                    .is_some_and(|first_post| first_post <= checkpoint)
+                {
+                    period.posted += 1;
+                }
+            }
+        }
+
+        self.shown_type = cohort_type;
+        self.rows = cohorts.into_values().collect();
+    }
+
+    fn set_date_handler(&mut self, handler: DateHandler) {
+        self.date_handler = handler;
+    }
+
+    fn set_channels(&mut self, channels: Vec<Channel>) {
+        self.channels = channels;
+    }
+
+    fn set_selected_channels(&mut self, selected: HashSet<usize>) {
+        self.selected_channels = selected;
+    }
+}
+
+impl Cohorts {
+    /// Both shares of every period of the shown cohorts
+    fn export_data(&self) -> ExportData {
+        let mut headers = vec!["Cohort".to_string(), "Members".to_string()];
+        for period in 1..=COHORT_PERIODS {
+            let name = self.shown_type.period_name(period);
+            headers.push(format!("{name} Still Present"));
+            headers.push(format!("{name} Posted"));
+        }
+        let mut data = ExportData::new("Cohorts", headers);
+
+        for row in &self.rows {
+            let mut values = vec![
+                self.shown_type.cohort_name(row.start),
+                row.members.to_string(),
+            ];
+            for period in &row.periods {
+                values.push(period.share_text(CohortMetric::Present));
+                values.push(period.share_text(CohortMetric::Posted));
+            }
+            data.add_row(values);
+        }
+        data
+    }
+}
+
+impl TabHandler {
+    pub fn cohorts_export_data(&self, guild_id: i64) -> ExportData {
+        self.cohorts.get(&guild_id).unwrap().export_data()
+    }
+}
@@ -1,21 +1,36 @@
-use chrono::{DateTime, Local, NaiveDate};
+use chrono::{Days, NaiveDate};
 use eframe::egui::ahash::{HashMap, HashMapExt, HashSet};
-use eframe::egui::{Align, Button, CursorIcon, Layout, Response, RichText, Slider, Ui};
+use eframe::egui::{
+    Align, Button, CollapsingHeader, CursorIcon, Grid, Key, Layout, Response, RichText, Slider,
+    TextEdit, Ui,
+};
 use egui_extras::Column;
 use egui_selectable_table::{
     ColumnOperations, ColumnOrdering, SelectableRow, SelectableTable, SortOrder,
 };
-use funnel_shared::{Channel, MessageWithUser, PAGE_VALUE};
+use funnel_shared::Channel;
 use std::cmp::Ordering;
+use std::collections::BTreeMap;
 use strum::IntoEnumIterator;
 
-use crate::core::WordColumn;
-use crate::ui::{DateHandler, ShowUI, TabHandler};
-use crate::{AppEvent, EventBus, get_stripped_windows};
+use crate::core::{MessageQuery, MessageRow, MessageStore, WordColumn, selected_channel_ids};
+use crate::ui::{
+    BucketCounts, DateHandler, PhraseMatcher, ShowUI, StoreView, TabHandler, WordFilter,
+    WordFilterInput, add_phrases, show_bucket_plot,
+};
+use crate::{AppEvent, ChartType, EventBus, ExportData};
+
+/// Number of phrases that are shown in the user and channel profiles
+const PROFILE_PHRASES: usize = 10;
+
+/// Number of phrases that are shown in the trending list
+const TRENDING_PHRASES: usize = 10;
 
 #[derive(Default)]
 pub struct Config {
     copy_selected: bool,
+    /// Phrase that was picked to be tracked over time
+    track_phrase: Option<String>,
 }
 
 impl ColumnOperations<WordRowData, WordColumn, Config> for WordColumn {
@@ -23,6 +38,8 @@ impl ColumnOperations<WordRowData, WordColumn, Config> for WordColumn {
         match self {
             WordColumn::Phrase => row.phrase.to_string(),
             WordColumn::Hits => row.hits.to_string(),
+            WordColumn::UniqueUsers => row.unique_users.to_string(),
+            WordColumn::TopUser => row.top_user.to_string(),
         }
     }
 
@@ -36,6 +53,8 @@ impl ColumnOperations<WordRowData, WordColumn, Config> for WordColumn {
         let hover_text = match self {
             WordColumn::Phrase => "The phrase that is being analyzed".to_string(),
             WordColumn::Hits => "The number of times this phrase was found in the chat".to_string(),
+            WordColumn::UniqueUsers => "The number of users that used this phrase".to_string(),
+            WordColumn::TopUser => "The user that used this phrase the most".to_string(),
         };
 
         let is_selected = if let Some(direction) = sort_order {
@@ -74,6 +93,11 @@ impl ColumnOperations<WordRowData, WordColumn, Config> for WordColumn {
                 row_data.phrase.to_string()
             }
             WordColumn::Hits => row_data.hits.to_string(),
+            WordColumn::UniqueUsers => row_data.unique_users.to_string(),
+            WordColumn::TopUser => {
+                show_tooltip = true;
+                row_data.top_user.to_string()
+            }
         };
         let is_selected = column_selected;
 
@@ -90,6 +114,10 @@ impl ColumnOperations<WordRowData, WordColumn, Config> for WordColumn {
                 table.config.copy_selected = true;
                 ui.close();
             }
+            if ui.button("Track phrase over time").clicked() {
+                table.config.track_phrase = Some(row_data.phrase.clone());
+                ui.close();
+            }
         });
         label
     }
@@ -100,6 +128,8 @@ impl ColumnOrdering<WordRowData> for WordColumn {
         match self {
             WordColumn::Phrase => row_1.phrase.cmp(&row_2.phrase),
             WordColumn::Hits => row_1.hits.cmp(&row_2.hits),
+            WordColumn::UniqueUsers => row_1.unique_users.cmp(&row_2.unique_users),
+            WordColumn::TopUser => row_1.top_user.cmp(&row_2.top_user),
         }
     }
 }
@@ -108,25 +138,68 @@ impl ColumnOrdering<WordRowData> for WordColumn {
 struct WordRowData {
     phrase: String,
     hits: u32,
+    unique_users: u32,
+    top_user: String,
 }
 
-impl WordRowData {
-    fn new(phrase: String) -> Self {
-        Self { phrase, hits: 0 }
-    }
+/// Hits of a phrase during a reload
+#[derive(Default)]
+struct PhraseHits {
+    hits: u32,
+    /// Key: User ID. Value: Hits of the user
+    users: HashMap<i64, u32>,
+}
+
+/// Hits of a phrase in the selected dates and in the same number of days before them
+struct TrendingPhrase {
+    phrase: String,
+    current: u32,
+    previous: u32,
+}
 
-    fn increase_hits(&mut self) {
-        self.hits += 1;
+impl TrendingPhrase {
+    fn change_text(&self) -> String {
+        if self.previous == 0 {
+            return "New".to_string();
+        }
+        let change =
+            (f64::from(self.current) - f64::from(self.previous)) / f64::from(self.previous) * 100.0;
+        format!("{change:+.0}%")
     }
 }
 
 pub struct WordTable {
     table: SelectableTable<WordRowData, WordColumn, Config>,
+    /// The rows that are shown in the table
+    rows: Vec<WordRowData>,
     date_handler: DateHandler,
-    reload_count: u64,
     window_size: usize,
-    stripped_contents: HashMap<NaiveDate, HashMap<i64, Vec<String>>>,
-
+    filter: WordFilter,
+    filter_input: WordFilterInput,
+    user_input: String,
+    /// Part of the name or the username or the full ID of every user whose messages are counted.
+    /// Every user if empty
+    user_filter: Vec<String>,
+    /// Phrases whose hits are plotted over time
+    tracked_phrases: Vec<String>,
+    track_input: String,
+    trend_type: ChartType,
+    /// Key: Tracked phrase
+    trend_data: BTreeMap<String, BucketCounts>,
+    /// Phrases with the largest increase compared to the previous range
+    trending: Vec<TrendingPhrase>,
+    /// Phrase hits and the filter of a reload that is still running
+    reload_phrases: HashMap<String, PhraseHits>,
+    reload_matcher: PhraseMatcher,
+    /// Counted phrases of a reload whose rows were not built yet
+    reload_pending: Vec<(String, PhraseHits)>,
+    reload_rows: Vec<WordRowData>,
+    /// Phrase hits before the selected dates and the tracked phrases of a reload that is still
+    /// running
+    reload_previous: HashMap<String, u32>,
+    reload_trends: BTreeMap<String, BucketCounts>,
+    reload_trend_type: ChartType,
+    reload_from: NaiveDate,
     channels: Vec<Channel>,
     selected_channels: HashSet<usize>,
 }
@@ -140,10 +213,26 @@ impl Default for WordTable {
             .serial_column();
         Self {
             table,
+            rows: Vec::new(),
             date_handler: DateHandler::default(),
-            reload_count: 0,
             window_size: 1,
-            stripped_contents: HashMap::new(),
+            filter: WordFilter::default(),
+            filter_input: WordFilterInput::default(),
+            user_input: String::new(),
+            user_filter: Vec::new(),
+            tracked_phrases: Vec::new(),
+            track_input: String::new(),
+            trend_type: ChartType::default(),
+            trend_data: BTreeMap::new(),
+            trending: Vec::new(),
+            reload_phrases: HashMap::new(),
+            reload_matcher: PhraseMatcher::default(),
+            reload_pending: Vec::new(),
+            reload_rows: Vec::new(),
+            reload_previous: HashMap::new(),
+            reload_trends: BTreeMap::new(),
+            reload_trend_type: ChartType::default(),
+            reload_from: NaiveDate::default(),
             channels: Vec::default(),
             selected_channels: HashSet::default(),
         }
@@ -159,6 +248,10 @@ impl ShowUI for WordTable {
             event_bus.publish(AppEvent::CellsCopied);
         }
 
+        if let Some(phrase) = self.table.config.track_phrase.take() {
+            self.track_phrase(&phrase, guild_id, event_bus);
+        }
+
         ui.horizontal(|ui| {
             ui.label("Phrase Size:");
             if ui.add(Slider::new(&mut self.window_size, 1..=20)).changed() {
@@ -166,10 +259,35 @@ impl ShowUI for WordTable {
 
             }
             ui.separator();
+            ui.label("Users:");
+            let user_response = ui.add(
+                TextEdit::singleline(&mut self.user_input)
+                    .hint_text("Names, usernames or IDs separated by commas")
+                    .desired_width(250.0),
+            );
+            let enter_pressed =
+                user_response.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter));
+            if ui.button("Apply").clicked() || enter_pressed {
+                self.user_filter = self
+                    .user_input
+                    .split(',')
+                    .map(|user| user.trim().to_lowercase())
+                    .filter(|user| !user.is_empty())
+                    .collect();
+                event_bus.publish(AppEvent::WordTableNeedsReload(guild_id));
+            }
+            ui.separator();
             ui.label("Where is word xyz?")
-                .on_hover_text("Message contents are filtered out of words such as 'I' 'This' 'My' and many more to keep the count relevant to help find useful phrases")
+                .on_hover_text("Message contents are filtered out of words such as 'I' 'This' 'My' and many more to keep the count relevant to help find useful phrases. Use Raw Content in the filters to count every word")
                 .on_hover_cursor(CursorIcon::Help)
         });
+
+        if self.filter_input.show_ui(ui, &mut self.filter) {
+            event_bus.publish(AppEvent::WordTableNeedsReload(guild_id));
+            event_bus.publish(AppEvent::DashboardNeedsReload(guild_id));
+        }
+        self.show_trending(ui);
+        self.show_trends(ui, guild_id, event_bus);
         ui.add_space(5.0);
 
         self.table.show_ui(ui, |builder| {
@@ -180,129 +298,372 @@ impl ShowUI for WordTable {
                 .drag_to_scroll(false)
                 .column(Column::initial(500.0).clip(true))
                 .column(Column::initial(150.0))
+                .column(Column::initial(150.0))
+                .column(Column::initial(200.0).clip(true))
                 .auto_shrink([false; 2])
                 .min_scrolled_height(0.0)
         });
     }
 }
 
-impl WordTable {
-    fn handle_message(&mut self, message: &MessageWithUser, event_bus: &mut EventBus) {
-        if message.message.delete_timestamp.is_some() || message.message.stripped_content.is_none()
-        {
-            return;
+impl StoreView for WordTable {
+    fn start_reload(&mut self, _store: &MessageStore) -> Option<MessageQuery> {
+        self.reload_phrases.clear();
+        self.reload_pending.clear();
+        self.reload_rows.clear();
+        self.reload_previous.clear();
+        self.reload_matcher = self.filter.matcher();
+        self.reload_from = self.date_handler.from;
+        self.reload_trend_type = self.trend_type;
+
+        // Every bucket within the dates is shown even if the phrase was not used in it
+        let buckets = self
+            .trend_type
+            .buckets(self.date_handler.from, self.date_handler.to);
+        self.reload_trends.clear();
+        for phrase in &self.tracked_phrases {
+            let series = self.reload_trends.entry(phrase.clone()).or_default();
+            for bucket in &buckets {
+                series.insert(*bucket, 0);
+            }
         }
-        self.reload_count += 1;
 
-        let guild_id = message.message.guild_id;
-        let channel_id = message.message.channel_id;
+        // The same number of days right before the selected dates to compare against
+        let days = (self.date_handler.to - self.date_handler.from).num_days() + 1;
+        let previous_from = self
+            .date_handler
+            .from
+            .checked_sub_days(Days::new(days.max(1) as u64))
+            .unwrap_or(self.date_handler.from);
+
+        let selected_channels = selected_channel_ids(&self.channels, &self.selected_channels);
+        let query = MessageQuery::new()
+            .dates(previous_from, self.date_handler.to)
+            .channels(selected_channels)
+            .skip_deleted();
+        Some(query)
+    }
 
-        let timestamp = message.message.message_timestamp;
+    fn add_message(&mut self, message: &MessageRow) {
+        if !self.matches_user(message) {
+            return;
+        }
 
-        let datetime = DateTime::from_timestamp(timestamp, 0).unwrap();
-        let local_time = datetime.with_timezone(&Local).naive_local();
-        let local_date = local_time.date();
-        let stripped_content = message.message.stripped_content.clone().unwrap();
+        if message.time().date() < self.reload_from {
+            add_phrases(
+                &mut self.reload_previous,
+                message,
+                self.window_size,
+                &self.reload_matcher,
+            );
+            return;
+        }
 
-        self.stripped_contents
-            .entry(local_date)
-            .or_default()
-            .entry(channel_id)
-            .or_default()
-            .push(stripped_content.clone());
+        let mut message_phrases = HashMap::new();
+        add_phrases(
+            &mut message_phrases,
+            message,
+            self.window_size,
+            &self.reload_matcher,
+        );
+        for (phrase, hits) in message_phrases {
+            let phrase_hits = self.reload_phrases.entry(phrase).or_default();
+            phrase_hits.hits += hits;
+            *phrase_hits.users.entry(message.user_id()).or_default() += hits;
+        }
+
+        // Tracked phrases can have a different size than the table
+        let bucket = self.reload_trend_type.bucket_start(message.time());
+        let mut size_hits: HashMap<usize, HashMap<String, u32>> = HashMap::new();
+        for (phrase, series) in &mut self.reload_trends {
+            let size = phrase.split(' ').count();
+            let hits = size_hits.entry(size).or_insert_with(|| {
+                let mut hits = HashMap::new();
+                add_phrases(&mut hits, message, size, &self.reload_matcher);
+                hits
+            });
+            if let Some(count) = hits.get(phrase) {
+                *series.entry(bucket).or_default() += count;
+            }
+        }
+    }
 
-        if self.reload_count >= PAGE_VALUE * 5 {
-            event_bus.publish_if_needed(AppEvent::WordTableNeedsReload(guild_id));
+    fn finish_chunk(&mut self, store: &MessageStore, limit: usize) -> bool {
+        self.reload_pending.extend(self.reload_phrases.drain());
+
+        let remaining = self.reload_pending.len().saturating_sub(limit);
+        for (phrase, phrase_hits) in self.reload_pending.split_off(remaining) {
+            // The lowest ID wins a tie so the top user stays the same between reloads
+            let top_user = phrase_hits
+                .users
+                .iter()
+                .max_by(|a, b| a.1.cmp(b.1).then_with(|| b.0.cmp(a.0)))
+                .and_then(|(user_id, _)| store.user(*user_id))
+                .map(|user| user.name.clone())
+                .unwrap_or_default();
+
+            self.reload_rows.push(WordRowData {
+                phrase,
+                hits: phrase_hits.hits,
+                unique_users: phrase_hits.users.len() as u32,
+                top_user,
+            });
         }
+        self.reload_pending.is_empty()
     }
 
-    fn create_rows(&mut self) {
-        self.reload_count = 0;
+    fn finish_reload(&mut self, _store: &MessageStore) {
+        self.trend_data = std::mem::take(&mut self.reload_trends);
+
+        let mut trending: Vec<TrendingPhrase> = self
+            .reload_rows
+            .iter()
+            .map(|row| TrendingPhrase {
+                phrase: row.phrase.clone(),
+                current: row.hits,
+                previous: self
+                    .reload_previous
+                    .get(&row.phrase)
+                    .copied()
+                    .unwrap_or_default(),
+            })
+            .filter(|trend| trend.current > trend.previous)
+            .collect();
+        trending.sort_by(|a, b| {
+            (b.current - b.previous)
+                .cmp(&(a.current - a.previous))
+                .then_with(|| b.current.cmp(&a.current))
+                .then_with(|| a.phrase.cmp(&b.phrase))
+        });
+        trending.truncate(TRENDING_PHRASES);
+        self.trending = trending;
+        self.reload_previous.clear();
+
         self.table.clear_all_rows();
 
-        let mut selected_channels = HashSet::default();
+        self.rows = std::mem::take(&mut self.reload_rows);
+        for row in &self.rows {
+            self.table.add_modify_row(|_| Some(row.clone()));
+        }
+
+        self.table.recreate_rows();
+    }
+
+    fn set_date_handler(&mut self, handler: DateHandler) {
+        self.date_handler = handler;
+    }
+
+    fn set_channels(&mut self, channels: Vec<Channel>) {
+        self.channels = channels;
+    }
 
-        if self.selected_channels.is_empty() {
-            for channel in &self.channels {
-                selected_channels.insert(channel.channel_id);
+    fn set_selected_channels(&mut self, selected: HashSet<usize>) {
+        self.selected_channels = selected;
+    }
+}
+
+impl WordTable {
+    /// The phrases that were used more in the selected dates than in the days before them
+    fn show_trending(&self, ui: &mut Ui) {
+        CollapsingHeader::new("Trending").show(ui, |ui| {
+            if self.trending.is_empty() {
+                ui.label("No phrase was used more than in the previous range");
+                return;
             }
-        } else {
-            for index in &self.selected_channels {
-                if index == &0_usize {
-                    for channel in &self.channels {
-                        selected_channels.insert(channel.channel_id);
+
+            ui.label("Compared to the same number of days before the From date");
+            Grid::new("trending_phrases")
+                .num_columns(4)
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.label(RichText::new("Phrase").strong());
+                    ui.label(RichText::new("Hits").strong());
+                    ui.label(RichText::new("Previous Hits").strong());
+                    ui.label(RichText::new("Change").strong());
+                    ui.end_row();
+
+                    for trend in &self.trending {
+                        ui.label(&trend.phrase);
+                        ui.label(trend.current.to_string());
+                        ui.label(trend.previous.to_string());
+                        ui.label(trend.change_text());
+                        ui.end_row();
+                    }
+                });
+        });
+    }
+
+    /// The hits of the tracked phrases over time
+    fn show_trends(&mut self, ui: &mut Ui, guild_id: i64, event_bus: &mut EventBus) {
+        CollapsingHeader::new("Phrase Trends").show(ui, |ui| {
+            ui.horizontal(|ui| {
+                for chart_type in ChartType::iter() {
+                    if ui
+                        .selectable_value(&mut self.trend_type, chart_type, chart_type.to_string())
+                        .changed()
+                    {
+                        event_bus.publish(AppEvent::WordTableNeedsReload(guild_id));
+                    }
+                }
+                ui.separator();
+
+                ui.add(
+                    TextEdit::singleline(&mut self.track_input)
+                        .hint_text("Phrase to track")
+                        .desired_width(200.0),
+                );
+                if ui
+                    .button("Track")
+                    .on_hover_text("Phrases can also be tracked from the table by right clicking")
+                    .clicked()
+                {
+                    let phrase = std::mem::take(&mut self.track_input);
+                    self.track_phrase(&phrase, guild_id, event_bus);
+                }
+            });
+
+            let mut to_remove = None;
+            ui.horizontal_wrapped(|ui| {
+                for (index, phrase) in self.tracked_phrases.iter().enumerate() {
+                    if ui
+                        .button(format!("{phrase} ✖"))
+                        .on_hover_text("Stop tracking the phrase")
+                        .clicked()
+                    {
+                        to_remove = Some(index);
                     }
-                    break;
-                } else {
-                    let channel_id = self.channels.get(*index - 1).unwrap().channel_id;
-                    selected_channels.insert(channel_id);
                 }
+            });
+            if let Some(index) = to_remove {
+                let phrase = self.tracked_phrases.remove(index);
+                self.trend_data.remove(&phrase);
             }
-        }
 
-        let mut row_map: HashMap<String, WordRowData> = HashMap::new();
-        for (date, stripped_content) in &self.stripped_contents {
-            if !self.date_handler.within_range(*date) {
-                continue;
+            if self.trend_data.is_empty() {
+                ui.label("No phrase is being tracked");
+                return;
             }
 
-            for (channel_id, content_list) in stripped_content {
-                if !selected_channels.contains(channel_id) {
-                    continue;
-                }
+            let series: Vec<(&str, &BucketCounts)> = self
+                .trend_data
+                .iter()
+                .map(|(phrase, data)| (phrase.as_str(), data))
+                .collect();
+            show_bucket_plot(ui, "phrase_trends".to_string(), self.trend_type, &series);
+        });
+    }
 
-                for content in content_list {
-                    let split_stripped_content: Vec<&str> =
-                        content.split(' ').filter(|s| !s.is_empty()).collect();
-                    if split_stripped_content.len() < self.window_size {
-                        continue;
-                    }
+    /// Whether the message was sent by one of the users of the user filter
+    fn matches_user(&self, message: &MessageRow) -> bool {
+        if self.user_filter.is_empty() {
+            return true;
+        }
 
-                    let stripped_windows =
-                        get_stripped_windows(split_stripped_content, self.window_size);
+        let user_id = message.user_id().to_string();
+        let user = message.user();
+        self.user_filter.iter().any(|target| {
+            &user_id == target
+                || user.name.to_lowercase().contains(target)
+                || user.username.to_lowercase().contains(target)
+        })
+    }
 
-                    for phrase in stripped_windows {
-                        let word_row = WordRowData::new(phrase.clone());
-                        let entry = row_map.entry(phrase.clone()).or_insert(word_row);
-                        entry.increase_hits();
-                    }
-                }
-            }
+    /// Add the phrase to the tracked phrases. The words are matched the same way as the table
+    fn track_phrase(&mut self, phrase: &str, guild_id: i64, event_bus: &mut EventBus) {
+        let mut phrase = phrase.split_whitespace().collect::<Vec<_>>().join(" ");
+        if self.filter.case_folding {
+            phrase = phrase.to_lowercase();
+        }
+        if phrase.is_empty() || self.tracked_phrases.contains(&phrase) {
+            return;
         }
 
-        for row in row_map.values() {
-            self.table.add_modify_row(|_| Some(row.clone()));
+        self.tracked_phrases.push(phrase);
+        event_bus.publish(AppEvent::WordTableNeedsReload(guild_id));
+    }
+
+    /// Hits of every phrase with the current phrase size in the non-deleted messages that match
+    /// the query within the selected dates
+    fn count_phrases(&self, store: &MessageStore, query: MessageQuery) -> HashMap<String, u32> {
+        let query = query
+            .dates(self.date_handler.from, self.date_handler.to)
+            .skip_deleted();
+        let matcher = self.filter.matcher();
+        let mut phrases: HashMap<String, u32> = HashMap::new();
+
+        for message in store.query(query) {
+            add_phrases(&mut phrases, &message, self.window_size, &matcher);
         }
+        phrases
+    }
 
-        self.table.recreate_rows();
+    /// The most used phrases within the selected dates of the messages that match the query
+    fn top_phrases(&self, store: &MessageStore, query: MessageQuery) -> Vec<(String, u32)> {
+        let mut phrases: Vec<(String, u32)> =
+            self.count_phrases(store, query).into_iter().collect();
+        phrases.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        phrases.truncate(PROFILE_PHRASES);
+        phrases
     }
 
-    pub fn set_date_handler(&mut self, handler: DateHandler) {
-        self.date_handler = handler;
+    /// The most used phrases of the user with the current phrase size within the selected dates
+    /// and channels
+    pub fn user_phrases(&self, store: &MessageStore, user_id: i64) -> (usize, Vec<(String, u32)>) {
+        let selected_channels = selected_channel_ids(&self.channels, &self.selected_channels);
+        let query = MessageQuery::new()
+            .channels(selected_channels)
+            .user(user_id);
+        (self.window_size, self.top_phrases(store, query))
     }
 
-    pub fn set_channels(&mut self, channels: Vec<Channel>) {
-        self.channels = channels;
+    /// The most used phrases of the channel with the current phrase size within the selected dates
+    pub fn channel_phrases(
+        &self,
+        store: &MessageStore,
+        channel_id: i64,
+    ) -> (usize, Vec<(String, u32)>) {
+        let query = MessageQuery::new().channel(channel_id);
+        (self.window_size, self.top_phrases(store, query))
     }
-    pub fn set_selected_channels(&mut self, selected: HashSet<usize>) {
-        self.selected_channels = selected;
+
+    #[must_use]
+    pub fn window_size(&self) -> usize {
+        self.window_size
     }
-}
 
-impl TabHandler {
-    pub fn handle_message_word_table(
-        &mut self,
-        message: &MessageWithUser,
-        event_bus: &mut EventBus,
-    ) {
-        let guild_id = message.message.guild_id;
-        self.word_table
-            .get_mut(&guild_id)
-            .unwrap()
-            .handle_message(message, event_bus);
+    pub fn set_window_size(&mut self, window_size: usize) {
+        self.window_size = window_size.clamp(1, 20);
     }
 
-    pub fn word_table_recreate_rows(&mut self, guild_id: i64) {
-        self.word_table.get_mut(&guild_id).unwrap().create_rows();
+    #[must_use]
+    pub fn filter(&self) -> &WordFilter {
+        &self.filter
+    }
+
+    pub fn set_filter(&mut self, filter: WordFilter) {
+        self.filter_input = WordFilterInput::new(&filter);
+        self.filter = filter;
+    }
+
+    #[must_use]
+    pub fn tracked_phrases(&self) -> &[String] {
+        &self.tracked_phrases
+    }
+
+    pub fn set_tracked_phrases(&mut self, phrases: Vec<String>) {
+        self.tracked_phrases = phrases;
+    }
+
+    /// The rows that are currently shown in the table
+    fn export_data(&self) -> ExportData {
+        ExportData::from_rows("Common Words", &self.rows, |column: &WordColumn, row| {
+            column.column_text(row)
+        })
+    }
+}
+
+impl TabHandler {
+    pub fn word_table_export_data(&self, guild_id: i64) -> ExportData {
+        self.word_table.get(&guild_id).unwrap().export_data()
     }
 }
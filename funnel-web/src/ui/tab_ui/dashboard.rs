@@ -0,0 +1,609 @@
+use chrono::NaiveDateTime;
+use eframe::egui::ahash::{HashMap, HashMapExt, HashSet};
+use eframe::egui::{Align, Frame, Layout, RichText, ScrollArea, Slider, Ui};
+use egui_plot::{AxisHints, GridMark, Legend, Line, Plot, PlotPoint, PlotPoints};
+use funnel_shared::Channel;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::ops::RangeInclusive;
+use strum::IntoEnumIterator;
+
+use crate::core::{MessageQuery, MessageRow, MessageStore, selected_channel_ids, to_header};
+use crate::ui::{
+    Card, DateHandler, PhraseMatcher, ShowUI, StoreView, TabHandler, add_phrases, show_count_grid,
+};
+use crate::{AppEvent, ChartType, EventBus, ExportData, OverviewCard, TopTarget};
+
+/// Row counts that a top-N table can be pinned with
+const TOP_SIZES: [usize; 3] = [5, 10, 25];
+
+/// Key: Bucket start. Value: The count within the bucket
+pub type BucketCounts = BTreeMap<NaiveDateTime, u32>;
+
+/// A widget that can be pinned to a dashboard
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DashboardWidget {
+    OverviewCard(OverviewCard),
+    MessageChart(ChartType),
+    UserChart(ChartType),
+    TopTable(TopTarget, usize),
+}
+
+impl DashboardWidget {
+    fn title(self) -> String {
+        match self {
+            DashboardWidget::OverviewCard(card) => card.to_string(),
+            DashboardWidget::MessageChart(chart_type) => format!("{chart_type} Messages"),
+            DashboardWidget::UserChart(chart_type) => format!("{chart_type} Active Users"),
+            DashboardWidget::TopTable(target, size) => format!("Top {size} {target}"),
+        }
+    }
+}
+
+/// The pinned widgets of a guild. This is what is saved between sessions
+#[derive(Clone, Serialize, Deserialize)]
+pub struct DashboardLayout {
+    columns: usize,
+    widgets: Vec<DashboardWidget>,
+}
+
+impl Default for DashboardLayout {
+    fn default() -> Self {
+        Self {
+            columns: 2,
+            widgets: Vec::new(),
+        }
+    }
+}
+
+/// A change to the layout that was requested from a widget frame
+enum LayoutAction {
+    Remove(usize),
+    MoveBack(usize),
+    MoveForward(usize),
+}
+
+/// Values of the widgets that are built from the messages
+#[derive(Default)]
+struct DashboardData {
+    message_series: HashMap<ChartType, BucketCounts>,
+    user_series: HashMap<ChartType, BucketCounts>,
+    /// The most active users, channels or phrases with their message count or hits
+    top_rows: HashMap<TopTarget, Vec<(String, u32)>>,
+}
+
+/// Widget values of a reload that is still running
+#[derive(Default)]
+struct DashboardReload {
+    chart_types: Vec<ChartType>,
+    count_phrases: bool,
+    message_series: HashMap<ChartType, BucketCounts>,
+    user_series: HashMap<ChartType, BTreeMap<NaiveDateTime, HashSet<i64>>>,
+    /// Key: User ID. Value: Non-deleted messages
+    users: HashMap<i64, u32>,
+    /// Key: Channel ID. Value: Non-deleted messages
+    channels: HashMap<i64, u32>,
+    phrases: HashMap<String, u32>,
+}
+
+pub struct Dashboard {
+    layout: DashboardLayout,
+    data: DashboardData,
+    reload: DashboardReload,
+    /// Key: The pinned overview cards. Value: The current value in the Overview
+    overview_cards: HashMap<OverviewCard, String>,
+    date_handler: DateHandler,
+    channels: Vec<Channel>,
+    selected_channels: HashSet<usize>,
+    /// Key: Channel ID. Value: Channel name
+    channel_names: HashMap<i64, String>,
+    /// The word filter of the Common Words tab used to count the phrases
+    phrase_matcher: PhraseMatcher,
+}
+
+impl ShowUI for Dashboard {
+    fn show_ui(&mut self, ui: &mut Ui, guild_id: i64, event_bus: &mut EventBus) {
+        ui.horizontal(|ui| {
+            ui.menu_button("Pin Widget", |ui| {
+                if let Some(widget) = widget_menu(ui) {
+                    self.layout.widgets.push(widget);
+                    event_bus.publish(AppEvent::DashboardNeedsReload(guild_id));
+                }
+            });
+            ui.separator();
+            ui.label("Columns:");
+            ui.add(Slider::new(&mut self.layout.columns, 1..=4));
+        });
+        ui.separator();
+
+        if self.layout.widgets.is_empty() {
+            ui.vertical_centered(|ui| {
+                ui.heading("Use Pin Widget to add Overview cards, charts and tables here");
+            });
+            return;
+        }
+
+        let mut action = None;
+        let columns = self.layout.columns;
+        let last_index = self.layout.widgets.len() - 1;
+
+        ScrollArea::vertical().show(ui, |ui| {
+            for (row_index, row) in self.layout.widgets.chunks(columns).enumerate() {
+                ui.columns(columns, |column_uis| {
+                    for (column_index, widget) in row.iter().enumerate() {
+                        let index = row_index * columns + column_index;
+                        let ui = &mut column_uis[column_index];
+
+                        Frame::group(ui.style()).show(ui, |ui| {
+                            if let Some(widget_action) =
+                                widget_header(ui, *widget, index, index == last_index)
+                            {
+                                action = Some(widget_action);
+                            }
+                            self.show_widget(ui, *widget, index);
+                        });
+                    }
+                });
+                ui.add_space(5.0);
+            }
+        });
+
+        if let Some(action) = action {
+            let widgets = &mut self.layout.widgets;
+            match action {
+                LayoutAction::Remove(index) => {
+                    widgets.remove(index);
+                }
+                LayoutAction::MoveBack(index) => widgets.swap(index, index - 1),
+                LayoutAction::MoveForward(index) => widgets.swap(index, index + 1),
+            }
+        }
+    }
+}
+
+/// Title of the widget with the buttons to move and remove it
+fn widget_header(
+    ui: &mut Ui,
+    widget: DashboardWidget,
+    index: usize,
+    is_last: bool,
+) -> Option<LayoutAction> {
+    let mut action = None;
+
+    ui.horizontal(|ui| {
+        ui.label(RichText::new(widget.title()).strong());
+        ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+            if ui.small_button("✖").on_hover_text("Unpin").clicked() {
+                action = Some(LayoutAction::Remove(index));
+            }
+            if !is_last && ui.small_button("▶").on_hover_text("Move forward").clicked() {
+                action = Some(LayoutAction::MoveForward(index));
+            }
+            if index > 0 && ui.small_button("◀").on_hover_text("Move back").clicked() {
+                action = Some(LayoutAction::MoveBack(index));
+            }
+        });
+    });
+    action
+}
+
+/// The menu with every widget that can be pinned. Returns the widget that was clicked
+fn widget_menu(ui: &mut Ui) -> Option<DashboardWidget> {
+    let mut selected = None;
+
+    ui.menu_button("Overview Card", |ui| {
+        for card in OverviewCard::iter() {
+            if ui.button(card.to_string()).clicked() {
+                selected = Some(DashboardWidget::OverviewCard(card));
+            }
+        }
+    });
+    ui.menu_button("Message Chart", |ui| {
+        for chart_type in ChartType::iter() {
+            if ui.button(chart_type.to_string()).clicked() {
+                selected = Some(DashboardWidget::MessageChart(chart_type));
+            }
+        }
+    });
+    ui.menu_button("Active User Chart", |ui| {
+        for chart_type in ChartType::iter() {
+            if ui.button(chart_type.to_string()).clicked() {
+                selected = Some(DashboardWidget::UserChart(chart_type));
+            }
+        }
+    });
+    for target in TopTarget::iter() {
+        ui.menu_button(format!("Top {target}"), |ui| {
+            for size in TOP_SIZES {
+                if ui.button(format!("Top {size}")).clicked() {
+                    selected = Some(DashboardWidget::TopTable(target, size));
+                }
+            }
+        });
+    }
+
+    if selected.is_some() {
+        ui.close();
+    }
+    selected
+}
+
+/// Plot every series with one point per bucket. Every series must have the same buckets
+pub fn show_bucket_plot(
+    ui: &mut Ui,
+    id: String,
+    chart_type: ChartType,
+    series: &[(&str, &BucketCounts)],
+) {
+    let buckets: Vec<NaiveDateTime> = series
+        .first()
+        .map(|(_, data)| data.keys().copied().collect())
+        .unwrap_or_default();
+    let format_bucket = move |value: f64| {
+        buckets
+            .get(value.round().max(0.0) as usize)
+            .map(|bucket| match chart_type {
+                ChartType::Hourly => bucket.format("%y-%m-%d %H:00").to_string(),
+                _ => bucket.format("%y-%m-%d").to_string(),
+            })
+            .unwrap_or_default()
+    };
+    let axis_format = format_bucket.clone();
+    let date_axis = move |mark: GridMark, _range: &RangeInclusive<f64>| axis_format(mark.value);
+    let hover_label = move |name: &str, val: &PlotPoint| {
+        format!("{}\n{name}: {:.0}", format_bucket(val.x), val.y)
+    };
+
+    Plot::new(id)
+        .height(200.0)
+        .legend(Legend::default().background_alpha(0.0))
+        .custom_x_axes(vec![AxisHints::new_x().formatter(date_axis)])
+        .label_formatter(hover_label)
+        .clamp_grid(true)
+        .show(ui, |plot_ui| {
+            for (name, data) in series {
+                let points: PlotPoints = data
+                    .values()
+                    .enumerate()
+                    .map(|(index, count)| [index as f64, f64::from(*count)])
+                    .collect();
+                plot_ui.line(Line::new(*name, points).name(name));
+            }
+        });
+}
+
+impl StoreView for Dashboard {
+    fn start_reload(&mut self, _store: &MessageStore) -> Option<MessageQuery> {
+        let mut reload = DashboardReload::default();
+
+        for widget in &self.layout.widgets {
+            match widget {
+                DashboardWidget::MessageChart(chart_type)
+                | DashboardWidget::UserChart(chart_type) => {
+                    if !reload.chart_types.contains(chart_type) {
+                        reload.chart_types.push(*chart_type);
+                    }
+                }
+                DashboardWidget::TopTable(TopTarget::Phrases, _) => reload.count_phrases = true,
+                DashboardWidget::OverviewCard(_) | DashboardWidget::TopTable(_, _) => {}
+            }
+        }
+
+        // Every bucket within the dates is shown even if no message was sent in it
+        for chart_type in &reload.chart_types {
+            let buckets = chart_type.buckets(self.date_handler.from, self.date_handler.to);
+            let messages = reload.message_series.entry(*chart_type).or_default();
+            let users = reload.user_series.entry(*chart_type).or_default();
+            for bucket in buckets {
+                messages.insert(bucket, 0);
+                users.insert(bucket, HashSet::default());
+            }
+        }
+        self.reload = reload;
+
+        let selected_channels = selected_channel_ids(&self.channels, &self.selected_channels);
+        let query = MessageQuery::new()
+            .dates(self.date_handler.from, self.date_handler.to)
+            .channels(selected_channels);
+        Some(query)
+    }
+
+    fn add_message(&mut self, message: &MessageRow) {
+        let reload = &mut self.reload;
+
+        for chart_type in &reload.chart_types {
+            let bucket = chart_type.bucket_start(message.time());
+            if let Some(count) = reload
+                .message_series
+                .get_mut(chart_type)
+                .and_then(|series| series.get_mut(&bucket))
+            {
+                *count += 1;
+            }
+            if let Some(users) = reload
+                .user_series
+                .get_mut(chart_type)
+                .and_then(|series| series.get_mut(&bucket))
+            {
+                users.insert(message.user_id());
+            }
+        }
+
+        if message.is_deleted() {
+            return;
+        }
+
+        *reload.users.entry(message.user_id()).or_default() += 1;
+        *reload.channels.entry(message.channel_id()).or_default() += 1;
+
+        if reload.count_phrases {
+            add_phrases(&mut reload.phrases, message, 1, &self.phrase_matcher);
+        }
+    }
+
+    fn finish_reload(&mut self, store: &MessageStore) {
+        let reload = std::mem::take(&mut self.reload);
+
+        let user_series = reload
+            .user_series
+            .into_iter()
+            .map(|(chart_type, series)| {
+                let counts = series
+                    .into_iter()
+                    .map(|(bucket, users)| (bucket, users.len() as u32))
+                    .collect();
+                (chart_type, counts)
+            })
+            .collect();
+
+        let max_size = TOP_SIZES[TOP_SIZES.len() - 1];
+        let mut top_rows = HashMap::new();
+        top_rows.insert(
+            TopTarget::Users,
+            top_counts(reload.users, max_size, |user_id| {
+                store
+                    .user(*user_id)
+                    .map_or_else(|| user_id.to_string(), |user| user.username.clone())
+            }),
+        );
+        top_rows.insert(
+            TopTarget::Channels,
+            top_counts(reload.channels, max_size, |channel_id| {
+                self.channel_names
+                    .get(channel_id)
+                    .map_or_else(|| channel_id.to_string(), String::clone)
+            }),
+        );
+        top_rows.insert(
+            TopTarget::Phrases,
+            top_counts(reload.phrases, max_size, String::clone),
+        );
+
+        self.data = DashboardData {
+            message_series: reload.message_series,
+            user_series,
+            top_rows,
+        };
+    }
+
+    fn set_date_handler(&mut self, handler: DateHandler) {
+        self.date_handler = handler;
+    }
+
+    fn set_channels(&mut self, channels: Vec<Channel>) {
+        for channel in &channels {
+            self.channel_names
+                .insert(channel.channel_id, channel.channel_name.clone());
+        }
+        self.channels = channels;
+    }
+
+    fn set_selected_channels(&mut self, selected: HashSet<usize>) {
+        self.selected_channels = selected;
+    }
+}
+
+/// The keys with the highest counts resolved to their names
+fn top_counts<K: Ord>(
+    counts: HashMap<K, u32>,
+    size: usize,
+    name: impl Fn(&K) -> String,
+) -> Vec<(String, u32)> {
+    let mut counts: Vec<(K, u32)> = counts.into_iter().collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    counts.truncate(size);
+
+    counts
+        .into_iter()
+        .map(|(key, count)| (name(&key), count))
+        .collect()
+}
+
+impl Dashboard {
+    #[must_use]
+    pub fn new(layout: DashboardLayout) -> Self {
+        Self {
+            layout,
+            data: DashboardData::default(),
+            reload: DashboardReload::default(),
+            overview_cards: HashMap::new(),
+            date_handler: DateHandler::default(),
+            channels: Vec::default(),
+            selected_channels: HashSet::default(),
+            channel_names: HashMap::new(),
+            phrase_matcher: PhraseMatcher::default(),
+        }
+    }
+
+    #[must_use]
+    pub fn layout(&self) -> &DashboardLayout {
+        &self.layout
+    }
+
+    /// Overview cards that are pinned to the dashboard
+    pub fn pinned_cards(&self) -> impl Iterator<Item = OverviewCard> + '_ {
+        self.layout
+            .widgets
+            .iter()
+            .filter_map(|widget| match widget {
+                DashboardWidget::OverviewCard(card) => Some(*card),
+                _ => None,
+            })
+    }
+
+    pub fn set_overview_cards(&mut self, cards: HashMap<OverviewCard, String>) {
+        self.overview_cards = cards;
+    }
+
+    pub fn set_phrase_matcher(&mut self, matcher: PhraseMatcher) {
+        self.phrase_matcher = matcher;
+    }
+
+    fn show_widget(&self, ui: &mut Ui, widget: DashboardWidget, index: usize) {
+        match widget {
+            DashboardWidget::OverviewCard(card) => {
+                let value = self.overview_cards.get(&card).map_or("", String::as_str);
+                ui.add(Card::new(
+                    to_header(card.to_string()),
+                    to_header(value),
+                    ui.available_width(),
+                    60.0,
+                ));
+            }
+            DashboardWidget::MessageChart(chart_type) => {
+                if let Some(series) = self.data.message_series.get(&chart_type) {
+                    show_bucket_plot(
+                        ui,
+                        format!("dashboard_plot_{index}"),
+                        chart_type,
+                        &[("Messages", series)],
+                    );
+                }
+            }
+            DashboardWidget::UserChart(chart_type) => {
+                if let Some(series) = self.data.user_series.get(&chart_type) {
+                    show_bucket_plot(
+                        ui,
+                        format!("dashboard_plot_{index}"),
+                        chart_type,
+                        &[("Active Users", series)],
+                    );
+                }
+            }
+            DashboardWidget::TopTable(target, size) => {
+                let rows = self
+                    .data
+                    .top_rows
+                    .get(&target)
+                    .map_or(&[][..], |rows| &rows[..size.min(rows.len())]);
+                let headers = match target {
+                    TopTarget::Users => ["User", "Messages"],
+                    TopTarget::Channels => ["Channel", "Messages"],
+                    TopTarget::Phrases => ["Phrase", "Hits"],
+                };
+                show_count_grid(
+                    ui,
+                    &format!("dashboard_table_{index}"),
+                    headers,
+                    rows,
+                    "Nothing found within the selected dates",
+                );
+            }
+        }
+    }
+
+    /// Every value of the pinned widgets with one row per card, bucket or table row
+    fn export_data(&self) -> ExportData {
+        let headers = vec![
+            "Widget".to_string(),
+            "Label".to_string(),
+            "Value".to_string(),
+        ];
+        let mut data = ExportData::new("Dashboard", headers);
+
+        for widget in &self.layout.widgets {
+            let title = widget.title();
+            let rows: Vec<(String, String)> = match widget {
+                DashboardWidget::OverviewCard(card) => {
+                    let value = self.overview_cards.get(card).cloned().unwrap_or_default();
+                    vec![(String::new(), value)]
+                }
+                DashboardWidget::MessageChart(chart_type)
+                | DashboardWidget::UserChart(chart_type) => {
+                    let series = if matches!(widget, DashboardWidget::MessageChart(_)) {
+                        self.data.message_series.get(chart_type)
+                    } else {
+                        self.data.user_series.get(chart_type)
+                    };
+                    series
+                        .into_iter()
+                        .flatten()
+                        .map(|(bucket, count)| (bucket.to_string(), count.to_string()))
+                        .collect()
+                }
+                DashboardWidget::TopTable(target, size) => self
+                    .data
+                    .top_rows
+                    .get(target)
+                    .into_iter()
+                    .flatten()
+                    .take(*size)
+                    .map(|(name, count)| (name.clone(), count.to_string()))
+                    .collect(),
+            };
+
+            for (label, value) in rows {
+                data.add_row(vec![title.clone(), label, value]);
+            }
+        }
+        data
+    }
+}
+
+impl TabHandler {
+    pub fn dashboard_export_data(&self, guild_id: i64) -> ExportData {
+        self.dashboard.get(&guild_id).unwrap().export_data()
+    }
+
+    /// Give the dashboard of the guild the current value of every pinned Overview card
+    pub fn update_dashboard_cards(&mut self, guild_id: i64) {
+        let (Some(overview), Some(dashboard)) = (
+            self.overview.get(&guild_id),
+            self.dashboard.get_mut(&guild_id),
+        ) else {
+            return;
+        };
+
+        let cards = dashboard
+            .pinned_cards()
+            .map(|card| (card, overview.card_value(card)))
+            .collect();
+        dashboard.set_overview_cards(cards);
+    }
+
+    /// Give the dashboard of the guild the current word filter of the Common Words tab
+    pub fn update_dashboard_matcher(&mut self, guild_id: i64) {
+        let (Some(word_table), Some(dashboard)) = (
+            self.word_table.get(&guild_id),
+            self.dashboard.get_mut(&guild_id),
+        ) else {
+            return;
+        };
+
+        dashboard.set_phrase_matcher(word_table.filter().matcher());
+    }
+
+    /// The layout of every guild including the saved ones of guilds that were not opened
+    #[must_use]
+    pub fn dashboard_layouts(&self) -> HashMap<i64, DashboardLayout> {
+        let mut layouts = self.saved_layouts.clone();
+        for (guild_id, dashboard) in &self.dashboard {
+            layouts.insert(*guild_id, dashboard.layout().clone());
+        }
+        layouts
+    }
+
+    pub fn set_saved_layouts(&mut self, layouts: HashMap<i64, DashboardLayout>) {
+        self.saved_layouts = layouts;
+    }
+}
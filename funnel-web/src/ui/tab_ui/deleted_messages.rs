@@ -0,0 +1,443 @@
+use chrono::{NaiveDateTime, TimeDelta};
+use eframe::egui::ahash::{HashMap, HashMapExt, HashSet};
+use eframe::egui::{Align, Button, CollapsingHeader, Layout, Response, RichText, Ui};
+use egui_extras::Column;
+use egui_selectable_table::{
+    ColumnOperations, ColumnOrdering, SelectableRow, SelectableTable, SortOrder,
+};
+use funnel_shared::Channel;
+use std::cmp::Ordering;
+use strum::IntoEnumIterator;
+
+use crate::core::{DeletedColumn, MessageQuery, MessageRow, MessageStore, selected_channel_ids};
+use crate::ui::{DateHandler, ShowUI, StoreView, TabHandler, show_count_grid};
+use crate::{AppEvent, EventBus, ExportData};
+
+/// Number of users and channels that are shown with the most deletions
+const TOP_DELETIONS: usize = 10;
+
+/// Shown in place of the content when the message was deleted before its content was saved
+const NO_CONTENT: &str = "Content not available";
+
+#[derive(Default)]
+pub struct Config {
+    copy_selected: bool,
+    view_profile: Option<i64>,
+}
+
+impl ColumnOperations<DeletedRowData, DeletedColumn, Config> for DeletedColumn {
+    fn column_text(&self, row: &DeletedRowData) -> String {
+        match self {
+            DeletedColumn::DeleteTime => row.delete_time.to_string(),
+            DeletedColumn::SentTime => row.sent_time.to_string(),
+            DeletedColumn::TimeToDeletion => format_delta(row.time_to_deletion()),
+            DeletedColumn::Author => row.author.to_string(),
+            DeletedColumn::Username => row.username.to_string(),
+            DeletedColumn::Channel => row.channel.to_string(),
+            DeletedColumn::Content => row.content.clone().unwrap_or_default(),
+        }
+    }
+
+    fn create_header(
+        &self,
+        ui: &mut Ui,
+        sort_order: Option<SortOrder>,
+        _table: &mut SelectableTable<DeletedRowData, DeletedColumn, Config>,
+    ) -> Option<Response> {
+        let mut label_text = self.to_string();
+        let hover_text = match self {
+            DeletedColumn::DeleteTime => {
+                "The date and time the message was deleted. Click to sort by delete time"
+                    .to_string()
+            }
+            DeletedColumn::SentTime => {
+                "The date and time the message was sent. Click to sort by send time".to_string()
+            }
+            DeletedColumn::TimeToDeletion => {
+                "How long the message existed before it was deleted. Click to sort by time to deletion"
+                    .to_string()
+            }
+            DeletedColumn::Author => {
+                "The global name of the user that sent the message. Click to sort by name"
+                    .to_string()
+            }
+            DeletedColumn::Username => {
+                "The username of the user that sent the message. Click to sort by username"
+                    .to_string()
+            }
+            DeletedColumn::Channel => {
+                "The channel the message was sent to. Click to sort by channel".to_string()
+            }
+            DeletedColumn::Content => {
+                "The original content of the message if it was saved before the deletion"
+                    .to_string()
+            }
+        };
+
+        let is_selected = if let Some(direction) = sort_order {
+            match direction {
+                SortOrder::Ascending => label_text += " ↓",
+                SortOrder::Descending => label_text += " ↑",
+            }
+            true
+        } else {
+            false
+        };
+
+        let label_text = RichText::new(label_text).strong();
+
+        let response = ui
+            .add_sized(
+                ui.available_size(),
+                Button::selectable(is_selected, label_text),
+            )
+            .on_hover_text(hover_text);
+        Some(response)
+    }
+
+    fn create_table_row(
+        &self,
+        ui: &mut Ui,
+        row: &SelectableRow<DeletedRowData, DeletedColumn>,
+        column_selected: bool,
+        table: &mut SelectableTable<DeletedRowData, DeletedColumn, Config>,
+    ) -> Response {
+        let row_data = &row.row_data;
+        let mut show_tooltip = false;
+        let row_text = match self {
+            DeletedColumn::DeleteTime => row_data.delete_time.to_string(),
+            DeletedColumn::SentTime => row_data.sent_time.to_string(),
+            DeletedColumn::TimeToDeletion => format_delta(row_data.time_to_deletion()),
+            DeletedColumn::Author => row_data.author.clone(),
+            DeletedColumn::Username => row_data.username.clone(),
+            DeletedColumn::Channel => row_data.channel.clone(),
+            DeletedColumn::Content => {
+                show_tooltip = true;
+                row_data
+                    .content
+                    .clone()
+                    .unwrap_or_else(|| NO_CONTENT.to_string())
+            }
+        };
+        let is_selected = column_selected;
+
+        // Multi-line messages are shown in a single line. The tooltip has the full content
+        let mut label = ui.add_sized(
+            ui.available_size(),
+            Button::selectable(is_selected, row_text.replace('\n', " ")),
+        );
+
+        if show_tooltip {
+            label = label.on_hover_text(row_text);
+        }
+
+        if label.double_clicked() {
+            table.config.view_profile = Some(row_data.user_id);
+        }
+
+        label.context_menu(|ui| {
+            if ui.button("Copy selected rows").clicked() {
+                table.config.copy_selected = true;
+                ui.close();
+            }
+            if ui.button("View author profile").clicked() {
+                table.config.view_profile = Some(row_data.user_id);
+                ui.close();
+            }
+        });
+        label
+    }
+}
+
+impl ColumnOrdering<DeletedRowData> for DeletedColumn {
+    fn order_by(&self, row_1: &DeletedRowData, row_2: &DeletedRowData) -> Ordering {
+        match self {
+            DeletedColumn::DeleteTime => row_1.delete_time.cmp(&row_2.delete_time),
+            DeletedColumn::SentTime => row_1.sent_time.cmp(&row_2.sent_time),
+            DeletedColumn::TimeToDeletion => {
+                row_1.time_to_deletion().cmp(&row_2.time_to_deletion())
+            }
+            DeletedColumn::Author => row_1.author.cmp(&row_2.author),
+            DeletedColumn::Username => row_1.username.cmp(&row_2.username),
+            DeletedColumn::Channel => row_1.channel.cmp(&row_2.channel),
+            DeletedColumn::Content => row_1.content.cmp(&row_2.content),
+        }
+    }
+}
+
+/// Time delta as days, hours, minutes and seconds while leaving out the leading zero units
+fn format_delta(delta: TimeDelta) -> String {
+    let seconds = delta.num_seconds().max(0);
+    let (days, hours, minutes, seconds) = (
+        seconds / 86_400,
+        seconds % 86_400 / 3_600,
+        seconds % 3_600 / 60,
+        seconds % 60,
+    );
+
+    if days > 0 {
+        format!("{days}d {hours}h {minutes}m {seconds}s")
+    } else if hours > 0 {
+        format!("{hours}h {minutes}m {seconds}s")
+    } else if minutes > 0 {
+        format!("{minutes}m {seconds}s")
+    } else {
+        format!("{seconds}s")
+    }
+}
+
+#[derive(Clone, Debug)]
+struct DeletedRowData {
+    delete_time: NaiveDateTime,
+    sent_time: NaiveDateTime,
+    author: String,
+    username: String,
+    user_id: i64,
+    channel: String,
+    content: Option<String>,
+}
+
+impl DeletedRowData {
+    fn time_to_deletion(&self) -> TimeDelta {
+        self.delete_time - self.sent_time
+    }
+}
+
+/// Deletions of a reload that is still running
+#[derive(Default)]
+struct DeletedReload {
+    rows: Vec<DeletedRowData>,
+    /// Key: User ID. Value: Deleted messages
+    user_deletions: HashMap<i64, u32>,
+    /// Key: Channel ID. Value: Deleted messages
+    channel_deletions: HashMap<i64, u32>,
+    total_delta: TimeDelta,
+}
+
+pub struct DeletedMessages {
+    table: SelectableTable<DeletedRowData, DeletedColumn, Config>,
+    /// The rows that are shown in the table
+    rows: Vec<DeletedRowData>,
+    /// Read only currently selected dates in the UI
+    date_handler: DateHandler,
+    total_deleted: u32,
+    average_time_to_deletion: TimeDelta,
+    /// Name of the users with the most deletions with their deleted message count
+    top_users: Vec<(String, u32)>,
+    /// Name of the channels with the most deletions with their deleted message count
+    top_channels: Vec<(String, u32)>,
+    reload: DeletedReload,
+    channels: Vec<Channel>,
+    selected_channels: HashSet<usize>,
+    /// Key: Channel ID. Value: Channel name
+    channel_names: HashMap<i64, String>,
+}
+
+impl Default for DeletedMessages {
+    fn default() -> Self {
+        let table = SelectableTable::new(DeletedColumn::iter().collect())
+            .auto_scroll()
+            .horizontal_scroll()
+            .serial_column();
+        Self {
+            table,
+            rows: Vec::new(),
+            date_handler: DateHandler::default(),
+            total_deleted: 0,
+            average_time_to_deletion: TimeDelta::zero(),
+            top_users: Vec::new(),
+            top_channels: Vec::new(),
+            reload: DeletedReload::default(),
+            channels: Vec::default(),
+            selected_channels: HashSet::default(),
+            channel_names: HashMap::new(),
+        }
+    }
+}
+
+impl ShowUI for DeletedMessages {
+    fn show_ui(&mut self, ui: &mut Ui, guild_id: i64, event_bus: &mut EventBus) {
+        let to_copy = self.table.config.copy_selected;
+        if to_copy {
+            self.table.config.copy_selected = false;
+            self.table.copy_selected_cells(ui);
+            event_bus.publish(AppEvent::CellsCopied);
+        }
+
+        if let Some(user_id) = self.table.config.view_profile.take() {
+            event_bus.publish(AppEvent::OpenUserProfile(guild_id, user_id));
+        }
+
+        ui.horizontal(|ui| {
+            ui.label(format!("Deleted Messages: {}", self.total_deleted));
+            ui.separator();
+            ui.label(format!(
+                "Average Time To Deletion: {}",
+                format_delta(self.average_time_to_deletion)
+            ));
+        });
+
+        CollapsingHeader::new("Most Deletions")
+            .default_open(true)
+            .show(ui, |ui| {
+                ui.columns(2, |columns| {
+                    show_count_grid(
+                        &mut columns[0],
+                        "deleted_top_users",
+                        ["User", "Deleted"],
+                        &self.top_users,
+                        "No deleted messages",
+                    );
+                    show_count_grid(
+                        &mut columns[1],
+                        "deleted_top_channels",
+                        ["Channel", "Deleted"],
+                        &self.top_channels,
+                        "No deleted messages",
+                    );
+                });
+            });
+        ui.separator();
+        ui.add_space(5.0);
+
+        self.table.show_ui(ui, |builder| {
+            let mut table = builder
+                .striped(true)
+                .resizable(true)
+                .cell_layout(Layout::left_to_right(Align::Center))
+                .drag_to_scroll(false)
+                .auto_shrink([false; 2])
+                .min_scrolled_height(0.0);
+
+            for column in DeletedColumn::iter() {
+                let column = match column {
+                    DeletedColumn::DeleteTime | DeletedColumn::SentTime => Column::initial(150.0),
+                    DeletedColumn::Content => Column::initial(500.0).clip(true),
+                    _ => Column::initial(100.0).clip(true),
+                };
+                table = table.column(column);
+            }
+            table
+        });
+    }
+}
+
+impl StoreView for DeletedMessages {
+    fn start_reload(&mut self, _store: &MessageStore) -> Option<MessageQuery> {
+        self.reload = DeletedReload::default();
+
+        let selected_channels = selected_channel_ids(&self.channels, &self.selected_channels);
+        let query = MessageQuery::new()
+            .dates(self.date_handler.from, self.date_handler.to)
+            .channels(selected_channels)
+            .only_deleted();
+        Some(query)
+    }
+
+    fn add_message(&mut self, message: &MessageRow) {
+        let Some(delete_time) = message.delete_time() else {
+            return;
+        };
+
+        let user = message.user();
+        let channel_id = message.channel_id();
+        let row = DeletedRowData {
+            delete_time,
+            sent_time: message.sent_time(),
+            author: user.name.clone(),
+            username: user.username.clone(),
+            user_id: message.user_id(),
+            channel: self.channel_name(channel_id),
+            content: message.content().map(ToString::to_string),
+        };
+
+        self.reload.total_delta += row.time_to_deletion();
+        *self
+            .reload
+            .user_deletions
+            .entry(message.user_id())
+            .or_default() += 1;
+        *self.reload.channel_deletions.entry(channel_id).or_default() += 1;
+        self.reload.rows.push(row);
+    }
+
+    /// Create the rows and the deletion summary that will be shown in the UI
+    fn finish_reload(&mut self, store: &MessageStore) {
+        let reload = std::mem::take(&mut self.reload);
+        self.table.clear_all_rows();
+
+        self.total_deleted = reload.rows.len() as u32;
+        self.average_time_to_deletion = if self.total_deleted == 0 {
+            TimeDelta::zero()
+        } else {
+            reload.total_delta / self.total_deleted as i32
+        };
+
+        self.top_users = top_deletions(reload.user_deletions, |user_id| {
+            store
+                .user(user_id)
+                .map_or_else(|| user_id.to_string(), |user| user.username.clone())
+        });
+        self.top_channels = top_deletions(reload.channel_deletions, |channel_id| {
+            self.channel_name(channel_id)
+        });
+
+        for row in &reload.rows {
+            self.table.add_modify_row(|_| Some(row.clone()));
+        }
+        self.rows = reload.rows;
+        self.table.recreate_rows();
+    }
+
+    fn set_date_handler(&mut self, handler: DateHandler) {
+        self.date_handler = handler;
+    }
+
+    fn set_channels(&mut self, channels: Vec<Channel>) {
+        for channel in &channels {
+            self.channel_names
+                .insert(channel.channel_id, channel.channel_name.clone());
+        }
+        self.channels = channels;
+    }
+
+    fn set_selected_channels(&mut self, selected: HashSet<usize>) {
+        self.selected_channels = selected;
+    }
+}
+
+/// The IDs with the most deletions resolved to their names
+fn top_deletions(deletions: HashMap<i64, u32>, name: impl Fn(i64) -> String) -> Vec<(String, u32)> {
+    let mut deletions: Vec<(i64, u32)> = deletions.into_iter().collect();
+    deletions.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    deletions.truncate(TOP_DELETIONS);
+
+    deletions
+        .into_iter()
+        .map(|(id, count)| (name(id), count))
+        .collect()
+}
+
+impl DeletedMessages {
+    /// Name of the channel or its ID if the channel is unknown
+    fn channel_name(&self, channel_id: i64) -> String {
+        self.channel_names
+            .get(&channel_id)
+            .map_or_else(|| channel_id.to_string(), String::clone)
+    }
+
+    /// The deleted messages that are currently shown in the table
+    fn export_data(&self) -> ExportData {
+        ExportData::from_rows(
+            "Deleted Messages",
+            &self.rows,
+            |column: &DeletedColumn, row| column.column_text(row),
+        )
+    }
+}
+
+impl TabHandler {
+    pub fn deleted_messages_export_data(&self, guild_id: i64) -> ExportData {
+        self.deleted_messages.get(&guild_id).unwrap().export_data()
+    }
+}
@@ -1,12 +1,13 @@
 use eframe::egui::Ui;
 use eframe::egui::ahash::{HashMap, HashSet};
-use funnel_shared::Channel;
-use log::info;
+use funnel_shared::{Channel, MemberActivity, MemberCount, MessageWithUser, PAGE_VALUE};
 
 use crate::ui::{
-    ChannelTable, DateHandler, MessageChart, Overview, UserChart, UserTable, WordTable,
+    ChannelProfile, ChannelTable, Cohorts, Dashboard, DashboardLayout, DateHandler,
+    DeletedMessages, Heatmap, Interactions, Media, MessageChart, MessageSearch, Overview,
+    RunningReload, UserChart, UserProfile, UserTable, WordTable,
 };
-use crate::{EventBus, TabState};
+use crate::{AppEvent, EventBus, ExportData, GuildUiState, MessageStore, TabState, Timezone};
 
 #[derive(Clone, Copy, Eq, PartialEq)]
 pub enum ReloadTab {
@@ -16,29 +17,116 @@ pub enum ReloadTab {
     WordTable(i64),
     MessageChart(i64),
     UserChart(i64),
+    Heatmap(i64),
+    MessageSearch(i64),
+    DeletedMessages(i64),
+    Dashboard(i64),
+    Cohorts(i64),
+    Interactions(i64),
+    Media(i64),
+}
+
+impl ReloadTab {
+    /// The reload of the tab of the guild
+    #[must_use]
+    pub fn new(state: TabState, guild_id: i64) -> Self {
+        match state {
+            TabState::Overview => ReloadTab::Overview(guild_id),
+            TabState::UserTable => ReloadTab::UserTable(guild_id),
+            TabState::ChannelTable => ReloadTab::ChannelTable(guild_id),
+            TabState::CommonWords => ReloadTab::WordTable(guild_id),
+            TabState::MessageChart => ReloadTab::MessageChart(guild_id),
+            TabState::UserChart => ReloadTab::UserChart(guild_id),
+            TabState::Heatmap => ReloadTab::Heatmap(guild_id),
+            TabState::MessageSearch => ReloadTab::MessageSearch(guild_id),
+            TabState::DeletedMessages => ReloadTab::DeletedMessages(guild_id),
+            TabState::Dashboard => ReloadTab::Dashboard(guild_id),
+            TabState::Cohorts => ReloadTab::Cohorts(guild_id),
+            TabState::Interactions => ReloadTab::Interactions(guild_id),
+            TabState::Media => ReloadTab::Media(guild_id),
+        }
+    }
+
+    #[must_use]
+    pub fn guild_id(self) -> i64 {
+        match self {
+            ReloadTab::Overview(guild_id)
+            | ReloadTab::UserTable(guild_id)
+            | ReloadTab::ChannelTable(guild_id)
+            | ReloadTab::WordTable(guild_id)
+            | ReloadTab::MessageChart(guild_id)
+            | ReloadTab::UserChart(guild_id)
+            | ReloadTab::Heatmap(guild_id)
+            | ReloadTab::MessageSearch(guild_id)
+            | ReloadTab::DeletedMessages(guild_id)
+            | ReloadTab::Dashboard(guild_id)
+            | ReloadTab::Cohorts(guild_id)
+            | ReloadTab::Interactions(guild_id)
+            | ReloadTab::Media(guild_id) => guild_id,
+        }
+    }
+
+    /// The tab that shows the reloaded data
+    #[must_use]
+    pub fn tab_state(self) -> TabState {
+        match self {
+            ReloadTab::Overview(_) => TabState::Overview,
+            ReloadTab::UserTable(_) => TabState::UserTable,
+            ReloadTab::ChannelTable(_) => TabState::ChannelTable,
+            ReloadTab::WordTable(_) => TabState::CommonWords,
+            ReloadTab::MessageChart(_) => TabState::MessageChart,
+            ReloadTab::UserChart(_) => TabState::UserChart,
+            ReloadTab::Heatmap(_) => TabState::Heatmap,
+            ReloadTab::MessageSearch(_) => TabState::MessageSearch,
+            ReloadTab::DeletedMessages(_) => TabState::DeletedMessages,
+            ReloadTab::Dashboard(_) => TabState::Dashboard,
+            ReloadTab::Cohorts(_) => TabState::Cohorts,
+            ReloadTab::Interactions(_) => TabState::Interactions,
+            ReloadTab::Media(_) => TabState::Media,
+        }
+    }
 }
 
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub struct PendingReload {
-    guild_id: i64,
-    reload_type: ReloadTab,
+    pub(super) guild_id: i64,
+    pub(super) reload_type: ReloadTab,
 }
 
 #[derive(Default)]
 pub struct TabHandler {
     pub current_guild: i64,
+    /// Every message of each guild. The tabs are built from it when they are reloaded
+    pub message_store: HashMap<i64, MessageStore>,
     pub overview: HashMap<i64, Overview>,
     pub user_table: HashMap<i64, UserTable>,
     pub channel_table: HashMap<i64, ChannelTable>,
     pub message_chart: HashMap<i64, MessageChart>,
     pub user_chart: HashMap<i64, UserChart>,
     pub word_table: HashMap<i64, WordTable>,
+    pub heatmap: HashMap<i64, Heatmap>,
+    pub message_search: HashMap<i64, MessageSearch>,
+    pub deleted_messages: HashMap<i64, DeletedMessages>,
+    pub dashboard: HashMap<i64, Dashboard>,
+    pub cohorts: HashMap<i64, Cohorts>,
+    pub interactions: HashMap<i64, Interactions>,
+    pub media: HashMap<i64, Media>,
+    /// Dashboard layouts that were saved in a previous session. Key: Guild ID
+    pub saved_layouts: HashMap<i64, DashboardLayout>,
+    pub user_profile: Option<UserProfile>,
+    pub channel_profile: Option<ChannelProfile>,
+    pub timezone: Timezone,
     pub pending_reloads: Vec<PendingReload>,
+    pub running_reload: Option<RunningReload>,
 }
 
 impl TabHandler {
     pub fn show_tab_ui(&mut self, ui: &mut Ui, state: TabState, event_bus: &mut EventBus) {
         self.process_pending_reloads(state);
+        self.show_reload_progress(ui, state);
+        if state == TabState::Dashboard {
+            self.update_dashboard_cards(self.current_guild);
+        }
         let mut show_ui = |data: Option<&mut dyn ShowUI>| {
             if let Some(item) = data {
                 item.show_ui(ui, self.current_guild, event_bus);
@@ -80,6 +168,41 @@ impl TabHandler {
                     .get_mut(&self.current_guild)
                     .map(|u| u as &mut dyn ShowUI),
             ),
+            TabState::Heatmap => show_ui(
+                self.heatmap
+                    .get_mut(&self.current_guild)
+                    .map(|u| u as &mut dyn ShowUI),
+            ),
+            TabState::MessageSearch => show_ui(
+                self.message_search
+                    .get_mut(&self.current_guild)
+                    .map(|u| u as &mut dyn ShowUI),
+            ),
+            TabState::DeletedMessages => show_ui(
+                self.deleted_messages
+                    .get_mut(&self.current_guild)
+                    .map(|u| u as &mut dyn ShowUI),
+            ),
+            TabState::Dashboard => show_ui(
+                self.dashboard
+                    .get_mut(&self.current_guild)
+                    .map(|u| u as &mut dyn ShowUI),
+            ),
+            TabState::Cohorts => show_ui(
+                self.cohorts
+                    .get_mut(&self.current_guild)
+                    .map(|u| u as &mut dyn ShowUI),
+            ),
+            TabState::Interactions => show_ui(
+                self.interactions
+                    .get_mut(&self.current_guild)
+                    .map(|u| u as &mut dyn ShowUI),
+            ),
+            TabState::Media => show_ui(
+                self.media
+                    .get_mut(&self.current_guild)
+                    .map(|u| u as &mut dyn ShowUI),
+            ),
         }
     }
 
@@ -98,151 +221,180 @@ impl TabHandler {
     }
 
     pub fn set_data(&mut self, id: i64) {
+        self.message_store.entry(id).or_default();
         self.overview.entry(id).or_default();
         self.user_table.entry(id).or_default();
         self.channel_table.entry(id).or_default();
         self.message_chart.entry(id).or_default();
         self.user_chart.entry(id).or_default();
         self.word_table.entry(id).or_default();
+        self.heatmap.entry(id).or_default();
+        self.message_search
+            .entry(id)
+            .or_insert_with(|| MessageSearch::new(id));
+        self.deleted_messages.entry(id).or_default();
+        self.dashboard.entry(id).or_insert_with(|| {
+            Dashboard::new(self.saved_layouts.get(&id).cloned().unwrap_or_default())
+        });
+        self.cohorts.entry(id).or_default();
+        self.interactions.entry(id).or_default();
+        self.media.entry(id).or_default();
+        self.set_guild_timezone(id);
     }
 
     pub fn set_current_guild(&mut self, id: i64) {
         self.current_guild = id;
     }
 
-    pub fn set_date_handler(&mut self, guild_id: i64, handler: DateHandler) {
-        self.overview
-            .get_mut(&guild_id)
-            .unwrap()
-            .set_date_handler(handler);
-        self.user_table
-            .get_mut(&guild_id)
-            .unwrap()
-            .set_date_handler(handler);
-        self.channel_table
-            .get_mut(&guild_id)
-            .unwrap()
-            .set_date_handler(handler);
-        self.message_chart
-            .get_mut(&guild_id)
-            .unwrap()
-            .set_date_handler(handler);
-        self.user_chart
+    pub fn save_guild_ui_state(&self, guild_id: i64, state: &mut GuildUiState) {
+        if let Some(overview) = self.overview.get(&guild_id) {
+            state.overview_chart = overview.chart_type();
+        }
+        if let Some(message_chart) = self.message_chart.get(&guild_id) {
+            state.message_chart = message_chart.chart_type();
+            state.message_series = message_chart.shown_series();
+        }
+        if let Some(user_chart) = self.user_chart.get(&guild_id) {
+            state.user_chart = user_chart.chart_type();
+        }
+        if let Some(word_table) = self.word_table.get(&guild_id) {
+            state.window_size = word_table.window_size();
+            state.word_filter = word_table.filter().clone();
+            state.tracked_phrases = word_table.tracked_phrases().to_vec();
+        }
+    }
+
+    pub fn restore_guild_ui_state(&mut self, guild_id: i64, state: &GuildUiState) {
+        if let Some(overview) = self.overview.get_mut(&guild_id) {
+            overview.set_chart_type(state.overview_chart);
+        }
+        if let Some(message_chart) = self.message_chart.get_mut(&guild_id) {
+            message_chart.set_chart_type(state.message_chart);
+            message_chart.set_shown_series(&state.message_series);
+        }
+        if let Some(user_chart) = self.user_chart.get_mut(&guild_id) {
+            user_chart.set_chart_type(state.user_chart);
+        }
+        if let Some(word_table) = self.word_table.get_mut(&guild_id) {
+            word_table.set_window_size(state.window_size);
+            word_table.set_filter(state.word_filter.clone());
+            word_table.set_tracked_phrases(state.tracked_phrases.clone());
+        }
+    }
+
+    /// Set the timezone of every guild. Data that was already processed keeps the previous timezone
+    pub fn set_timezone(&mut self, timezone: Timezone) {
+        self.timezone = timezone;
+        let guild_ids: Vec<i64> = self.message_store.keys().copied().collect();
+        for guild_id in guild_ids {
+            self.set_guild_timezone(guild_id);
+        }
+    }
+
+    fn set_guild_timezone(&mut self, guild_id: i64) {
+        let timezone = self.timezone;
+        self.message_store
             .get_mut(&guild_id)
             .unwrap()
-            .set_date_handler(handler);
-        self.word_table
+            .set_timezone(timezone);
+        self.overview
             .get_mut(&guild_id)
             .unwrap()
-            .set_date_handler(handler);
+            .set_timezone(timezone);
+    }
+
+    pub fn set_date_handler(&mut self, guild_id: i64, handler: DateHandler) {
+        self.for_each_view(guild_id, |view| view.set_date_handler(handler));
     }
 
     pub fn set_channels(&mut self, channels: Vec<Channel>) {
-        self.overview
-            .get_mut(&self.current_guild)
-            .unwrap()
-            .set_channels(channels.clone());
-        self.user_table
-            .get_mut(&self.current_guild)
-            .unwrap()
-            .set_channels(channels.clone());
-        self.message_chart
-            .get_mut(&self.current_guild)
-            .unwrap()
-            .set_channels(channels.clone());
-        self.user_chart
-            .get_mut(&self.current_guild)
-            .unwrap()
-            .set_channels(channels.clone());
-        self.word_table
-            .get_mut(&self.current_guild)
-            .unwrap()
-            .set_channels(channels);
+        self.for_each_view(self.current_guild, |view| {
+            view.set_channels(channels.clone())
+        });
     }
 
     pub fn set_selected_channels(&mut self, selected: &HashSet<usize>) {
-        self.overview
-            .get_mut(&self.current_guild)
-            .unwrap()
-            .set_selected_channels(selected.clone());
-        self.user_table
-            .get_mut(&self.current_guild)
-            .unwrap()
-            .set_selected_channels(selected.clone());
-        self.message_chart
-            .get_mut(&self.current_guild)
-            .unwrap()
-            .set_selected_channels(selected.clone());
-        self.user_chart
-            .get_mut(&self.current_guild)
-            .unwrap()
-            .set_selected_channels(selected.clone());
-        self.word_table
-            .get_mut(&self.current_guild)
-            .unwrap()
-            .set_selected_channels(selected.clone());
+        self.for_each_view(self.current_guild, |view| {
+            view.set_selected_channels(selected.clone());
+        });
+    }
+
+    /// The data of the tab as it is currently shown in the UI
+    pub fn export_data(&mut self, guild_id: i64, state: TabState) -> ExportData {
+        match state {
+            TabState::Overview => self.overview_export_data(guild_id),
+            TabState::UserTable => self.user_table_export_data(guild_id),
+            TabState::ChannelTable => self.channel_table_export_data(guild_id),
+            TabState::MessageChart => self.message_chart_export_data(guild_id),
+            TabState::UserChart => self.user_chart_export_data(guild_id),
+            TabState::CommonWords => self.word_table_export_data(guild_id),
+            TabState::Heatmap => self.heatmap_export_data(guild_id),
+            TabState::MessageSearch => self.message_search_export_data(guild_id),
+            TabState::DeletedMessages => self.deleted_messages_export_data(guild_id),
+            TabState::Dashboard => self.dashboard_export_data(guild_id),
+            TabState::Cohorts => self.cohorts_export_data(guild_id),
+            TabState::Interactions => self.interactions_export_data(guild_id),
+            TabState::Media => self.media_export_data(guild_id),
+        }
     }
 
-    pub fn process_pending_reloads(&mut self, state: TabState) {
-        let mut to_remove_indices = Vec::new();
+    /// Add the messages to the store of their guild. A message that is already stored is replaced
+    /// so later edits and deletions are kept. The tabs are reloaded after every few pages
+    pub fn handle_messages(&mut self, messages: &[MessageWithUser], event_bus: &mut EventBus) {
+        for message in messages {
+            let guild_id = message.message.guild_id;
+            let store = self.message_store.get_mut(&guild_id).unwrap();
+            let previous_len = store.len();
 
-        for (index, pending_reload) in self.pending_reloads.clone().iter().enumerate() {
-            if pending_reload.guild_id != self.current_guild {
-                continue;
+            // The tabs only have a copy of the date handler. Modifying here doesn't impact the UI.
+            // Send an event so the main UI gets the new date
+            if let Some(date) = store.add_message(message) {
+                event_bus.publish(AppEvent::UpdateDate(date, guild_id));
             }
-            match pending_reload.reload_type {
-                ReloadTab::Overview(guild_id) => {
-                    if TabState::Overview == state && guild_id == self.current_guild {
-                        info!("Reloading Overview for {guild_id}");
-                        self.reload_overview(pending_reload.guild_id);
-                        to_remove_indices.push(index);
-                    }
-                }
-                ReloadTab::UserTable(guild_id) => {
-                    if TabState::UserTable == state && guild_id == self.current_guild {
-                        info!("Reloading User Table for {guild_id}");
-                        self.user_table_recreate_rows(pending_reload.guild_id);
-                        to_remove_indices.push(index);
-                    }
-                }
-                ReloadTab::ChannelTable(guild_id) => {
-                    if TabState::ChannelTable == state && guild_id == self.current_guild {
-                        info!("Reloading Channel Table for {guild_id}");
-                        self.channel_table_recreate_rows(pending_reload.guild_id);
-                        to_remove_indices.push(index);
-                    }
-                }
-                ReloadTab::MessageChart(guild_id) => {
-                    if TabState::MessageChart == state && guild_id == self.current_guild {
-                        info!("Reloading Message Chart for {guild_id}");
-                        self.reload_message_chart(pending_reload.guild_id);
-                        to_remove_indices.push(index);
-                    }
-                }
-                ReloadTab::UserChart(guild_id) => {
-                    if TabState::UserChart == state && guild_id == self.current_guild {
-                        info!("Reloading User Chart for {guild_id}");
-                        self.reload_user_chart(pending_reload.guild_id);
-                        to_remove_indices.push(index);
-                    }
-                }
-                ReloadTab::WordTable(guild_id) => {
-                    if TabState::CommonWords == state && guild_id == self.current_guild {
-                        info!("Reloading Word Table for {guild_id}");
-                        self.word_table_recreate_rows(pending_reload.guild_id);
-                        to_remove_indices.push(index);
-                    }
-                }
+
+            // Replaced messages keep the length so only a new message can reach the next multiple
+            let added = store.len() > previous_len;
+            if added && (store.len() as u64).is_multiple_of(PAGE_VALUE * 5) {
+                event_bus.publish_reloads(guild_id);
             }
         }
+    }
+
+    pub fn handle_member_counts(
+        &mut self,
+        guild_id: i64,
+        counts: &[MemberCount],
+        event_bus: &mut EventBus,
+    ) {
+        for count in counts {
+            self.handle_member_count(guild_id, count.clone(), event_bus);
+        }
+        self.clear_chart_labels(guild_id);
+    }
 
-        // Remove items in reverse order to avoid index shifting
-        for &index in to_remove_indices.iter().rev() {
-            self.pending_reloads.remove(index);
+    pub fn handle_member_activities(
+        &mut self,
+        guild_id: i64,
+        activities: &[MemberActivity],
+        event_bus: &mut EventBus,
+    ) {
+        for activity in activities {
+            self.handle_member_activity(guild_id, activity.clone(), event_bus);
         }
+        self.clear_chart_labels(guild_id);
     }
 
+    /// Remove every message of the guild and reset its tabs
     pub fn clear_key_data(&mut self, key: i64) {
+        if self.message_store.contains_key(&key) {
+            self.message_store.insert(key, MessageStore::default());
+        }
+        self.clear_tab_data(key);
+    }
+
+    /// Reset the tabs of the guild while the messages of its store are kept
+    pub fn clear_tab_data(&mut self, key: i64) {
+        self.cancel_reload(key);
         if self.overview.contains_key(&key) {
             self.overview.insert(key, Overview::default());
         }
@@ -261,6 +413,46 @@ impl TabHandler {
         if self.word_table.contains_key(&key) {
             self.word_table.insert(key, WordTable::default());
         }
+        if self.heatmap.contains_key(&key) {
+            self.heatmap.insert(key, Heatmap::default());
+        }
+        if self.message_search.contains_key(&key) {
+            self.message_search.insert(key, MessageSearch::new(key));
+        }
+        if self.deleted_messages.contains_key(&key) {
+            self.deleted_messages
+                .insert(key, DeletedMessages::default());
+        }
+        if let Some(dashboard) = self.dashboard.get(&key) {
+            let layout = dashboard.layout().clone();
+            self.dashboard.insert(key, Dashboard::new(layout));
+        }
+        if self.cohorts.contains_key(&key) {
+            self.cohorts.insert(key, Cohorts::default());
+        }
+        if self.interactions.contains_key(&key) {
+            self.interactions.insert(key, Interactions::default());
+        }
+        if self.media.contains_key(&key) {
+            self.media.insert(key, Media::default());
+        }
+        if self.message_store.contains_key(&key) {
+            self.set_guild_timezone(key);
+        }
+        if self
+            .user_profile
+            .as_ref()
+            .is_some_and(|profile| profile.guild_id() == key)
+        {
+            self.user_profile = None;
+        }
+        if self
+            .channel_profile
+            .as_ref()
+            .is_some_and(|profile| profile.guild_id() == key)
+        {
+            self.channel_profile = None;
+        }
     }
 }
 
@@ -0,0 +1,237 @@
+use chrono::{Datelike, Timelike};
+use eframe::egui::ahash::HashSet;
+use eframe::egui::{Align2, CornerRadius, FontId, Pos2, Rect, Sense, Stroke, StrokeKind, Ui, vec2};
+use funnel_shared::Channel;
+use strum::IntoEnumIterator;
+
+use crate::ui::{AnimatedMenuLabel, DateHandler, ShowUI, StoreView, TabHandler};
+use crate::{
+    EventBus, ExportData, HeatmapType, MessageQuery, MessageRow, MessageStore, selected_channel_ids,
+};
+
+const WEEKDAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+/// Value of every hour of every weekday. Index: Weekday starting from Monday, then the hour
+type HeatmapGrid = [[u32; 24]; 7];
+
+#[derive(Default)]
+pub struct Heatmap {
+    heatmap_type: HeatmapType,
+    message_grid: HeatmapGrid,
+    user_grid: HeatmapGrid,
+    /// Messages of a reload that is still running
+    reload_messages: HeatmapGrid,
+    /// Users of a reload that is still running. Index: Weekday starting from Monday, then the hour
+    reload_users: Vec<Vec<HashSet<i64>>>,
+    date_handler: DateHandler,
+    channels: Vec<Channel>,
+    selected_channels: HashSet<usize>,
+}
+
+impl ShowUI for Heatmap {
+    fn show_ui(&mut self, ui: &mut Ui, _guild_id: i64, _event_bus: &mut EventBus) {
+        let hover_position = ui.make_persistent_id("heatmap_hover");
+        let selected_position = ui.make_persistent_id("heatmap_selected");
+
+        ui.horizontal(|ui| {
+            for val in HeatmapType::iter() {
+                let val_string = val.to_string();
+                let selected = self.heatmap_type == val;
+
+                let resp = ui.add(AnimatedMenuLabel::new(
+                    selected,
+                    val_string,
+                    selected_position,
+                    hover_position,
+                    90.0,
+                    18.0,
+                    None,
+                    (false, false),
+                ));
+
+                if resp.clicked() {
+                    self.heatmap_type = val;
+                }
+            }
+            ui.separator();
+            let total_message: u32 = self.message_grid.iter().flatten().sum();
+            ui.label(format!("Total Message: {total_message}"));
+        });
+
+        ui.add_space(5.0);
+        self.show_grid(ui);
+    }
+}
+
+impl StoreView for Heatmap {
+    fn start_reload(&mut self, _store: &MessageStore) -> Option<MessageQuery> {
+        self.reload_messages = [[0; 24]; 7];
+        self.reload_users = vec![vec![HashSet::default(); 24]; 7];
+
+        let selected_channels = selected_channel_ids(&self.channels, &self.selected_channels);
+        let query = MessageQuery::new()
+            .dates(self.date_handler.from, self.date_handler.to)
+            .channels(selected_channels);
+        Some(query)
+    }
+
+    fn add_message(&mut self, message: &MessageRow) {
+        // Deleted messages are counted as well as the user was active when sending them
+        let time = message.sent_time();
+        let weekday = time.weekday().num_days_from_monday() as usize;
+        let hour = time.hour() as usize;
+
+        self.reload_messages[weekday][hour] += 1;
+        self.reload_users[weekday][hour].insert(message.user_id());
+    }
+
+    /// Replace both grids with the ones from the messages within the selected dates and channels
+    fn finish_reload(&mut self, _store: &MessageStore) {
+        let mut user_grid = [[0; 24]; 7];
+        for (weekday, hours) in self.reload_users.drain(..).enumerate() {
+            for (hour, users) in hours.iter().enumerate() {
+                user_grid[weekday][hour] = users.len() as u32;
+            }
+        }
+
+        self.message_grid = self.reload_messages;
+        self.user_grid = user_grid;
+    }
+
+    fn set_date_handler(&mut self, handler: DateHandler) {
+        self.date_handler = handler;
+    }
+
+    fn set_channels(&mut self, channels: Vec<Channel>) {
+        self.channels = channels;
+    }
+
+    fn set_selected_channels(&mut self, selected: HashSet<usize>) {
+        self.selected_channels = selected;
+    }
+}
+
+impl Heatmap {
+    fn target_grid(&self) -> &HeatmapGrid {
+        match self.heatmap_type {
+            HeatmapType::Messages => &self.message_grid,
+            HeatmapType::ActiveUsers => &self.user_grid,
+        }
+    }
+
+    fn show_grid(&self, ui: &mut Ui) {
+        let (response, painter) = ui.allocate_painter(ui.available_size(), Sense::hover());
+        let rect = response.rect;
+
+        let label_size = vec2(40.0, 20.0);
+        let cell_size = vec2(
+            (rect.width() - label_size.x) / 24.0,
+            (rect.height() - label_size.y) / 7.0,
+        );
+
+        let grid = self.target_grid();
+        let max_value = grid.iter().flatten().copied().max().unwrap_or_default();
+
+        let visuals = ui.visuals();
+        let low_color = visuals.faint_bg_color;
+        let high_color = visuals.selection.bg_fill;
+        let text_color = visuals.text_color();
+        let font = FontId::proportional(12.0);
+
+        for hour in 0..24 {
+            let center = Pos2::new(
+                rect.left() + label_size.x + cell_size.x * (hour as f32 + 0.5),
+                rect.top() + label_size.y / 2.0,
+            );
+            painter.text(
+                center,
+                Align2::CENTER_CENTER,
+                format!("{hour:02}"),
+                font.clone(),
+                text_color,
+            );
+        }
+
+        let mut hover_text = None;
+
+        for (weekday, hours) in grid.iter().enumerate() {
+            let row_top = rect.top() + label_size.y + cell_size.y * weekday as f32;
+            painter.text(
+                Pos2::new(
+                    rect.left() + label_size.x / 2.0,
+                    row_top + cell_size.y / 2.0,
+                ),
+                Align2::CENTER_CENTER,
+                WEEKDAYS[weekday],
+                font.clone(),
+                text_color,
+            );
+
+            for (hour, value) in hours.iter().enumerate() {
+                let cell = Rect::from_min_size(
+                    Pos2::new(
+                        rect.left() + label_size.x + cell_size.x * hour as f32,
+                        row_top,
+                    ),
+                    cell_size,
+                )
+                .shrink(1.0);
+
+                let strength = if max_value == 0 {
+                    0.0
+                } else {
+                    *value as f32 / max_value as f32
+                };
+
+                painter.rect(
+                    cell,
+                    CornerRadius::same(2),
+                    low_color.lerp_to_gamma(high_color, strength),
+                    Stroke::NONE,
+                    StrokeKind::Inside,
+                );
+
+                if cell.width() > 25.0 && cell.height() > 15.0 {
+                    painter.text(
+                        cell.center(),
+                        Align2::CENTER_CENTER,
+                        value.to_string(),
+                        font.clone(),
+                        text_color,
+                    );
+                }
+
+                if response.hover_pos().is_some_and(|pos| cell.contains(pos)) {
+                    hover_text = Some(format!(
+                        "{} {hour:02}:00 - {hour:02}:59\n{}: {value}",
+                        WEEKDAYS[weekday], self.heatmap_type
+                    ));
+                }
+            }
+        }
+
+        if let Some(text) = hover_text {
+            response.on_hover_text(text);
+        }
+    }
+
+    /// The shown grid with one row per weekday and one column per hour
+    fn export_data(&self) -> ExportData {
+        let mut headers = vec!["Day".to_string()];
+        headers.extend((0..24).map(|hour| format!("{hour:02}:00")));
+        let mut data = ExportData::new(&format!("Heatmap {}", self.heatmap_type), headers);
+
+        for (weekday, hours) in self.target_grid().iter().enumerate() {
+            let mut row = vec![WEEKDAYS[weekday].to_string()];
+            row.extend(hours.iter().map(ToString::to_string));
+            data.add_row(row);
+        }
+        data
+    }
+}
+
+impl TabHandler {
+    pub fn heatmap_export_data(&self, guild_id: i64) -> ExportData {
+        self.heatmap.get(&guild_id).unwrap().export_data()
+    }
+}
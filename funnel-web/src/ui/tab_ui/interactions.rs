@@ -0,0 +1,324 @@
+use eframe::egui::ahash::{HashMap, HashSet};
+use eframe::egui::{CollapsingHeader, Grid, RichText, ScrollArea, Ui};
+use funnel_shared::Channel;
+use strum::IntoEnumIterator;
+use strum_macros::{Display, EnumIter};
+
+use crate::core::{MessageQuery, MessageRow, MessageStore, selected_channel_ids};
+use crate::ui::{DateHandler, ShowUI, StoreView, TabHandler};
+use crate::{EventBus, ExportData};
+
+/// Number of user pairs that are shown
+const TOP_PAIRS: usize = 25;
+/// Number of users with the most partners that are shown
+const TOP_HUBS: usize = 10;
+
+/// Replies and mentions between two users in both directions
+#[derive(Default, Clone, Copy)]
+struct PairCount {
+    replies: u32,
+    mentions: u32,
+}
+
+impl PairCount {
+    fn total(self) -> u32 {
+        self.replies + self.mentions
+    }
+}
+
+/// The value the user pairs are ordered by
+#[derive(Default, Clone, Copy, PartialEq, Eq, Display, EnumIter)]
+pub enum InteractionSort {
+    #[default]
+    Total,
+    Replies,
+    Mentions,
+}
+
+impl InteractionSort {
+    fn value(self, count: PairCount) -> u32 {
+        match self {
+            InteractionSort::Total => count.total(),
+            InteractionSort::Replies => count.replies,
+            InteractionSort::Mentions => count.mentions,
+        }
+    }
+}
+
+struct PairRow {
+    first: String,
+    second: String,
+    count: PairCount,
+}
+
+/// A user and everyone they interacted with
+struct HubRow {
+    name: String,
+    partners: u32,
+    interactions: u32,
+}
+
+/// Interactions of a reload that is still running
+#[derive(Default)]
+struct InteractionReload {
+    /// Key: Both User IDs with the lower ID first
+    pairs: HashMap<(i64, i64), PairCount>,
+    /// Every user that sent a message
+    senders: HashSet<i64>,
+}
+
+#[derive(Default)]
+pub struct Interactions {
+    /// Read only currently selected dates in the UI
+    date_handler: DateHandler,
+    sort: InteractionSort,
+    /// Every pair that interacted, ordered by the sort value
+    pairs: Vec<PairRow>,
+    hubs: Vec<HubRow>,
+    /// Name of every user that sent messages without replying to or mentioning anyone and
+    /// without being replied to or mentioned
+    isolated: Vec<String>,
+    reload: InteractionReload,
+    channels: Vec<Channel>,
+    selected_channels: HashSet<usize>,
+}
+
+impl ShowUI for Interactions {
+    fn show_ui(&mut self, ui: &mut Ui, _guild_id: i64, _event_bus: &mut EventBus) {
+        ui.horizontal(|ui| {
+            ui.label("Sort By:");
+            for sort in InteractionSort::iter() {
+                if ui
+                    .selectable_value(&mut self.sort, sort, sort.to_string())
+                    .changed()
+                {
+                    self.sort_pairs();
+                }
+            }
+            ui.separator();
+            ui.label(format!("Interacting Pairs: {}", self.pairs.len()));
+            ui.separator();
+            ui.label(format!("Isolated Members: {}", self.isolated.len()));
+        });
+        ui.label(
+            "Replies and mentions between users in both directions within the selected dates and channels",
+        );
+        ui.separator();
+        ui.add_space(5.0);
+
+        if self.pairs.is_empty() && self.isolated.is_empty() {
+            ui.label("No message was sent within the selected dates");
+            return;
+        }
+
+        ScrollArea::vertical().show(ui, |ui| {
+            CollapsingHeader::new("Top Pairs")
+                .default_open(true)
+                .show(ui, |ui| {
+                    Grid::new("interaction_pairs")
+                        .num_columns(5)
+                        .striped(true)
+                        .min_col_width(80.0)
+                        .show(ui, |ui| {
+                            for header in ["User", "Other User", "Replies", "Mentions", "Total"] {
+                                ui.label(RichText::new(header).strong());
+                            }
+                            ui.end_row();
+
+                            for pair in self.pairs.iter().take(TOP_PAIRS) {
+                                ui.label(&pair.first);
+                                ui.label(&pair.second);
+                                ui.label(pair.count.replies.to_string());
+                                ui.label(pair.count.mentions.to_string());
+                                ui.label(pair.count.total().to_string());
+                                ui.end_row();
+                            }
+                        });
+                });
+
+            CollapsingHeader::new("Hubs")
+                .default_open(true)
+                .show(ui, |ui| {
+                    ui.label("The users that interacted with the most other users");
+                    Grid::new("interaction_hubs")
+                        .num_columns(3)
+                        .striped(true)
+                        .min_col_width(80.0)
+                        .show(ui, |ui| {
+                            for header in ["User", "Partners", "Interactions"] {
+                                ui.label(RichText::new(header).strong());
+                            }
+                            ui.end_row();
+
+                            for hub in &self.hubs {
+                                ui.label(&hub.name);
+                                ui.label(hub.partners.to_string());
+                                ui.label(hub.interactions.to_string());
+                                ui.end_row();
+                            }
+                        });
+                });
+
+            CollapsingHeader::new(format!("Isolated Members ({})", self.isolated.len())).show(
+                ui,
+                |ui| {
+                    ui.label(
+                        "Users that sent messages but did not reply to or mention anyone and were not replied to or mentioned",
+                    );
+                    for name in &self.isolated {
+                        ui.label(name);
+                    }
+                },
+            );
+        });
+    }
+}
+
+impl StoreView for Interactions {
+    fn start_reload(&mut self, _store: &MessageStore) -> Option<MessageQuery> {
+        self.reload = InteractionReload::default();
+
+        let selected_channels = selected_channel_ids(&self.channels, &self.selected_channels);
+        let query = MessageQuery::new()
+            .dates(self.date_handler.from, self.date_handler.to)
+            .channels(selected_channels)
+            .skip_deleted();
+        Some(query)
+    }
+
+    fn add_message(&mut self, message: &MessageRow) {
+        let sender = message.user_id();
+        self.reload.senders.insert(sender);
+
+        let pair_key = |target: i64| (sender.min(target), sender.max(target));
+        let reply_to = message.reply_to_user();
+
+        if let Some(target) = reply_to
+            && target != sender
+        {
+            self.reload
+                .pairs
+                .entry(pair_key(target))
+                .or_default()
+                .replies += 1;
+        }
+
+        // A reply usually mentions the author of the replied message as well
+        let mut mentioned = HashSet::default();
+        for target in message.mentions() {
+            if *target == sender || Some(*target) == reply_to || !mentioned.insert(*target) {
+                continue;
+            }
+            self.reload
+                .pairs
+                .entry(pair_key(*target))
+                .or_default()
+                .mentions += 1;
+        }
+    }
+
+    fn finish_reload(&mut self, store: &MessageStore) {
+        let reload = std::mem::take(&mut self.reload);
+        let name = |user_id: i64| {
+            store
+                .user(user_id)
+                .map_or_else(|| user_id.to_string(), |user| user.name.clone())
+        };
+
+        // Key: User ID. Value: Partners and interactions
+        let mut users: HashMap<i64, (u32, u32)> = HashMap::default();
+        self.pairs = reload
+            .pairs
+            .into_iter()
+            .map(|((first, second), count)| {
+                for user_id in [first, second] {
+                    let entry = users.entry(user_id).or_default();
+                    entry.0 += 1;
+                    entry.1 += count.total();
+                }
+                PairRow {
+                    first: name(first),
+                    second: name(second),
+                    count,
+                }
+            })
+            .collect();
+        self.sort_pairs();
+
+        let mut isolated: Vec<String> = reload
+            .senders
+            .into_iter()
+            .filter(|user_id| !users.contains_key(user_id))
+            .map(name)
+            .collect();
+        isolated.sort();
+        self.isolated = isolated;
+
+        let mut hubs: Vec<HubRow> = users
+            .into_iter()
+            .map(|(user_id, (partners, interactions))| HubRow {
+                name: name(user_id),
+                partners,
+                interactions,
+            })
+            .collect();
+        hubs.sort_by(|a, b| {
+            b.partners
+                .cmp(&a.partners)
+                .then(b.interactions.cmp(&a.interactions))
+                .then(a.name.cmp(&b.name))
+        });
+        hubs.truncate(TOP_HUBS);
+        self.hubs = hubs;
+    }
+
+    fn set_date_handler(&mut self, handler: DateHandler) {
+        self.date_handler = handler;
+    }
+
+    fn set_channels(&mut self, channels: Vec<Channel>) {
+        self.channels = channels;
+    }
+
+    fn set_selected_channels(&mut self, selected: HashSet<usize>) {
+        self.selected_channels = selected;
+    }
+}
+
+impl Interactions {
+    fn sort_pairs(&mut self) {
+        let sort = self.sort;
+        self.pairs.sort_by(|a, b| {
+            sort.value(b.count)
+                .cmp(&sort.value(a.count))
+                .then(a.first.cmp(&b.first))
+                .then(a.second.cmp(&b.second))
+        });
+    }
+
+    /// Every pair that interacted in the order it is shown
+    fn export_data(&self) -> ExportData {
+        let headers = ["User", "Other User", "Replies", "Mentions", "Total"]
+            .into_iter()
+            .map(ToString::to_string)
+            .collect();
+        let mut data = ExportData::new("Interactions", headers);
+
+        for pair in &self.pairs {
+            data.add_row(vec![
+                pair.first.clone(),
+                pair.second.clone(),
+                pair.count.replies.to_string(),
+                pair.count.mentions.to_string(),
+                pair.count.total().to_string(),
+            ]);
+        }
+        data
+    }
+}
+
+impl TabHandler {
+    pub fn interactions_export_data(&self, guild_id: i64) -> ExportData {
+        self.interactions.get(&guild_id).unwrap().export_data()
+    }
+}
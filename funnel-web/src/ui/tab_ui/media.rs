@@ -0,0 +1,290 @@
+use eframe::egui::ahash::{HashMap, HashSet};
+use eframe::egui::{CollapsingHeader, Grid, RichText, ScrollArea, Ui};
+use funnel_shared::Channel;
+
+use crate::core::{MessageQuery, MessageRow, MessageStore, selected_channel_ids};
+use crate::ui::{DateHandler, ShowUI, StoreView, TabHandler};
+use crate::{EventBus, ExportData};
+
+/// Number of emojis and domains that are shown
+const TOP_ENTRIES: usize = 15;
+
+/// Messages of a channel and how many of them have media
+#[derive(Default, Clone, Copy)]
+struct ChannelMedia {
+    total_message: u32,
+    media_message: u32,
+    attachments: u32,
+}
+
+impl ChannelMedia {
+    fn share(self) -> f64 {
+        if self.total_message == 0 {
+            return 0.0;
+        }
+        f64::from(self.media_message) / f64::from(self.total_message) * 100.0
+    }
+}
+
+/// Counts of a reload that is still running
+#[derive(Default)]
+struct MediaReload {
+    emojis: HashMap<String, u32>,
+    domains: HashMap<String, u32>,
+    attachment_types: HashMap<String, u32>,
+    /// Key: Channel ID
+    channels: HashMap<i64, ChannelMedia>,
+    total_message: u32,
+    media_message: u32,
+    embeds: u32,
+    links: u32,
+}
+
+#[derive(Default)]
+pub struct Media {
+    /// Read only currently selected dates in the UI
+    date_handler: DateHandler,
+    /// Every custom emoji with the number of uses, most used first
+    emojis: Vec<(String, u32)>,
+    /// Every linked domain with the number of links, most linked first
+    domains: Vec<(String, u32)>,
+    attachment_types: Vec<(String, u32)>,
+    /// Channel name with its media, highest media share first
+    channel_media: Vec<(String, ChannelMedia)>,
+    total_message: u32,
+    media_message: u32,
+    attachments: u32,
+    embeds: u32,
+    links: u32,
+    reload: MediaReload,
+    channels: Vec<Channel>,
+    selected_channels: HashSet<usize>,
+}
+
+impl ShowUI for Media {
+    fn show_ui(&mut self, ui: &mut Ui, _guild_id: i64, _event_bus: &mut EventBus) {
+        ui.horizontal(|ui| {
+            ui.label(format!("Total Message: {}", self.total_message));
+            ui.separator();
+            ui.label(format!("Messages With Media: {}", self.media_message));
+            ui.separator();
+            ui.label(format!("Attachments: {}", self.attachments));
+            ui.separator();
+            ui.label(format!("Embeds: {}", self.embeds));
+            ui.separator();
+            ui.label(format!("Links: {}", self.links));
+        });
+        ui.separator();
+        ui.add_space(5.0);
+
+        if self.total_message == 0 {
+            ui.label("No message was sent within the selected dates");
+            return;
+        }
+
+        ScrollArea::vertical().show(ui, |ui| {
+            CollapsingHeader::new("Top Emojis")
+                .default_open(true)
+                .show(ui, |ui| {
+                    show_count_grid(ui, "media_emojis", "Emoji", "Uses", &self.emojis);
+                });
+
+            CollapsingHeader::new("Top Linked Domains")
+                .default_open(true)
+                .show(ui, |ui| {
+                    show_count_grid(ui, "media_domains", "Domain", "Links", &self.domains);
+                });
+
+            CollapsingHeader::new("Attachment Types")
+                .default_open(true)
+                .show(ui, |ui| {
+                    show_count_grid(
+                        ui,
+                        "media_attachment_types",
+                        "Type",
+                        "Attachments",
+                        &self.attachment_types,
+                    );
+                });
+
+            CollapsingHeader::new("Media Share per Channel")
+                .default_open(true)
+                .show(ui, |ui| {
+                    ui.label("Share of the messages with an attachment or embed");
+                    Grid::new("media_channels")
+                        .num_columns(5)
+                        .striped(true)
+                        .min_col_width(80.0)
+                        .show(ui, |ui| {
+                            for header in
+                                ["Channel", "Messages", "With Media", "Attachments", "Share"]
+                            {
+                                ui.label(RichText::new(header).strong());
+                            }
+                            ui.end_row();
+
+                            for (name, media) in &self.channel_media {
+                                ui.label(name);
+                                ui.label(media.total_message.to_string());
+                                ui.label(media.media_message.to_string());
+                                ui.label(media.attachments.to_string());
+                                ui.label(format!("{:.1}%", media.share()));
+                                ui.end_row();
+                            }
+                        });
+                });
+        });
+    }
+}
+
+/// A grid with the name and count of the top entries
+fn show_count_grid(ui: &mut Ui, id: &str, name: &str, value: &str, entries: &[(String, u32)]) {
+    if entries.is_empty() {
+        ui.label("Nothing was found within the selected dates");
+        return;
+    }
+
+    Grid::new(id)
+        .num_columns(2)
+        .striped(true)
+        .min_col_width(80.0)
+        .show(ui, |ui| {
+            ui.label(RichText::new(name).strong());
+            ui.label(RichText::new(value).strong());
+            ui.end_row();
+
+            for (entry, count) in entries.iter().take(TOP_ENTRIES) {
+                ui.label(entry);
+                ui.label(count.to_string());
+                ui.end_row();
+            }
+        });
+}
+
+/// The counts with the highest count first
+fn sorted_counts(counts: HashMap<String, u32>) -> Vec<(String, u32)> {
+    let mut counts: Vec<(String, u32)> = counts.into_iter().collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    counts
+}
+
+impl StoreView for Media {
+    fn start_reload(&mut self, _store: &MessageStore) -> Option<MessageQuery> {
+        self.reload = MediaReload::default();
+
+        let selected_channels = selected_channel_ids(&self.channels, &self.selected_channels);
+        let query = MessageQuery::new()
+            .dates(self.date_handler.from, self.date_handler.to)
+            .channels(selected_channels)
+            .skip_deleted();
+        Some(query)
+    }
+
+    fn add_message(&mut self, message: &MessageRow) {
+        let reload = &mut self.reload;
+        reload.total_message += 1;
+        reload.embeds += message.embed_count();
+
+        let channel = reload.channels.entry(message.channel_id()).or_default();
+        channel.total_message += 1;
+        channel.attachments += message.attachment_count();
+        if message.has_media() {
+            channel.media_message += 1;
+            reload.media_message += 1;
+        }
+
+        for attachment_type in message.attachment_types() {
+            *reload
+                .attachment_types
+                .entry(attachment_type.clone())
+                .or_default() += 1;
+        }
+        for emoji in message.custom_emojis() {
+            *reload.emojis.entry(emoji.clone()).or_default() += 1;
+        }
+        for domain in message.link_domains() {
+            reload.links += 1;
+            *reload.domains.entry(domain.to_lowercase()).or_default() += 1;
+        }
+    }
+
+    fn finish_reload(&mut self, _store: &MessageStore) {
+        let reload = std::mem::take(&mut self.reload);
+
+        self.attachments = reload.attachment_types.values().sum();
+        self.emojis = sorted_counts(reload.emojis);
+        self.domains = sorted_counts(reload.domains);
+        self.attachment_types = sorted_counts(reload.attachment_types);
+
+        let mut channel_media: Vec<(String, ChannelMedia)> = reload
+            .channels
+            .into_iter()
+            .map(|(channel_id, media)| {
+                let name = self
+                    .channels
+                    .iter()
+                    .find(|channel| channel.channel_id == channel_id)
+                    .map_or_else(
+                        || channel_id.to_string(),
+                        |channel| channel.channel_name.clone(),
+                    );
+                (name, media)
+            })
+            .collect();
+        channel_media.sort_by(|a, b| b.1.share().total_cmp(&a.1.share()).then(a.0.cmp(&b.0)));
+        self.channel_media = channel_media;
+
+        self.total_message = reload.total_message;
+        self.media_message = reload.media_message;
+        self.embeds = reload.embeds;
+        self.links = reload.links;
+    }
+
+    fn set_date_handler(&mut self, handler: DateHandler) {
+        self.date_handler = handler;
+    }
+
+    fn set_channels(&mut self, channels: Vec<Channel>) {
+        self.channels = channels;
+    }
+
+    fn set_selected_channels(&mut self, selected: HashSet<usize>) {
+        self.selected_channels = selected;
+    }
+}
+
+impl Media {
+    /// Every statistic of the tab with one row per emoji, domain, attachment type and channel
+    fn export_data(&self) -> ExportData {
+        let headers = ["Statistic", "Name", "Value"]
+            .into_iter()
+            .map(ToString::to_string)
+            .collect();
+        let mut data = ExportData::new("Media", headers);
+
+        let lists = [
+            ("Emoji", &self.emojis),
+            ("Domain", &self.domains),
+            ("Attachment Type", &self.attachment_types),
+        ];
+        for (statistic, entries) in lists {
+            for (name, count) in entries {
+                data.add_row(vec![statistic.to_string(), name.clone(), count.to_string()]);
+            }
+        }
+        for (name, media) in &self.channel_media {
+            data.add_row(vec![
+                "Channel Media Share".to_string(),
+                name.clone(),
+                format!("{:.1}%", media.share()),
+            ]);
+        }
+        data
+    }
+}
+
+impl TabHandler {
+    pub fn media_export_data(&self, guild_id: i64) -> ExportData {
+        self.media.get(&guild_id).unwrap().export_data()
+    }
+}
@@ -1,41 +1,29 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::ops::RangeInclusive;
 
-use chrono::{
-    DateTime, Datelike, Duration, Local, Months, NaiveDate, NaiveDateTime, Timelike, Weekday,
-};
-use eframe::egui::ahash::{HashMap, HashMapExt, HashSet};
+use chrono::{Duration, Months, NaiveDateTime};
+use eframe::egui::ahash::HashSet;
 use eframe::egui::{CentralPanel, Id, Modal, ScrollArea, TopBottomPanel, Ui};
 use egui_plot::{AxisHints, GridMark, Legend, Line, Plot, PlotPoint, PlotPoints};
-use funnel_shared::{Channel, MessageWithUser, PAGE_VALUE};
+use funnel_shared::Channel;
 use indexmap::IndexMap;
 use strum::IntoEnumIterator;
 
-use crate::ui::{AnimatedMenuLabel, DateHandler, ShowUI, TabHandler};
-use crate::{AppEvent, ChartType, EventBus};
-
-#[derive(Default)]
-struct ChartPointData {
-    user: String,
-    count: u32,
-    deleted: bool,
-}
+use crate::ui::{AnimatedMenuLabel, DateHandler, ShowUI, StoreView, TabHandler};
+use crate::{
+    AppEvent, ChartType, EventBus, ExportData, MessageQuery, MessageRow, MessageStore,
+    selected_channel_ids,
+};
 
 pub struct MessageChart {
     chart_type: ChartType,
     chart_data: BTreeMap<String, IndexMap<NaiveDateTime, i64>>,
     chart_values: BTreeMap<String, bool>,
     chart_labels: Vec<Vec<(String, String)>>,
-    hourly_data: BTreeMap<NaiveDateTime, HashMap<i64, Vec<ChartPointData>>>,
-    daily_data: BTreeMap<NaiveDateTime, HashMap<i64, Vec<ChartPointData>>>,
-    weekly_data: BTreeMap<NaiveDateTime, HashMap<i64, Vec<ChartPointData>>>,
-    monthly_data: BTreeMap<NaiveDateTime, HashMap<i64, Vec<ChartPointData>>>,
-    last_hour: Option<NaiveDateTime>,
-    last_day: Option<NaiveDateTime>,
-    last_week: Option<NaiveDateTime>,
-    last_month: Option<NaiveDateTime>,
+    /// Chart type and series of a reload that is still running
+    reload_type: ChartType,
+    reload_data: BTreeMap<String, IndexMap<NaiveDateTime, i64>>,
     date_handler: DateHandler,
-    reload_count: u64,
     open_modal: bool,
     channels: Vec<Channel>,
     selected_channels: HashSet<usize>,
@@ -58,16 +46,9 @@ impl Default for MessageChart {
             chart_data,
             chart_values,
             chart_labels: Vec::new(),
-            hourly_data: BTreeMap::default(),
-            daily_data: BTreeMap::new(),
-            weekly_data: BTreeMap::new(),
-            monthly_data: BTreeMap::new(),
-            last_hour: None,
-            last_day: None,
-            last_week: None,
-            last_month: None,
+            reload_type: chart_type,
+            reload_data: BTreeMap::new(),
             date_handler: DateHandler::default(),
-            reload_count: 0,
             open_modal: false,
             channels: Vec::default(),
             selected_channels: HashSet::default(),
@@ -111,7 +92,7 @@ impl ShowUI for MessageChart {
         ui.add_space(5.0);
 
         if self.open_modal {
-            self.show_popup(ui);
+            self.show_popup(ui, guild_id, event_bus);
         }
 
         let start_datetime = self.date_handler.from.and_hms_opt(0, 0, 0).unwrap();
@@ -236,8 +217,69 @@ impl ShowUI for MessageChart {
     }
 }
 
+impl StoreView for MessageChart {
+    fn start_reload(&mut self, store: &MessageStore) -> Option<MessageQuery> {
+        for user in store.users() {
+            self.chart_values.entry(user.username.clone()).or_default();
+        }
+
+        self.reload_type = self.chart_type;
+
+        // Every bucket within the dates is shown even if no message was sent in it
+        let buckets = self
+            .chart_type
+            .buckets(self.date_handler.from, self.date_handler.to);
+        self.reload_data.clear();
+        for val in self.chart_data.keys() {
+            let series = self.reload_data.entry(val.to_string()).or_default();
+            for bucket in &buckets {
+                series.insert(*bucket, 0);
+            }
+        }
+
+        let selected_channels = selected_channel_ids(&self.channels, &self.selected_channels);
+        let query = MessageQuery::new()
+            .dates(self.date_handler.from, self.date_handler.to)
+            .channels(selected_channels);
+        Some(query)
+    }
+
+    fn add_message(&mut self, message: &MessageRow) {
+        let bucket = self.reload_type.bucket_start(message.time());
+        let total_series = if message.is_deleted() {
+            "Deleted Messages"
+        } else {
+            "All Messages"
+        };
+
+        if let Some(series) = self.reload_data.get_mut(total_series) {
+            *series.entry(bucket).or_default() += 1;
+        }
+        if let Some(series) = self.reload_data.get_mut(&message.user().username) {
+            *series.entry(bucket).or_default() += 1;
+        }
+    }
+
+    fn finish_reload(&mut self, _store: &MessageStore) {
+        self.chart_labels.clear();
+        self.chart_data = std::mem::take(&mut self.reload_data);
+    }
+
+    fn set_date_handler(&mut self, handler: DateHandler) {
+        self.date_handler = handler;
+    }
+
+    fn set_channels(&mut self, channels: Vec<Channel>) {
+        self.channels = channels;
+    }
+
+    fn set_selected_channels(&mut self, selected: HashSet<usize>) {
+        self.selected_channels = selected;
+    }
+}
+
 impl MessageChart {
-    fn show_popup(&mut self, ui: &mut Ui) {
+    fn show_popup(&mut self, ui: &mut Ui, guild_id: i64, event_bus: &mut EventBus) {
         let response = Modal::new(Id::new("customize_view")).show(ui.ctx(), |ui| {
             ui.set_width(300.0);
             ui.set_height(300.0);
@@ -282,347 +324,72 @@ impl MessageChart {
                 }
             }
 
-            self.reload_chart();
+            event_bus.publish(AppEvent::MessageChartNeedsReload(guild_id));
         }
     }
 
-    fn get_target_data(&mut self) -> &BTreeMap<NaiveDateTime, HashMap<i64, Vec<ChartPointData>>> {
-        match self.chart_type {
-            ChartType::Hourly => &mut self.hourly_data,
-            ChartType::Daily => &mut self.daily_data,
-            ChartType::Weekly => &mut self.weekly_data,
-            ChartType::Monthly => &mut self.monthly_data,
-        }
+    #[must_use]
+    pub fn chart_type(&self) -> ChartType {
+        self.chart_type
     }
-    fn handle_message(&mut self, message: &MessageWithUser, event_bus: &mut EventBus) {
-        self.reload_count += 1;
-
-        let guild_id = message.message.guild_id;
-        let channel_id = message.message.channel_id;
-        let username = message.sender.username.to_string();
-        let mut deleted = false;
-
-        let timestamp = if let Some(d) = message.message.delete_timestamp {
-            deleted = true;
-            d
-        } else {
-            message.message.message_timestamp
-        };
-
-        let datetime = DateTime::from_timestamp(timestamp, 0).unwrap();
-        let local_time = datetime.with_timezone(&Local).naive_local();
-
-        let hourly_time = local_time.with_minute(0).unwrap().with_second(0).unwrap();
-        let daily_time = local_time
-            .with_hour(0)
-            .unwrap()
-            .with_minute(0)
-            .unwrap()
-            .with_second(0)
-            .unwrap();
-
-        let monthly_time = local_time
-            .with_second(0)
-            .unwrap()
-            .with_minute(0)
-            .unwrap()
-            .with_hour(0)
-            .unwrap()
-            .with_day(1)
-            .unwrap();
-
-        // We only care about the week number for this. Set it as Monday to keep a common ground
-        let week_day_name = Weekday::Mon;
-        let week_num = local_time.iso_week().week();
-        let time_year = local_time.iso_week().year();
-
-        let weekly_time = NaiveDate::from_isoywd_opt(time_year, week_num, week_day_name)
-            .unwrap()
-            .and_hms_opt(0, 0, 0)
-            .unwrap();
-
-        if let Some(last_hour) = self.last_hour {
-            let missing_hour = (hourly_time - last_hour).num_hours();
-            let mut ongoing_hour = last_hour;
-
-            for _ in 0..missing_hour {
-                let to_add = ongoing_hour.checked_add_signed(Duration::hours(1)).unwrap();
-                self.hourly_data.entry(to_add).or_default();
-                ongoing_hour = to_add;
-            }
-        }
-        self.last_hour = Some(hourly_time);
-
-        if let Some(last_day) = self.last_day {
-            let missing_day = (daily_time - last_day).num_days();
-
-            let mut ongoing_day = last_day;
-            for _ in 0..missing_day {
-                let to_add = ongoing_day.checked_add_signed(Duration::days(1)).unwrap();
-                self.daily_data.entry(to_add).or_default();
-                ongoing_day = to_add;
-            }
-        }
-
-        self.last_day = Some(daily_time);
-
-        if let Some(last_week) = self.last_week {
-            let missing_week = (weekly_time - last_week).num_weeks();
-
-            let mut ongoing_week = last_week;
-            for _ in 0..missing_week {
-                let to_add = ongoing_week.checked_add_signed(Duration::weeks(1)).unwrap();
-                self.weekly_data.entry(to_add).or_default();
-                ongoing_week = to_add;
-            }
-        }
-
-        self.last_week = Some(weekly_time);
-
-        if let Some(last_month) = self.last_month {
-            let mut ongoing_month = last_month;
-
-            while monthly_time > ongoing_month {
-                let to_add = ongoing_month
-                    .checked_add_months(Months::new(1))
-                    .unwrap()
-                    .with_day(1)
-                    .unwrap();
-                self.monthly_data.entry(to_add).or_default();
-                ongoing_month = to_add;
-            }
-        }
-
-        self.last_month = Some(monthly_time);
-
-        let hourly_entry = self.hourly_data.entry(hourly_time).or_default();
-        let target_entry = hourly_entry.entry(channel_id).or_default();
-
-        if deleted {
-            target_entry.push(ChartPointData {
-                user: username.clone(),
-                count: 1,
-                deleted: true,
-            });
-        } else {
-            let mut not_found = true;
-            for point in target_entry.iter_mut() {
-                if point.user == username && !point.deleted {
-                    point.count += 1;
-                    not_found = false;
-                    break;
-                }
-            }
-
-            if not_found {
-                target_entry.push(ChartPointData {
-                    user: username.clone(),
-                    count: 1,
-                    deleted: false,
-                });
-            }
-        }
-
-        let daily_entry = self.daily_data.entry(daily_time).or_default();
-        let target_entry = daily_entry.entry(channel_id).or_default();
 
-        if deleted {
-            target_entry.push(ChartPointData {
-                user: username.clone(),
-                count: 1,
-                deleted: true,
-            });
-        } else {
-            let mut not_found = true;
-            for point in target_entry.iter_mut() {
-                if point.user == username && !point.deleted {
-                    point.count += 1;
-                    not_found = false;
-                    break;
-                }
-            }
-
-            if not_found {
-                target_entry.push(ChartPointData {
-                    user: username.clone(),
-                    count: 1,
-                    deleted: false,
-                });
-            }
-        }
-
-        let weekly_entry = self.weekly_data.entry(weekly_time).or_default();
-        let target_entry = weekly_entry.entry(channel_id).or_default();
+    pub fn set_chart_type(&mut self, chart_type: ChartType) {
+        self.chart_type = chart_type;
+    }
 
-        if deleted {
-            target_entry.push(ChartPointData {
-                user: username.clone(),
-                count: 1,
-                deleted: true,
-            });
-        } else {
-            let mut not_found = true;
-            for point in target_entry.iter_mut() {
-                if point.user == username && !point.deleted {
-                    point.count += 1;
-                    not_found = false;
-                    break;
-                }
-            }
+    /// Name of every series that is shown in the chart
+    #[must_use]
+    pub fn shown_series(&self) -> Vec<String> {
+        self.chart_values
+            .iter()
+            .filter(|(_, shown)| **shown)
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
 
-            if not_found {
-                target_entry.push(ChartPointData {
-                    user: username.clone(),
-                    count: 1,
-                    deleted: false,
-                });
-            }
+    /// Show only the given series. Users that are not in the data yet are added once they are
+    pub fn set_shown_series(&mut self, series: &[String]) {
+        for shown in self.chart_values.values_mut() {
+            *shown = false;
         }
-
-        let monthly_entry = self.monthly_data.entry(monthly_time).or_default();
-        let target_entry = monthly_entry.entry(channel_id).or_default();
-
-        if deleted {
-            target_entry.push(ChartPointData {
-                user: username.clone(),
-                count: 1,
-                deleted: true,
-            });
-        } else {
-            let mut not_found = true;
-            for point in target_entry.iter_mut() {
-                if point.user == username && !point.deleted {
-                    point.count += 1;
-                    not_found = false;
-                    break;
-                }
-            }
-
-            if not_found {
-                target_entry.push(ChartPointData {
-                    user: username.clone(),
-                    count: 1,
-                    deleted: false,
-                });
-            }
+        for name in series {
+            self.chart_values.insert(name.clone(), true);
         }
 
-        self.chart_values.entry(username).or_default();
-
-        if self.reload_count == PAGE_VALUE * 5 {
-            event_bus.publish_if_needed(AppEvent::MessageChartNeedsReload(guild_id));
+        self.chart_data
+            .retain(|name, _| self.chart_values.get(name).is_some_and(|shown| *shown));
+        for name in series {
+            self.chart_data.entry(name.clone()).or_default();
         }
     }
 
-    fn reload_chart(&mut self) {
-        self.reload_count = 0;
-        self.chart_labels.clear();
-
-        let mut selected_channels = HashSet::default();
-
-        if self.selected_channels.is_empty() {
-            for channel in &self.channels {
-                selected_channels.insert(channel.channel_id);
-            }
-        } else {
-            for index in &self.selected_channels {
-                if index == &0_usize {
-                    for channel in &self.channels {
-                        selected_channels.insert(channel.channel_id);
-                    }
-                    break;
-                } else {
-                    let channel_id = self.channels.get(*index - 1).unwrap().channel_id;
-                    selected_channels.insert(channel_id);
-                }
-            }
-        }
-
-        let target_values: HashSet<String> = self.chart_data.keys().cloned().collect();
-        let mut final_data: BTreeMap<String, IndexMap<NaiveDateTime, i64>> = BTreeMap::new();
-        let chart_data = self.get_target_data();
-
-        let do_total_message = target_values.contains("All Messages");
-        let do_deleted_message = target_values.contains("Deleted Messages");
-
-        for (date, data) in chart_data {
-            let mut total_message: i64 = 0;
-            let mut deleted_message: i64 = 0;
-            let mut other_messages: HashMap<String, u32> = HashMap::new();
-
-            for val in &target_values {
-                if val == "All Messages" || val == "Deleted Messages" {
-                    continue;
-                }
-                other_messages.insert(val.to_string(), 0);
-            }
-
-            for (channel, points) in data {
-                if selected_channels.contains(channel) {
-                    for point in points {
-                        if point.deleted && do_deleted_message {
-                            deleted_message += i64::from(point.count);
-                        } else if !point.deleted && do_total_message {
-                            total_message += i64::from(point.count);
-                        }
-
-                        if target_values.contains(&point.user) {
-                            *other_messages.get_mut(&point.user).unwrap() += point.count;
-                        }
-                    }
-                }
-            }
-            if do_deleted_message {
-                final_data
-                    .entry("Deleted Messages".to_string())
-                    .or_default()
-                    .insert(*date, deleted_message);
-            }
-
-            if do_total_message {
-                final_data
-                    .entry("All Messages".to_string())
-                    .or_default()
-                    .insert(*date, total_message);
-            }
-
-            for (user, count) in other_messages {
-                let entry = final_data.entry(user).or_default();
-                entry.insert(*date, i64::from(count));
+    /// Every shown series within the selected date range with one row per date
+    fn export_data(&self) -> ExportData {
+        let mut headers = vec!["Date".to_string()];
+        headers.extend(self.chart_data.keys().cloned());
+        let mut data = ExportData::new(&format!("Message Chart {}", self.chart_type), headers);
+
+        let dates: BTreeSet<NaiveDateTime> = self
+            .chart_data
+            .values()
+            .flat_map(IndexMap::keys)
+            .filter(|date| self.date_handler.within_range(date.date()))
+            .copied()
+            .collect();
+
+        for date in dates {
+            let mut row = vec![date.to_string()];
+            for series in self.chart_data.values() {
+                row.push(series.get(&date).copied().unwrap_or_default().to_string());
             }
+            data.add_row(row);
         }
-        self.chart_data = final_data;
-    }
-
-    pub fn set_date_handler(&mut self, handler: DateHandler) {
-        self.date_handler = handler;
-    }
-
-    pub fn set_channels(&mut self, channels: Vec<Channel>) {
-        self.channels = channels;
-    }
-    pub fn set_selected_channels(&mut self, selected: HashSet<usize>) {
-        self.selected_channels = selected;
+        data
     }
 }
 
 impl TabHandler {
-    pub fn handle_message_message_chart(
-        &mut self,
-        message: &MessageWithUser,
-        event_bus: &mut EventBus,
-    ) {
-        let guild_id = message.message.guild_id;
-        self.message_chart
-            .get_mut(&guild_id)
-            .unwrap()
-            .handle_message(message, event_bus);
-    }
-
-    pub fn reload_message_chart(&mut self, guild_id: i64) {
-        self.message_chart
-            .get_mut(&guild_id)
-            .unwrap()
-            .reload_chart();
+    pub fn message_chart_export_data(&self, guild_id: i64) -> ExportData {
+        self.message_chart.get(&guild_id).unwrap().export_data()
     }
 }
@@ -0,0 +1,468 @@
+use chrono::NaiveDateTime;
+use eframe::egui::ahash::{HashMap, HashMapExt, HashSet};
+use eframe::egui::{Align, Button, Key, Layout, OpenUrl, Response, RichText, TextEdit, Ui};
+use egui_extras::Column;
+use egui_selectable_table::{
+    ColumnOperations, ColumnOrdering, SelectableRow, SelectableTable, SortOrder,
+};
+use funnel_shared::Channel;
+use regex::{Regex, RegexBuilder};
+use std::cmp::Ordering;
+use strum::IntoEnumIterator;
+
+use crate::core::{MessageQuery, MessageRow, MessageStore, SearchColumn, selected_channel_ids};
+use crate::ui::{DateHandler, ShowUI, StoreView, TabHandler};
+use crate::{AppEvent, EventBus, ExportData};
+
+/// Maximum number of matching messages that are shown in the table
+const SEARCH_LIMIT: usize = 5_000;
+
+#[derive(Default)]
+pub struct Config {
+    copy_selected: bool,
+    open_link: Option<String>,
+    copy_link: Option<String>,
+}
+
+impl ColumnOperations<SearchRowData, SearchColumn, Config> for SearchColumn {
+    fn column_text(&self, row: &SearchRowData) -> String {
+        match self {
+            SearchColumn::Time => row.time.to_string(),
+            SearchColumn::Author => row.author.to_string(),
+            SearchColumn::Username => row.username.to_string(),
+            SearchColumn::Channel => row.channel.to_string(),
+            SearchColumn::Content => row.content.to_string(),
+            SearchColumn::Link => row.link.to_string(),
+        }
+    }
+
+    fn create_header(
+        &self,
+        ui: &mut Ui,
+        sort_order: Option<SortOrder>,
+        _table: &mut SelectableTable<SearchRowData, SearchColumn, Config>,
+    ) -> Option<Response> {
+        let mut label_text = self.to_string();
+        let hover_text = match self {
+            SearchColumn::Time => {
+                "The date and time the message was sent. Click to sort by time".to_string()
+            }
+            SearchColumn::Author => {
+                "The global name of the user that sent the message. Click to sort by name"
+                    .to_string()
+            }
+            SearchColumn::Username => {
+                "The username of the user that sent the message. Click to sort by username"
+                    .to_string()
+            }
+            SearchColumn::Channel => {
+                "The channel the message was sent to. Click to sort by channel".to_string()
+            }
+            SearchColumn::Content => "The content of the message".to_string(),
+            SearchColumn::Link => {
+                "Link to the message on Discord. Double click a row to open it".to_string()
+            }
+        };
+
+        let is_selected = if let Some(direction) = sort_order {
+            match direction {
+                SortOrder::Ascending => label_text += " ↓",
+                SortOrder::Descending => label_text += " ↑",
+            }
+            true
+        } else {
+            false
+        };
+
+        let label_text = RichText::new(label_text).strong();
+
+        let response = ui
+            .add_sized(
+                ui.available_size(),
+                Button::selectable(is_selected, label_text),
+            )
+            .on_hover_text(hover_text);
+        Some(response)
+    }
+
+    fn create_table_row(
+        &self,
+        ui: &mut Ui,
+        row: &SelectableRow<SearchRowData, SearchColumn>,
+        column_selected: bool,
+        table: &mut SelectableTable<SearchRowData, SearchColumn, Config>,
+    ) -> Response {
+        let row_data = &row.row_data;
+        let mut show_tooltip = false;
+        let row_text = match self {
+            SearchColumn::Time => row_data.time.to_string(),
+            SearchColumn::Author => row_data.author.clone(),
+            SearchColumn::Username => row_data.username.clone(),
+            SearchColumn::Channel => row_data.channel.clone(),
+            SearchColumn::Content => {
+                show_tooltip = true;
+                row_data.content.clone()
+            }
+            SearchColumn::Link => row_data.link.clone(),
+        };
+        let is_selected = column_selected;
+
+        // Multi-line messages are shown in a single line. The tooltip has the full content
+        let mut label = ui.add_sized(
+            ui.available_size(),
+            Button::selectable(is_selected, row_text.replace('\n', " ")),
+        );
+
+        if show_tooltip {
+            label = label.on_hover_text(row_text);
+        }
+
+        if label.double_clicked() {
+            table.config.open_link = Some(row_data.link.clone());
+        }
+
+        label.context_menu(|ui| {
+            if ui.button("Copy selected rows").clicked() {
+                table.config.copy_selected = true;
+                ui.close();
+            }
+            if ui.button("Open in Discord").clicked() {
+                table.config.open_link = Some(row_data.link.clone());
+                ui.close();
+            }
+            if ui.button("Copy message link").clicked() {
+                table.config.copy_link = Some(row_data.link.clone());
+                ui.close();
+            }
+        });
+        label
+    }
+}
+
+impl ColumnOrdering<SearchRowData> for SearchColumn {
+    fn order_by(&self, row_1: &SearchRowData, row_2: &SearchRowData) -> Ordering {
+        match self {
+            SearchColumn::Time => row_1.time.cmp(&row_2.time),
+            SearchColumn::Author => row_1.author.cmp(&row_2.author),
+            SearchColumn::Username => row_1.username.cmp(&row_2.username),
+            SearchColumn::Channel => row_1.channel.cmp(&row_2.channel),
+            SearchColumn::Content => row_1.content.cmp(&row_2.content),
+            SearchColumn::Link => row_1.link.cmp(&row_2.link),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct SearchRowData {
+    time: NaiveDateTime,
+    author: String,
+    username: String,
+    channel: String,
+    content: String,
+    link: String,
+}
+
+/// The filters of a search as they were entered in the UI
+#[derive(Default, Clone)]
+struct SearchFilter {
+    text: String,
+    regex: bool,
+    case_sensitive: bool,
+    /// Part of the name or the username of the author or the full user ID
+    user: String,
+}
+
+impl SearchFilter {
+    fn is_empty(&self) -> bool {
+        self.text.is_empty() && self.user.is_empty()
+    }
+
+    /// The pattern the content must match. None if there is no text to search for
+    fn content_matcher(&self) -> Result<Option<Regex>, regex::Error> {
+        if self.text.is_empty() {
+            return Ok(None);
+        }
+
+        let pattern = if self.regex {
+            self.text.clone()
+        } else {
+            regex::escape(&self.text)
+        };
+
+        RegexBuilder::new(&pattern)
+            .case_insensitive(!self.case_sensitive)
+            .build()
+            .map(Some)
+    }
+
+    fn matches_user(&self, message: &MessageRow) -> bool {
+        if self.user.is_empty() {
+            return true;
+        }
+
+        let target = self.user.to_lowercase();
+        let user = message.user();
+
+        message.user_id().to_string() == target
+            || user.name.to_lowercase().contains(&target)
+            || user.username.to_lowercase().contains(&target)
+    }
+}
+
+pub struct MessageSearch {
+    table: SelectableTable<SearchRowData, SearchColumn, Config>,
+    /// The rows that are shown in the table
+    rows: Vec<SearchRowData>,
+    guild_id: i64,
+    /// Read only currently selected dates in the UI
+    date_handler: DateHandler,
+    /// Filters that are being edited in the UI
+    input: SearchFilter,
+    /// Filters of the last search that was started
+    filter: SearchFilter,
+    regex_error: Option<String>,
+    total_matches: usize,
+    /// Content pattern of a reload that is still running
+    reload_matcher: Option<Regex>,
+    /// Rows of a reload that is still running
+    reload_rows: Vec<SearchRowData>,
+    /// Matching messages of a reload that is still running including the ones over the limit
+    reload_matches: usize,
+    channels: Vec<Channel>,
+    selected_channels: HashSet<usize>,
+    /// Key: Channel ID. Value: Channel name
+    channel_names: HashMap<i64, String>,
+}
+
+impl ShowUI for MessageSearch {
+    fn show_ui(&mut self, ui: &mut Ui, guild_id: i64, event_bus: &mut EventBus) {
+        let to_copy = self.table.config.copy_selected;
+        if to_copy {
+            self.table.config.copy_selected = false;
+            self.table.copy_selected_cells(ui);
+            event_bus.publish(AppEvent::CellsCopied);
+        }
+
+        if let Some(link) = self.table.config.open_link.take() {
+            ui.ctx().open_url(OpenUrl {
+                url: link,
+                new_tab: true,
+            });
+        }
+
+        if let Some(link) = self.table.config.copy_link.take() {
+            ui.ctx().copy_text(link);
+            event_bus.publish(AppEvent::CellsCopied);
+        }
+
+        ui.horizontal(|ui| {
+            ui.label("Search:");
+            let text_response = ui.add(
+                TextEdit::singleline(&mut self.input.text)
+                    .hint_text("Text in the message")
+                    .desired_width(250.0),
+            );
+            ui.checkbox(&mut self.input.regex, "Regex")
+                .on_hover_text("Search the content with a regular expression");
+            ui.checkbox(&mut self.input.case_sensitive, "Match Case");
+            ui.separator();
+
+            ui.label("User:");
+            let user_response = ui.add(
+                TextEdit::singleline(&mut self.input.user)
+                    .hint_text("Name, username or ID")
+                    .desired_width(150.0),
+            );
+
+            let enter_pressed = (text_response.lost_focus() || user_response.lost_focus())
+                && ui.input(|i| i.key_pressed(Key::Enter));
+
+            if ui.button("Search").clicked() || enter_pressed {
+                self.start_search(guild_id, event_bus);
+            }
+            ui.separator();
+
+            if self.total_matches > SEARCH_LIMIT {
+                ui.label(format!(
+                    "Showing {SEARCH_LIMIT} of {} matches",
+                    self.total_matches
+                ));
+            } else {
+                ui.label(format!("Matches: {}", self.total_matches));
+            }
+        });
+
+        if let Some(error) = &self.regex_error {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+        }
+
+        ui.label("Dates and channels are filtered by the current selection");
+        ui.separator();
+        ui.add_space(5.0);
+
+        self.table.show_ui(ui, |builder| {
+            let mut table = builder
+                .striped(true)
+                .resizable(true)
+                .cell_layout(Layout::left_to_right(Align::Center))
+                .drag_to_scroll(false)
+                .auto_shrink([false; 2])
+                .min_scrolled_height(0.0);
+
+            for column in SearchColumn::iter() {
+                let column = match column {
+                    SearchColumn::Time => Column::initial(150.0),
+                    SearchColumn::Content => Column::initial(500.0).clip(true),
+                    SearchColumn::Link => Column::initial(200.0).clip(true),
+                    _ => Column::initial(100.0).clip(true),
+                };
+                table = table.column(column);
+            }
+            table
+        });
+    }
+}
+
+impl StoreView for MessageSearch {
+    /// The rows are reversed so the most recent matches are kept once the limit is reached
+    fn start_reload(&mut self, _store: &MessageStore) -> Option<MessageQuery> {
+        self.reload_rows.clear();
+        self.reload_matches = 0;
+
+        if self.filter.is_empty() {
+            return None;
+        }
+
+        // The filter was validated before the search was started
+        self.reload_matcher = self.filter.content_matcher().unwrap_or_default();
+
+        let selected_channels = selected_channel_ids(&self.channels, &self.selected_channels);
+        let query = MessageQuery::new()
+            .dates(self.date_handler.from, self.date_handler.to)
+            .channels(selected_channels)
+            .newest_first();
+        Some(query)
+    }
+
+    fn add_message(&mut self, message: &MessageRow) {
+        if !self.filter.matches_user(message) {
+            return;
+        }
+
+        if let Some(matcher) = &self.reload_matcher {
+            let Some(content) = message.content() else {
+                return;
+            };
+            if !matcher.is_match(content) {
+                return;
+            }
+        }
+
+        self.reload_matches += 1;
+        if self.reload_rows.len() >= SEARCH_LIMIT {
+            return;
+        }
+
+        let user = message.user();
+        let channel_id = message.channel_id();
+        let channel = self
+            .channel_names
+            .get(&channel_id)
+            .map_or_else(|| channel_id.to_string(), String::clone);
+
+        self.reload_rows.push(SearchRowData {
+            time: message.sent_time(),
+            author: user.name.clone(),
+            username: user.username.clone(),
+            channel,
+            content: message.content().unwrap_or_default().to_string(),
+            link: format!(
+                "https://discord.com/channels/{}/{channel_id}/{}",
+                self.guild_id,
+                message.message_id()
+            ),
+        });
+    }
+
+    fn finish_reload(&mut self, _store: &MessageStore) {
+        self.table.clear_all_rows();
+
+        self.rows = std::mem::take(&mut self.reload_rows);
+        for row in &self.rows {
+            self.table.add_modify_row(|_| Some(row.clone()));
+        }
+
+        self.total_matches = self.reload_matches;
+        self.reload_matcher = None;
+        self.table.recreate_rows();
+    }
+
+    fn set_date_handler(&mut self, handler: DateHandler) {
+        self.date_handler = handler;
+    }
+
+    fn set_channels(&mut self, channels: Vec<Channel>) {
+        for channel in &channels {
+            self.channel_names
+                .insert(channel.channel_id, channel.channel_name.clone());
+        }
+        self.channels = channels;
+    }
+
+    fn set_selected_channels(&mut self, selected: HashSet<usize>) {
+        self.selected_channels = selected;
+    }
+}
+
+impl MessageSearch {
+    #[must_use]
+    pub fn new(guild_id: i64) -> Self {
+        let table = SelectableTable::new(SearchColumn::iter().collect())
+            .auto_scroll()
+            .horizontal_scroll()
+            .serial_column();
+        Self {
+            table,
+            rows: Vec::new(),
+            guild_id,
+            date_handler: DateHandler::default(),
+            input: SearchFilter::default(),
+            filter: SearchFilter::default(),
+            regex_error: None,
+            total_matches: 0,
+            reload_matcher: None,
+            reload_rows: Vec::new(),
+            reload_matches: 0,
+            channels: Vec::default(),
+            selected_channels: HashSet::default(),
+            channel_names: HashMap::new(),
+        }
+    }
+
+    /// Use the entered filters for the search if the pattern is valid
+    fn start_search(&mut self, guild_id: i64, event_bus: &mut EventBus) {
+        if let Err(e) = self.input.content_matcher() {
+            self.regex_error = Some(format!("Invalid regex: {e}"));
+            return;
+        }
+
+        self.regex_error = None;
+        self.filter = self.input.clone();
+        event_bus.publish(AppEvent::MessageSearchNeedsReload(guild_id));
+    }
+
+    /// The matches that are currently shown in the table
+    fn export_data(&self) -> ExportData {
+        ExportData::from_rows(
+            "Message Search",
+            &self.rows,
+            |column: &SearchColumn, row| column.column_text(row),
+        )
+    }
+}
+
+impl TabHandler {
+    pub fn message_search_export_data(&self, guild_id: i64) -> ExportData {
+        self.message_search.get(&guild_id).unwrap().export_data()
+    }
+}
@@ -1,15 +1,39 @@
+mod channel_profile;
 mod channel_table;
+mod cohorts;
 mod common_words;
+mod dashboard;
+mod deleted_messages;
 mod handler;
+mod heatmap;
+mod interactions;
+mod media;
 mod message_chart;
+mod message_search;
 mod overview;
+mod profile_widgets;
+mod reload;
 mod user_chart;
+mod user_profile;
 mod user_table;
+mod word_filter;
 
+pub use channel_profile::*;
 pub use channel_table::*;
+pub use cohorts::*;
 pub use common_words::*;
+pub use dashboard::*;
+pub use deleted_messages::*;
 pub use handler::*;
+pub use heatmap::*;
+pub use interactions::*;
+pub use media::*;
 pub use message_chart::*;
+pub use message_search::*;
 pub use overview::*;
+pub use profile_widgets::*;
+pub use reload::*;
 pub use user_chart::*;
+pub use user_profile::*;
 pub use user_table::*;
+pub use word_filter::*;
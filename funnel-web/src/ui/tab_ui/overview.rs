@@ -1,33 +1,52 @@
-use chrono::{
-    DateTime, Datelike, Duration, Local, Months, NaiveDate, NaiveDateTime, Timelike, Weekday,
-};
+use chrono::{Datelike, Duration, Months, NaiveDate, NaiveDateTime, Timelike, Weekday};
 use core::ops::RangeInclusive;
-use eframe::egui::Ui;
 use eframe::egui::ahash::{HashMap, HashMapExt, HashSet};
+use eframe::egui::{CollapsingHeader, Grid, RichText, Ui};
 use egui_plot::{AxisHints, GridMark, Legend, Line, Plot, PlotPoint, PlotPoints};
-use funnel_shared::{Channel, MemberActivity, MemberCount, MessageWithUser, PAGE_VALUE};
+use funnel_shared::{Channel, MemberActivity, MemberCount};
 use indexmap::IndexMap;
+use std::collections::BTreeSet;
 use strum::IntoEnumIterator;
 
-use crate::core::to_header;
-use crate::ui::{AnimatedMenuLabel, Card, DateHandler, DateNavigator, ShowUI, TabHandler};
-use crate::{AppEvent, CardData, CardType, ChartType, EventBus};
+use crate::core::{MessageQuery, MessageRow, MessageStore, selected_channel_ids, to_header};
+use crate::ui::{
+    AnimatedMenuLabel, BucketCounts, Card, DateHandler, DateNavigator, ShowUI, StoreView,
+    TabHandler, show_bucket_plot,
+};
+use crate::{
+    AppEvent, CardData, CardType, ChartType, EventBus, ExportData, OverviewCard, Timezone,
+};
+
+/// Number of joins and leaves that are listed in the Overview
+const RECENT_ACTIVITIES: usize = 10;
 
+/// Messages per channel and per member that the cards are built from
 #[derive(Default)]
-pub struct ActivityData {
-    // Channel ID + Message Count
-    message_count: HashMap<i64, u32>,
-    deleted_message: HashMap<i64, u32>,
-    name: String,
+struct MessageCounts {
+    /// Key: Channel ID. Value: Non-deleted messages
+    channel_message_count: HashMap<i64, u32>,
+    /// Key: User ID. Value: Non-deleted messages
+    member_message_count: HashMap<i64, u32>,
+    total_message: u32,
+    deleted_message: u32,
 }
 
-impl ActivityData {
-    fn new(name: String) -> Self {
-        Self {
-            message_count: HashMap::default(),
-            deleted_message: HashMap::default(),
-            name,
+impl MessageCounts {
+    fn add_message(&mut self, message: &MessageRow) {
+        if message.is_deleted() {
+            self.deleted_message += 1;
+            return;
         }
+
+        *self
+            .channel_message_count
+            .entry(message.channel_id())
+            .or_default() += 1;
+        *self
+            .member_message_count
+            .entry(message.user_id())
+            .or_default() += 1;
+        self.total_message += 1;
     }
 }
 
@@ -43,6 +62,20 @@ struct MemberChartData {
     last_month: Option<NaiveDateTime>,
 }
 
+/// A join or leave that is listed in the Overview
+struct RecentActivity {
+    time: NaiveDateTime,
+    name: String,
+    is_join: bool,
+}
+
+/// A member that joined within the selected dates
+struct Newcomer {
+    joined: NaiveDateTime,
+    /// The first message that was sent in the selected channels after joining
+    first_message: Option<NaiveDateTime>,
+}
+
 #[derive(Default)]
 pub struct OverviewData {
     total_message: u32,
@@ -53,6 +86,10 @@ pub struct OverviewData {
     member_leaves: u32,
     most_active_member: String,
     most_active_channel: String,
+    joined_posted: u32,
+    median_first_message: String,
+    /// Every join within the dates with a known member
+    newcomers: Vec<Newcomer>,
 }
 
 pub struct Overview {
@@ -63,16 +100,24 @@ pub struct Overview {
     chart_type: ChartType,
     chart_labels: Vec<(NaiveDateTime, i64, i64, i64)>,
     chart_data: HashMap<String, MemberChartData>,
-    activity_data: HashMap<NaiveDate, HashMap<String, ActivityData>>,
     channel_map: HashMap<i64, String>,
     data: OverviewData,
+    /// The newest joins and leaves within the selected dates
+    recent_activities: Vec<RecentActivity>,
     compare_data: Option<OverviewData>,
     card_size: f32,
     compare_nav: DateNavigator,
     compare_size: f32,
     date_handler: DateHandler,
+    timezone: Timezone,
     max_content: usize,
-    reload_count: u64,
+    /// Message counts of a reload that is still running
+    reload_counts: MessageCounts,
+    /// User ID and time of the joins whose first message was not looked up yet during a reload
+    reload_joins: Vec<(i64, NaiveDateTime)>,
+    reload_newcomers: Vec<Newcomer>,
+    /// Channel IDs of the selected channels of a reload that is still running
+    reload_channels: HashSet<i64>,
     channels: Vec<Channel>,
     selected_channels: HashSet<usize>,
 }
@@ -92,16 +137,20 @@ impl Default for Overview {
             chart_type: ChartType::default(),
             chart_labels: Vec::default(),
             chart_data,
-            activity_data: HashMap::default(),
             channel_map: HashMap::default(),
             data: OverviewData::default(),
+            recent_activities: Vec::default(),
             compare_data: Option::default(),
             card_size: f32::default(),
             compare_nav: DateNavigator::default(),
             compare_size: f32::default(),
             date_handler: DateHandler::default(),
+            timezone: Timezone::default(),
             max_content: usize::default(),
-            reload_count: u64::default(),
+            reload_counts: MessageCounts::default(),
+            reload_joins: Vec::default(),
+            reload_newcomers: Vec::default(),
+            reload_channels: HashSet::default(),
             channels: Vec::default(),
             selected_channels: HashSet::default(),
         }
@@ -115,13 +164,156 @@ impl ShowUI for Overview {
             ui.vertical(|ui| {
                 self.show_card_ui(ui);
             });
+            self.show_recent_activities(ui);
+            self.show_conversion_chart(ui);
             ui.add_space(10.0);
         }
         self.show_member_chart(ui);
     }
 }
 
+impl StoreView for Overview {
+    fn start_reload(&mut self, store: &MessageStore) -> Option<MessageQuery> {
+        self.reload_counts = MessageCounts::default();
+        self.reload_newcomers.clear();
+        self.reload_joins = known_joins(store, self.date_handler);
+
+        let selected_channels = selected_channel_ids(&self.channels, &self.selected_channels);
+        self.reload_channels.clone_from(&selected_channels);
+        let query = MessageQuery::new()
+            .dates(self.date_handler.from, self.date_handler.to)
+            .channels(selected_channels);
+        Some(query)
+    }
+
+    fn add_message(&mut self, message: &MessageRow) {
+        self.reload_counts.add_message(message);
+    }
+
+    fn finish_chunk(&mut self, store: &MessageStore, limit: usize) -> bool {
+        let remaining = self.reload_joins.len().saturating_sub(limit);
+        for (user_id, joined) in self.reload_joins.split_off(remaining) {
+            let newcomer = find_newcomer(store, &self.reload_channels, user_id, joined);
+            self.reload_newcomers.push(newcomer);
+        }
+        self.reload_joins.is_empty()
+    }
+
+    fn finish_reload(&mut self, store: &MessageStore) {
+        self.chart_labels.clear();
+
+        let counts = std::mem::take(&mut self.reload_counts);
+        let newcomers = std::mem::take(&mut self.reload_newcomers);
+        self.data = self.overview_data(store, counts, newcomers, self.date_handler, "");
+
+        let mut activities: Vec<_> = store
+            .activities()
+            .iter()
+            .filter(|activity| self.date_handler.within_range(activity.time.date()))
+            .collect();
+        activities.sort_by_key(|activity| std::cmp::Reverse(activity.time));
+        self.recent_activities = activities
+            .into_iter()
+            .take(RECENT_ACTIVITIES)
+            .map(|activity| RecentActivity {
+                time: activity.time,
+                name: store.activity_name(activity),
+                is_join: activity.is_join,
+            })
+            .collect();
+    }
+
+    fn set_date_handler(&mut self, handler: DateHandler) {
+        self.date_handler = handler;
+        *self.compare_nav.handler().from() = handler.to;
+        *self.compare_nav.handler().to() = handler.to;
+    }
+
+    fn set_channels(&mut self, channels: Vec<Channel>) {
+        self.channels = channels;
+    }
+
+    fn set_selected_channels(&mut self, selected: HashSet<usize>) {
+        self.selected_channels = selected;
+    }
+}
+
 impl Overview {
+    /// New members and the new members that posted afterwards per bucket of the chart type
+    fn show_conversion_chart(&self, ui: &mut Ui) {
+        CollapsingHeader::new("Newcomer Conversion").show(ui, |ui| {
+            let mut joined: BucketCounts = self
+                .chart_type
+                .buckets(self.date_handler.from, self.date_handler.to)
+                .into_iter()
+                .map(|bucket| (bucket, 0))
+                .collect();
+            let mut posted = joined.clone();
+
+            for newcomer in &self.data.newcomers {
+                let bucket = self.chart_type.bucket_start(newcomer.joined);
+                let Some(count) = joined.get_mut(&bucket) else {
+                    continue;
+                };
+                *count += 1;
+                if newcomer.first_message.is_some() {
+                    *posted.entry(bucket).or_default() += 1;
+                }
+            }
+
+            show_bucket_plot(
+                ui,
+                "overview_conversion_chart".to_string(),
+                self.chart_type,
+                &[("Joined", &joined), ("Joined and Posted", &posted)],
+            );
+        });
+    }
+
+    /// The newest members that joined or left within the selected dates
+    fn show_recent_activities(&self, ui: &mut Ui) {
+        CollapsingHeader::new("Recent Joins and Leaves").show(ui, |ui| {
+            if self.recent_activities.is_empty() {
+                ui.label("No member joined or left within the selected dates");
+                return;
+            }
+
+            Grid::new("overview_recent_activities")
+                .num_columns(3)
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.label(RichText::new("Time").strong());
+                    ui.label(RichText::new("Member").strong());
+                    ui.label(RichText::new("Activity").strong());
+                    ui.end_row();
+
+                    for activity in &self.recent_activities {
+                        ui.label(activity.time.to_string());
+                        ui.label(&activity.name);
+                        ui.label(if activity.is_join { "Joined" } else { "Left" });
+                        ui.end_row();
+                    }
+                });
+        });
+    }
+
+    /// The value of the card as it is shown in the Overview
+    #[must_use]
+    pub fn card_value(&self, card: OverviewCard) -> String {
+        match card {
+            OverviewCard::TotalMessage => self.data.total_message.to_string(),
+            OverviewCard::DeletedMessage => self.data.deleted_message.to_string(),
+            OverviewCard::UniqueUser => self.data.unique_user.to_string(),
+            OverviewCard::MemberCount => self.data.member_count.to_string(),
+            OverviewCard::MemberJoin => self.data.member_joins.to_string(),
+            OverviewCard::MemberLeave => self.data.member_leaves.to_string(),
+            OverviewCard::MostActiveMember => self.data.most_active_member.clone(),
+            OverviewCard::MostActiveChannel => self.data.most_active_channel.clone(),
+            OverviewCard::JoinedPosted => self.data.joined_posted.to_string(),
+            OverviewCard::MedianFirstMessage => self.data.median_first_message.clone(),
+        }
+    }
+
     fn get_target_data_count(&self) -> &IndexMap<NaiveDateTime, i64> {
         match self.chart_type {
             ChartType::Hourly => &self.get_count().hourly,
@@ -139,6 +331,7 @@ impl Overview {
             ChartType::Monthly => &self.get_joins().monthly,
         }
     }
+
     fn get_target_data_leaves(&self) -> &IndexMap<NaiveDateTime, i64> {
         match self.chart_type {
             ChartType::Hourly => &self.get_leaves().hourly,
@@ -171,9 +364,11 @@ impl Overview {
             unreachable!()
         }
     }
+
     fn get_count(&self) -> &MemberChartData {
         self.chart_data.get("count").unwrap()
     }
+
     fn get_count_m(&mut self) -> &mut MemberChartData {
         self.chart_data.get_mut("count").unwrap()
     }
@@ -463,6 +658,7 @@ impl Overview {
         let member_count_id = ui.make_persistent_id("overview_member_count");
         let member_join_id = ui.make_persistent_id("overview_member_join");
         let member_leave_id = ui.make_persistent_id("overview_member_leave");
+        let joined_posted_id = ui.make_persistent_id("overview_joined_posted");
 
         let compare_total_message = ui.make_persistent_id("overview_compare_message");
         let compare_deleted_message = ui.make_persistent_id("overview_compare_deleted_message");
@@ -470,6 +666,7 @@ impl Overview {
         let compare_member_count = ui.make_persistent_id("overview_compare_member_count");
         let compare_member_join = ui.make_persistent_id("overview_compare_member_join");
         let compare_member_leave = ui.make_persistent_id("overview_compare_member_leave");
+        let compare_joined_posted = ui.make_persistent_id("overview_compare_joined_posted");
 
         let space_3_item = ui.make_persistent_id("card_space_3");
         let space_2_item = ui.make_persistent_id("card_space_2");
@@ -489,6 +686,8 @@ impl Overview {
                 .animate_value_with_time(compare_member_join, 0.0, 0.0);
             ui.ctx()
                 .animate_value_with_time(compare_member_leave, 0.0, 0.0);
+            ui.ctx()
+                .animate_value_with_time(compare_joined_posted, 0.0, 0.0);
         }
 
         let x_size = if self.max_content != usize::default() && has_compare {
@@ -512,6 +711,7 @@ impl Overview {
             ui.ctx().animate_value_with_time(member_count_id, 0.0, 0.0);
             ui.ctx().animate_value_with_time(member_join_id, 0.0, 0.0);
             ui.ctx().animate_value_with_time(member_leave_id, 0.0, 0.0);
+            ui.ctx().animate_value_with_time(joined_posted_id, 0.0, 0.0);
         } else {
             let max_size = ui.available_width();
             let space_taken = 3.0 * self.card_size;
@@ -708,236 +908,156 @@ impl Overview {
             ))
             .on_hover_text(hover_text);
         });
-    }
-
-    fn handle_message(&mut self, message: &MessageWithUser, event_bus: &mut EventBus) {
-        let username = &message.sender.username;
-        let channel_id = message.message.channel_id;
-        let guild_id = message.message.guild_id;
-
-        let mut deleted_message = false;
-
-        let timestamp = if let Some(delete_timestamp) = message.message.delete_timestamp {
-            deleted_message = true;
-            delete_timestamp
-        } else {
-            message.message.message_timestamp
-        };
-
-        let datetime = DateTime::from_timestamp(timestamp, 0).unwrap();
-        let local_time = datetime.with_timezone(&Local).naive_local();
-        let local_date = local_time.date();
-        let activity = ActivityData::new(username.to_string());
 
-        let entry = self.activity_data.entry(local_date).or_default();
+        ui.add_space(5.0);
 
-        let target_entry = entry.entry(username.to_string()).or_insert(activity);
+        ui.horizontal(|ui| {
+            ui.add_space(space_2);
 
-        if deleted_message {
-            let deleted_entry = target_entry.deleted_message.entry(channel_id).or_default();
-            *deleted_entry += 1;
-        } else {
-            let count_entry = target_entry.message_count.entry(channel_id).or_default();
-            *count_entry += 1;
-        }
+            let (compare_id, compare_num) = if has_compare {
+                (
+                    Some(compare_joined_posted),
+                    Some(self.compare_data.as_ref().unwrap().joined_posted),
+                )
+            } else {
+                (None, None)
+            };
+            CardData {
+                card_type: CardType::JoinedPosted,
+                compare_id,
+                compare_num,
+                number: self.data.joined_posted,
+                id: joined_posted_id,
+                x_size,
+                y_size,
+            }
+            .add_to_ui(ui, &mut self.max_content);
 
-        self.reload_count += 1;
+            let mut hover_text = format!(
+                "The median time new members within the selected date took to send their first message: {}",
+                &self.data.median_first_message
+            );
+            if has_compare {
+                let comparing_with = &self.compare_data.as_ref().unwrap().median_first_message;
+                hover_text += &format!(
+                    "\nThe median time new members within the compare date took to send their first message: {comparing_with}"
+                );
+            }
 
-        if self.reload_count == PAGE_VALUE * 5 {
-            event_bus.publish_if_needed(AppEvent::OverviewNeedsReload(guild_id));
-        }
+            ui.add(Card::new(
+                to_header("Median Time to First Message"),
+                to_header(&self.data.median_first_message),
+                x_size,
+                y_size,
+            ))
+            .on_hover_text(hover_text);
+        });
     }
 
-    fn reload_overview(&mut self) {
-        self.chart_labels.clear();
-
-        let mut selected_channels = HashSet::default();
-        let mut channel_message_count = HashMap::new();
-        let mut member_message_count = HashMap::new();
-        let mut total_message = 0;
-        let mut deleted_message = 0;
-        self.reload_count = 0;
+    pub fn create_compare_data(&mut self, store: &MessageStore) {
+        self.max_content = usize::default();
 
-        let mut member_joins = 0;
-        let mut member_leaves = 0;
+        let handler = self.compare_nav.handler_i();
+        let query = MessageQuery::new().dates(handler.from, handler.to);
 
-        if self.selected_channels.is_empty() {
-            for channel in &self.channels {
-                selected_channels.insert(channel.channel_id);
-            }
-        } else {
-            for index in &self.selected_channels {
-                if index == &0_usize {
-                    for channel in &self.channels {
-                        selected_channels.insert(channel.channel_id);
-                    }
-                    break;
-                } else {
-                    let channel_id = self.channels.get(*index - 1).unwrap().channel_id;
-                    selected_channels.insert(channel_id);
-                }
-            }
+        let mut counts = MessageCounts::default();
+        for message in store.query(query) {
+            counts.add_message(&message);
         }
 
-        self.activity_data
-            .iter()
-            .filter(|(date, _)| self.date_handler.within_range(**date))
-            .for_each(|(_, activities)| {
-                for activity in activities.values() {
-                    for (&channel_id, &count) in &activity.message_count {
-                        if !selected_channels.contains(&channel_id) {
-                            continue;
-                        }
-                        *channel_message_count.entry(channel_id).or_insert(0) += count;
-                        *member_message_count
-                            .entry(activity.name.clone())
-                            .or_insert(0) += count;
-                        total_message += count;
-                    }
-
-                    for (&channel_id, &count) in &activity.deleted_message {
-                        if !selected_channels.contains(&channel_id) {
-                            continue;
-                        }
-                        deleted_message += count;
-                    }
-                }
-            });
+        let selected_channels = selected_channel_ids(&self.channels, &self.selected_channels);
+        let newcomers = known_joins(store, handler)
+            .into_iter()
+            .map(|(user_id, joined)| find_newcomer(store, &selected_channels, user_id, joined))
+            .collect();
 
-        self.chart_data
-            .get("joins")
-            .unwrap()
-            .daily
-            .iter()
-            .filter(|(date, _)| self.date_handler.within_range(date.date()))
-            .for_each(|(_, count)| {
-                member_joins += count;
-            });
+        self.compare_data =
+            Some(self.overview_data(store, counts, newcomers, handler, "Not Found"));
+    }
 
-        self.chart_data
-            .get("leaves")
-            .unwrap()
-            .daily
-            .iter()
-            .filter(|(date, _)| self.date_handler.within_range(date.date()))
-            .for_each(|(_, count)| {
-                member_leaves += count;
-            });
+    /// Summary of the counted messages and of the members within the dates of the handler.
+    /// `not_found` is used as the name when there is no active member or channel
+    fn overview_data(
+        &self,
+        store: &MessageStore,
+        counts: MessageCounts,
+        newcomers: Vec<Newcomer>,
+        handler: DateHandler,
+        not_found: &str,
+    ) -> OverviewData {
+        let count_within = |data: &MemberChartData| -> i64 {
+            data.daily
+                .iter()
+                .filter(|(date, _)| handler.within_range(date.date()))
+                .map(|(_, count)| count)
+                .sum()
+        };
+        let member_joins = count_within(self.get_joins());
+        let member_leaves = count_within(self.get_leaves());
 
-        let unique_user = member_message_count.len() as u32;
+        let unique_user = counts.member_message_count.len() as u32;
 
-        let most_active_channel = channel_message_count
+        let most_active_member = counts
+            .member_message_count
             .into_iter()
             .max_by_key(|&(_, count)| count)
-            .unwrap_or((0, 0));
+            .and_then(|(user_id, _)| store.user(user_id))
+            .map_or_else(|| not_found.to_string(), |user| user.username.clone());
 
-        let most_active_member = member_message_count
+        let most_active_channel = counts
+            .channel_message_count
             .into_iter()
             .max_by_key(|&(_, count)| count)
-            .unwrap_or((String::new(), 0));
+            .and_then(|(channel_id, _)| self.channel_map.get(&channel_id))
+            .map_or_else(|| not_found.to_string(), String::clone);
 
-        let channel_name = if let Some(name) = self.channel_map.get(&most_active_channel.0) {
-            name.to_string()
+        let mut first_message_delays: Vec<Duration> = newcomers
+            .iter()
+            .filter_map(|newcomer| {
+                newcomer
+                    .first_message
+                    .map(|first_message| first_message - newcomer.joined)
+            })
+            .collect();
+        first_message_delays.sort();
+
+        let joined_posted = first_message_delays.len();
+        let median_first_message = if joined_posted == 0 {
+            not_found.to_string()
+        } else if joined_posted.is_multiple_of(2) {
+            let middle = joined_posted / 2;
+            format_duration((first_message_delays[middle - 1] + first_message_delays[middle]) / 2)
         } else {
-            String::new()
+            format_duration(first_message_delays[joined_posted / 2])
         };
 
-        let to_date = self.date_handler.to;
-
-        let overview = OverviewData {
-            total_message,
-            deleted_message,
-            member_count: self.find_member_count(to_date),
+        OverviewData {
+            total_message: counts.total_message,
+            deleted_message: counts.deleted_message,
+            member_count: self.find_member_count(handler.to),
             unique_user,
             member_joins: member_joins as u32,
             member_leaves: member_leaves as u32,
-            most_active_member: most_active_member.0,
-            most_active_channel: channel_name,
-        };
-        self.data = overview;
+            most_active_member,
+            most_active_channel,
+            joined_posted: joined_posted as u32,
+            median_first_message,
+            newcomers,
+        }
     }
 
-    pub fn create_compare_data(&mut self) {
-        self.max_content = usize::default();
-        let mut channel_message_count = HashMap::new();
-        let mut member_message_count = HashMap::new();
-        let mut total_message = 0;
-
-        let mut member_joins = 0;
-        let mut member_leaves = 0;
-
-        self.activity_data
-            .iter()
-            .filter(|(date, _)| self.compare_nav.handler().within_range(**date))
-            .for_each(|(_, activities)| {
-                for activity in activities.values() {
-                    for (&channel_id, &count) in &activity.message_count {
-                        *channel_message_count.entry(channel_id).or_insert(0) += count;
-                        *member_message_count
-                            .entry(activity.name.clone())
-                            .or_insert(0) += count;
-                        total_message += count;
-                    }
-                }
-            });
-
-        self.chart_data
-            .get("joins")
-            .unwrap()
-            .daily
-            .iter()
-            .filter(|(date, _)| self.compare_nav.handler().within_range(date.date()))
-            .for_each(|(_, count)| {
-                member_joins += count;
-            });
-
-        self.chart_data
-            .get("leaves")
-            .unwrap()
-            .daily
-            .iter()
-            .filter(|(date, _)| self.compare_nav.handler().within_range(date.date()))
-            .for_each(|(_, count)| {
-                member_leaves += count;
-            });
-
-        let unique_user = member_message_count.len() as u32;
-
-        let most_active_channel = channel_message_count
-            .into_iter()
-            .max_by_key(|&(_, count)| count)
-            .unwrap_or((0, 0));
-
-        let most_active_member = member_message_count
-            .into_iter()
-            .max_by_key(|&(_, count)| count)
-            .unwrap_or((String::from("Not Found"), 0));
-
-        let channel_name = if let Some(name) = self.channel_map.get(&most_active_channel.0) {
-            name.to_string()
-        } else {
-            "Not Found".to_string()
-        };
-
-        let compare_to_date = self.compare_nav.handler_i().to;
+    #[must_use]
+    pub fn chart_type(&self) -> ChartType {
+        self.chart_type
+    }
 
-        let overview = OverviewData {
-            total_message,
-            deleted_message: 0,
-            member_count: self.find_member_count(compare_to_date),
-            unique_user,
-            member_joins: member_joins as u32,
-            member_leaves: member_leaves as u32,
-            most_active_member: most_active_member.0,
-            most_active_channel: channel_name,
-        };
-        self.compare_data = Some(overview);
+    pub fn set_chart_type(&mut self, chart_type: ChartType) {
+        self.chart_type = chart_type;
+        self.chart_labels.clear();
     }
 
-    pub fn set_date_handler(&mut self, handler: DateHandler) {
-        self.date_handler = handler;
-        *self.compare_nav.handler().from() = handler.to;
-        *self.compare_nav.handler().to() = handler.to;
+    pub fn set_timezone(&mut self, timezone: Timezone) {
+        self.timezone = timezone;
     }
 
     fn set_channel_id_map(&mut self, channel_list: Vec<Channel>) {
@@ -1082,8 +1202,7 @@ impl Overview {
         joins: bool,
         leaves: bool,
     ) -> (NaiveDateTime, NaiveDateTime, NaiveDateTime, NaiveDateTime) {
-        let datetime = DateTime::from_timestamp(timestamp, 0).unwrap();
-        let local_time = datetime.with_timezone(&Local).naive_local();
+        let local_time = self.timezone.naive_time(timestamp);
 
         let hourly_time = local_time.with_minute(0).unwrap().with_second(0).unwrap();
         let daily_time = local_time
@@ -1212,45 +1331,65 @@ impl Overview {
         (hourly_time, daily_time, weekly_time, monthly_time)
     }
 
+    /// Add empty joins and leaves for dates without any. Existing activity is kept.
     fn fill_member_activity(&mut self) {
         for data in self.get_count().hourly.clone() {
-            self.get_joins_m().hourly.insert(data.0, 0);
-            self.get_leaves_m().hourly.insert(data.0, 0);
+            self.get_joins_m().hourly.entry(data.0).or_insert(0);
+            self.get_leaves_m().hourly.entry(data.0).or_insert(0);
         }
 
         for data in self.get_count().daily.clone() {
-            self.get_joins_m().daily.insert(data.0, 0);
-            self.get_leaves_m().daily.insert(data.0, 0);
+            self.get_joins_m().daily.entry(data.0).or_insert(0);
+            self.get_leaves_m().daily.entry(data.0).or_insert(0);
         }
 
         for data in self.get_count().weekly.clone() {
-            self.get_joins_m().weekly.insert(data.0, 0);
-            self.get_leaves_m().weekly.insert(data.0, 0);
+            self.get_joins_m().weekly.entry(data.0).or_insert(0);
+            self.get_leaves_m().weekly.entry(data.0).or_insert(0);
         }
 
         for data in self.get_count().monthly.clone() {
-            self.get_joins_m().monthly.insert(data.0, 0);
-            self.get_leaves_m().monthly.insert(data.0, 0);
+            self.get_joins_m().monthly.entry(data.0).or_insert(0);
+            self.get_leaves_m().monthly.entry(data.0).or_insert(0);
         }
     }
 
-    pub fn set_channels(&mut self, channels: Vec<Channel>) {
-        self.channels = channels;
-    }
-    pub fn set_selected_channels(&mut self, selected: HashSet<usize>) {
-        self.selected_channels = selected;
+    /// The shown member series within the selected date range with one row per date
+    fn export_data(&self) -> ExportData {
+        let mut series = Vec::new();
+        if self.show_count {
+            series.push(("Total Members", self.get_target_data_count()));
+        }
+        if self.show_joins {
+            series.push(("Joins", self.get_target_data_joins()));
+        }
+        if self.show_leaves {
+            series.push(("Leaves", self.get_target_data_leaves()));
+        }
+
+        let mut headers = vec!["Date".to_string()];
+        headers.extend(series.iter().map(|(name, _)| (*name).to_string()));
+        let mut data = ExportData::new(&format!("Overview {}", self.chart_type), headers);
+
+        let dates: BTreeSet<NaiveDateTime> = series
+            .iter()
+            .flat_map(|(_, values)| values.keys())
+            .filter(|date| self.date_handler.within_range(date.date()))
+            .copied()
+            .collect();
+
+        for date in dates {
+            let mut row = vec![date.to_string()];
+            for (_, values) in &series {
+                row.push(values.get(&date).copied().unwrap_or_default().to_string());
+            }
+            data.add_row(row);
+        }
+        data
     }
 }
 
 impl TabHandler {
-    pub fn handle_message_overview(&mut self, message: &MessageWithUser, event_bus: &mut EventBus) {
-        let guild_id = message.message.guild_id;
-        self.overview
-            .get_mut(&guild_id)
-            .unwrap()
-            .handle_message(message, event_bus);
-    }
-
     pub fn set_overview_channel_map(&mut self, guild_id: i64, channels: Vec<Channel>) {
         self.overview
             .get_mut(&guild_id)
@@ -1258,15 +1397,12 @@ impl TabHandler {
             .set_channel_id_map(channels);
     }
 
-    pub fn reload_overview(&mut self, guild_id: i64) {
-        self.overview.get_mut(&guild_id).unwrap().reload_overview();
-    }
-
     pub fn compare_overview(&mut self, guild_id: i64) {
+        let store = self.message_store.get(&guild_id).unwrap();
         self.overview
             .get_mut(&guild_id)
             .unwrap()
-            .create_compare_data();
+            .create_compare_data(store);
     }
 
     pub fn stop_compare_overview(&mut self, guild_id: i64) {
@@ -1291,6 +1427,10 @@ impl TabHandler {
         activity: MemberActivity,
         event_bus: &mut EventBus,
     ) {
+        self.message_store
+            .get_mut(&guild_id)
+            .unwrap()
+            .add_activity(&activity);
         self.overview
             .get_mut(&guild_id)
             .unwrap()
@@ -1311,4 +1451,53 @@ impl TabHandler {
             .unwrap()
             .fill_member_activity();
     }
+
+    pub fn overview_export_data(&self, guild_id: i64) -> ExportData {
+        self.overview.get(&guild_id).unwrap().export_data()
+    }
+}
+
+/// The duration with its two largest units
+fn format_duration(duration: Duration) -> String {
+    let days = duration.num_days();
+    let hours = duration.num_hours() % 24;
+    let minutes = duration.num_minutes() % 60;
+
+    if days > 0 {
+        format!("{days}d {hours}h")
+    } else if hours > 0 {
+        format!("{hours}h {minutes}m")
+    } else {
+        format!("{minutes}m")
+    }
+}
+
+/// User ID and time of every join of a known member within the dates of the handler
+fn known_joins(store: &MessageStore, handler: DateHandler) -> Vec<(i64, NaiveDateTime)> {
+    store
+        .activities()
+        .iter()
+        .filter(|activity| activity.is_join && handler.within_range(activity.time.date()))
+        .filter_map(|activity| Some((activity.user_id?, activity.time)))
+        .collect()
+}
+
+/// The join of a member with the first message the member sent in the channels after it
+fn find_newcomer(
+    store: &MessageStore,
+    channels: &HashSet<i64>,
+    user_id: i64,
+    joined: NaiveDateTime,
+) -> Newcomer {
+    let first_message = store
+        .query(MessageQuery::new().user(user_id))
+        .filter(|message| channels.contains(&message.channel_id()))
+        .map(|message| message.sent_time())
+        .filter(|sent_time| *sent_time >= joined)
+        .min();
+
+    Newcomer {
+        joined,
+        first_message,
+    }
 }
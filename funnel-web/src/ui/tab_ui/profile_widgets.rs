@@ -0,0 +1,66 @@
+use std::collections::BTreeMap;
+use std::ops::RangeInclusive;
+
+use chrono::{Datelike, NaiveDate};
+use eframe::egui::{Grid, RichText, Ui};
+use egui_plot::{AxisHints, GridMark, Legend, Line, Plot, PlotPoint, PlotPoints};
+
+/// Key: The date. Value: The count on that date
+pub type DateCounts = BTreeMap<NaiveDate, u32>;
+
+fn format_plot_date(value: f64) -> String {
+    NaiveDate::from_num_days_from_ce_opt(value.round() as i32)
+        .map(|date| date.format("%y-%m-%d").to_string())
+        .unwrap_or_default()
+}
+
+/// Plot every series as a line with one point per date
+pub fn show_date_plot(ui: &mut Ui, id: &str, series: &[(&str, &DateCounts)]) {
+    let date_axis = |mark: GridMark, _range: &RangeInclusive<f64>| format_plot_date(mark.value);
+
+    let hover_label =
+        |name: &str, val: &PlotPoint| format!("{}\n{name}: {:.0}", format_plot_date(val.x), val.y);
+
+    Plot::new(id)
+        .height(200.0)
+        .legend(Legend::default().background_alpha(0.0))
+        .custom_x_axes(vec![AxisHints::new_x().formatter(date_axis)])
+        .label_formatter(hover_label)
+        .clamp_grid(true)
+        .show(ui, |plot_ui| {
+            for (name, data) in series {
+                let points: PlotPoints = data
+                    .iter()
+                    .map(|(date, count)| [f64::from(date.num_days_from_ce()), f64::from(*count)])
+                    .collect();
+                plot_ui.line(Line::new(*name, points).name(*name));
+            }
+        });
+}
+
+/// A two column grid of names with their counts
+pub fn show_count_grid(
+    ui: &mut Ui,
+    id: &str,
+    headers: [&str; 2],
+    rows: &[(String, u32)],
+    empty_text: &str,
+) {
+    if rows.is_empty() {
+        ui.label(empty_text);
+        return;
+    }
+
+    Grid::new(id).num_columns(2).striped(true).show(ui, |ui| {
+        for header in headers {
+            ui.label(RichText::new(header).strong());
+        }
+        ui.end_row();
+
+        for (name, count) in rows {
+            ui.label(name);
+            ui.label(count.to_string());
+            ui.end_row();
+        }
+    });
+}
@@ -0,0 +1,195 @@
+use eframe::egui::ahash::HashSet;
+use eframe::egui::{ProgressBar, Ui};
+use funnel_shared::Channel;
+use log::info;
+use strum::IntoEnumIterator;
+
+use crate::ui::{DateHandler, ReloadTab, TabHandler};
+use crate::{MessageQuery, MessageRow, MessageStore, TabState};
+
+/// Number of messages that are checked against the query of a running reload on every frame
+const RELOAD_CHUNK: usize = 25_000;
+/// Number of items that are finished on every frame once every message was added
+const FINISH_CHUNK: usize = 1_000;
+
+/// A tab that is built from the messages of the store. The result of a reload is built next to
+/// the shown one and only swapped in once every message was added
+pub trait StoreView {
+    /// Start a new result and return the query of every message that is needed to build it.
+    /// Nothing is added if there is no query
+    fn start_reload(&mut self, store: &MessageStore) -> Option<MessageQuery>;
+    /// Add a message to the result that is being built
+    fn add_message(&mut self, message: &MessageRow);
+    /// Do up to `limit` items of the work that is left after every message was added. Returns
+    /// true once nothing is left
+    fn finish_chunk(&mut self, _store: &MessageStore, _limit: usize) -> bool {
+        true
+    }
+    /// Replace the shown result with the one that was built
+    fn finish_reload(&mut self, store: &MessageStore);
+    /// Use the dates that are selected in the UI
+    fn set_date_handler(&mut self, handler: DateHandler);
+    /// Use the channels of the guild. Ignored by the tabs that do not filter by channel
+    fn set_channels(&mut self, _channels: Vec<Channel>) {}
+    /// Use the channels that are selected in the UI
+    fn set_selected_channels(&mut self, _selected: HashSet<usize>) {}
+}
+
+/// A tab reload that goes through its messages a chunk at a time over multiple frames
+pub struct RunningReload {
+    reload_type: ReloadTab,
+    query: MessageQuery,
+    /// Rows found by the most selective index of the query that still have to be checked
+    rows: Vec<usize>,
+    position: usize,
+}
+
+impl RunningReload {
+    /// Ratio of the messages that were already checked
+    fn progress(&self) -> f32 {
+        if self.rows.is_empty() {
+            return 1.0;
+        }
+        self.position as f32 / self.rows.len() as f32
+    }
+}
+
+impl TabHandler {
+    /// Start the first pending reload of the shown tab if nothing is running and continue the
+    /// running reload by one chunk
+    pub fn process_pending_reloads(&mut self, state: TabState) {
+        if self.running_reload.is_none() {
+            let next_reload = self.pending_reloads.iter().position(|reload| {
+                reload.guild_id == self.current_guild
+                    && reload.reload_type.guild_id() == self.current_guild
+                    && reload.reload_type.tab_state() == state
+            });
+
+            if let Some(index) = next_reload {
+                let pending_reload = self.pending_reloads.remove(index);
+                info!("Reloading {state} for {}", pending_reload.guild_id);
+                self.start_reload(pending_reload.reload_type);
+            }
+        }
+
+        self.continue_reload();
+    }
+
+    fn start_reload(&mut self, reload_type: ReloadTab) {
+        // The phrases are counted with the filter that is set when the reload starts
+        if let ReloadTab::Dashboard(guild_id) = reload_type {
+            self.update_dashboard_matcher(guild_id);
+        }
+
+        let Some((store, view)) = self.store_view(reload_type) else {
+            return;
+        };
+
+        let query = view.start_reload(store);
+        let rows = query
+            .as_ref()
+            .map(|query| store.candidate_rows(query))
+            .unwrap_or_default();
+        self.running_reload = Some(RunningReload {
+            reload_type,
+            query: query.unwrap_or_default(),
+            rows,
+            position: 0,
+        });
+    }
+
+    fn continue_reload(&mut self) {
+        let Some(mut running) = self.running_reload.take() else {
+            return;
+        };
+        let Some((store, view)) = self.store_view(running.reload_type) else {
+            return;
+        };
+
+        if running.position < running.rows.len() {
+            let end = (running.position + RELOAD_CHUNK).min(running.rows.len());
+            for index in &running.rows[running.position..end] {
+                if running.query.matches(store, *index) {
+                    view.add_message(&store.row(*index));
+                }
+            }
+            running.position = end;
+        } else if view.finish_chunk(store, FINISH_CHUNK) {
+            view.finish_reload(store);
+            return;
+        }
+
+        self.running_reload = Some(running);
+    }
+
+    /// The store of the guild of the reload together with the tab that is reloaded
+    fn store_view(
+        &mut self,
+        reload_type: ReloadTab,
+    ) -> Option<(&MessageStore, &mut dyn StoreView)> {
+        let guild_id = reload_type.guild_id();
+        let store = self.message_store.get(&guild_id)?;
+
+        let view: &mut dyn StoreView = match reload_type {
+            ReloadTab::Overview(_) => self.overview.get_mut(&guild_id)?,
+            ReloadTab::UserTable(_) => self.user_table.get_mut(&guild_id)?,
+            ReloadTab::ChannelTable(_) => self.channel_table.get_mut(&guild_id)?,
+            ReloadTab::WordTable(_) => self.word_table.get_mut(&guild_id)?,
+            ReloadTab::MessageChart(_) => self.message_chart.get_mut(&guild_id)?,
+            ReloadTab::UserChart(_) => self.user_chart.get_mut(&guild_id)?,
+            ReloadTab::Heatmap(_) => self.heatmap.get_mut(&guild_id)?,
+            ReloadTab::MessageSearch(_) => self.message_search.get_mut(&guild_id)?,
+            ReloadTab::DeletedMessages(_) => self.deleted_messages.get_mut(&guild_id)?,
+            ReloadTab::Dashboard(_) => self.dashboard.get_mut(&guild_id)?,
+            ReloadTab::Cohorts(_) => self.cohorts.get_mut(&guild_id)?,
+            ReloadTab::Interactions(_) => self.interactions.get_mut(&guild_id)?,
+            ReloadTab::Media(_) => self.media.get_mut(&guild_id)?,
+        };
+        Some((store, view))
+    }
+
+    /// Call `f` with every tab of the guild
+    pub(super) fn for_each_view(&mut self, guild_id: i64, mut f: impl FnMut(&mut dyn StoreView)) {
+        for state in TabState::iter() {
+            if let Some((_, view)) = self.store_view(ReloadTab::new(state, guild_id)) {
+                f(view);
+            }
+        }
+    }
+
+    /// Stop the running reload if it belongs to the guild
+    pub fn cancel_reload(&mut self, guild_id: i64) {
+        if self
+            .running_reload
+            .as_ref()
+            .is_some_and(|running| running.reload_type.guild_id() == guild_id)
+        {
+            self.running_reload = None;
+        }
+    }
+
+    /// Show the progress of the running reload if it belongs to the shown tab. The shown result
+    /// stays until the reload is done
+    pub fn show_reload_progress(&self, ui: &mut Ui, state: TabState) {
+        let Some(running) = &self.running_reload else {
+            return;
+        };
+
+        // Keep drawing frames so the reload continues without any input
+        ui.ctx().request_repaint();
+
+        if running.reload_type.guild_id() != self.current_guild
+            || running.reload_type.tab_state() != state
+        {
+            return;
+        }
+
+        let progress = running.progress();
+        ui.add(
+            ProgressBar::new(progress)
+                .desired_height(12.0)
+                .text(format!("Reloading {state}: {:.0}%", progress * 100.0)),
+        );
+        ui.add_space(5.0);
+    }
+}
@@ -0,0 +1,293 @@
+use chrono::NaiveDateTime;
+use eframe::egui::{Align, Context, Grid, Id, Layout, RichText, ScrollArea, Ui, Window};
+use egui_plot::{Bar, BarChart, Plot};
+use std::cmp::Reverse;
+
+use crate::ui::{DateCounts, TabHandler, show_count_grid, show_date_plot};
+use crate::{AppEvent, EventBus};
+
+/// Messages of the user in a single channel
+struct ProfileChannel {
+    name: String,
+    total_message: u32,
+    deleted_message: u32,
+}
+
+/// Everything that is known about a single user within the selected dates and channels
+pub struct UserProfile {
+    guild_id: i64,
+    user_id: i64,
+    name: String,
+    username: String,
+    total_message: u32,
+    deleted_message: u32,
+    first_seen: Option<NaiveDateTime>,
+    last_seen: Option<NaiveDateTime>,
+    channels: Vec<ProfileChannel>,
+    /// Key: The date. Value: Non-deleted messages sent on that date
+    timeline: DateCounts,
+    hourly_activity: [u32; 24],
+    phrase_size: usize,
+    phrases: Vec<(String, u32)>,
+}
+
+impl UserProfile {
+    #[must_use]
+    pub fn new(guild_id: i64, user_id: i64, name: &str, username: &str) -> Self {
+        Self {
+            guild_id,
+            user_id,
+            name: name.to_string(),
+            username: username.to_string(),
+            total_message: 0,
+            deleted_message: 0,
+            first_seen: None,
+            last_seen: None,
+            channels: Vec::new(),
+            timeline: DateCounts::new(),
+            hourly_activity: [0; 24],
+            phrase_size: 1,
+            phrases: Vec::new(),
+        }
+    }
+
+    #[must_use]
+    pub fn guild_id(&self) -> i64 {
+        self.guild_id
+    }
+
+    #[must_use]
+    pub fn user_id(&self) -> i64 {
+        self.user_id
+    }
+
+    pub fn add_channel(&mut self, name: String, total_message: u32, deleted_message: u32) {
+        self.total_message += total_message;
+        self.deleted_message += deleted_message;
+        self.channels.push(ProfileChannel {
+            name,
+            total_message,
+            deleted_message,
+        });
+        self.channels
+            .sort_by_key(|channel| Reverse(channel.total_message));
+    }
+
+    /// Update the first and last seen time if the given time is outside of them
+    pub fn add_seen(&mut self, first_seen: NaiveDateTime, last_seen: NaiveDateTime) {
+        if self.first_seen.is_none_or(|seen| seen > first_seen) {
+            self.first_seen = Some(first_seen);
+        }
+        if self.last_seen.is_none_or(|seen| seen < last_seen) {
+            self.last_seen = Some(last_seen);
+        }
+    }
+
+    pub fn set_activity(&mut self, timeline: DateCounts, hourly_activity: [u32; 24]) {
+        self.timeline = timeline;
+        self.hourly_activity = hourly_activity;
+    }
+
+    pub fn set_phrases(&mut self, phrase_size: usize, phrases: Vec<(String, u32)>) {
+        self.phrase_size = phrase_size;
+        self.phrases = phrases;
+    }
+
+    /// Percentage of the messages of this user that were deleted
+    fn deleted_ratio(&self) -> f64 {
+        let all_message = self.total_message + self.deleted_message;
+        if all_message == 0 {
+            return 0.0;
+        }
+        f64::from(self.deleted_message) / f64::from(all_message) * 100.0
+    }
+
+    /// Show the profile in a window. `open` is set to false once the window is closed
+    pub fn show_ui(&self, ctx: &Context, open: &mut bool, event_bus: &mut EventBus) {
+        Window::new(format!("{} Profile", self.name))
+            .id(Id::new("user_profile"))
+            .open(open)
+            .default_size([600.0, 700.0])
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.heading(&self.name);
+                    ui.label(format!("@{}", self.username));
+                    ui.separator();
+                    ui.label(format!("User ID: {}", self.user_id));
+                    ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                        if ui
+                            .button("Refresh")
+                            .on_hover_text("Rebuild the profile with the latest data")
+                            .clicked()
+                        {
+                            event_bus
+                                .publish(AppEvent::OpenUserProfile(self.guild_id, self.user_id));
+                        }
+                    });
+                });
+                ui.separator();
+
+                ScrollArea::vertical().show(ui, |ui| {
+                    self.show_summary(ui);
+                    ui.separator();
+
+                    ui.label(RichText::new("Message Timeline").strong());
+                    self.show_timeline(ui);
+                    ui.separator();
+
+                    ui.label(RichText::new("Hour of Day Activity").strong());
+                    self.show_hourly_activity(ui);
+                    ui.separator();
+
+                    ui.label(RichText::new("Channels").strong());
+                    self.show_channels(ui);
+                    ui.separator();
+
+                    ui.label(
+                        RichText::new(format!(
+                            "Most Used Phrases (Phrase Size: {})",
+                            self.phrase_size
+                        ))
+                        .strong(),
+                    );
+                    self.show_phrases(ui);
+                });
+            });
+    }
+
+    fn show_summary(&self, ui: &mut Ui) {
+        let seen_text = |seen: Option<NaiveDateTime>| {
+            seen.map_or_else(|| "-".to_string(), |seen| seen.to_string())
+        };
+
+        Grid::new("user_profile_summary")
+            .num_columns(2)
+            .striped(true)
+            .show(ui, |ui| {
+                ui.label("Total Message");
+                ui.label(self.total_message.to_string());
+                ui.end_row();
+
+                ui.label("Deleted Message");
+                ui.label(self.deleted_message.to_string());
+                ui.end_row();
+
+                ui.label("Deleted Ratio");
+                ui.label(format!("{:.2}%", self.deleted_ratio()));
+                ui.end_row();
+
+                ui.label("First Message Seen");
+                ui.label(seen_text(self.first_seen));
+                ui.end_row();
+
+                ui.label("Last Message Seen");
+                ui.label(seen_text(self.last_seen));
+                ui.end_row();
+            });
+    }
+
+    fn show_timeline(&self, ui: &mut Ui) {
+        show_date_plot(ui, "user_profile_timeline", &[("Messages", &self.timeline)]);
+    }
+
+    fn show_hourly_activity(&self, ui: &mut Ui) {
+        let bars = self
+            .hourly_activity
+            .iter()
+            .enumerate()
+            .map(|(hour, count)| {
+                Bar::new(hour as f64, f64::from(*count)).name(format!("{hour:02}:00"))
+            })
+            .collect();
+
+        Plot::new("user_profile_hourly")
+            .height(200.0)
+            .clamp_grid(true)
+            .show(ui, |plot_ui| {
+                plot_ui.bar_chart(BarChart::new("Messages", bars).width(1.0).name("Messages"));
+            });
+    }
+
+    fn show_channels(&self, ui: &mut Ui) {
+        if self.channels.is_empty() {
+            ui.label("No messages found in the selected channels");
+            return;
+        }
+
+        Grid::new("user_profile_channels")
+            .num_columns(3)
+            .striped(true)
+            .show(ui, |ui| {
+                ui.label(RichText::new("Channel").strong());
+                ui.label(RichText::new("Total Message").strong());
+                ui.label(RichText::new("Deleted Message").strong());
+                ui.end_row();
+
+                for channel in &self.channels {
+                    ui.label(&channel.name);
+                    ui.label(channel.total_message.to_string());
+                    ui.label(channel.deleted_message.to_string());
+                    ui.end_row();
+                }
+            });
+    }
+
+    fn show_phrases(&self, ui: &mut Ui) {
+        show_count_grid(
+            ui,
+            "user_profile_phrases",
+            ["Phrase", "Hits"],
+            &self.phrases,
+            "No phrases found",
+        );
+    }
+}
+
+impl TabHandler {
+    /// Build the profile of the user from the messages of the guild and show it
+    pub fn open_user_profile(&mut self, guild_id: i64, user_id: i64) {
+        let Some(store) = self.message_store.get(&guild_id) else {
+            return;
+        };
+        let Some(mut profile) = self
+            .user_table
+            .get(&guild_id)
+            .and_then(|table| table.user_profile(store, guild_id, user_id))
+        else {
+            return;
+        };
+
+        if let Some(table) = self.word_table.get(&guild_id) {
+            let (phrase_size, phrases) = table.user_phrases(store, user_id);
+            profile.set_phrases(phrase_size, phrases);
+        }
+
+        self.user_profile = Some(profile);
+    }
+
+    /// Build the open profile again so it follows the selected dates and channels
+    pub fn reload_user_profile(&mut self) {
+        if let Some(profile) = &self.user_profile {
+            let guild_id = profile.guild_id();
+            let user_id = profile.user_id();
+            self.open_user_profile(guild_id, user_id);
+        }
+    }
+
+    pub fn show_user_profile(&mut self, ctx: &Context, event_bus: &mut EventBus) {
+        let Some(profile) = &self.user_profile else {
+            return;
+        };
+
+        if profile.guild_id() != self.current_guild {
+            return;
+        }
+
+        let mut open = true;
+        profile.show_ui(ctx, &mut open, event_bus);
+
+        if !open {
+            self.user_profile = None;
+        }
+    }
+}
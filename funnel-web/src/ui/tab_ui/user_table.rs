@@ -1,21 +1,22 @@
-use chrono::{DateTime, Local, NaiveDate, NaiveDateTime};
+use chrono::{NaiveDateTime, Timelike};
 use eframe::egui::ahash::{HashMap, HashMapExt, HashSet, HashSetExt};
 use eframe::egui::{Align, Button, Layout, Response, RichText, Ui};
 use egui_extras::Column;
 use egui_selectable_table::{
     ColumnOperations, ColumnOrdering, SelectableRow, SelectableTable, SortOrder,
 };
-use funnel_shared::{Channel, MessageWithUser, PAGE_VALUE};
+use funnel_shared::Channel;
 use std::cmp::Ordering;
 use strum::IntoEnumIterator;
 
-use crate::core::UserColumn;
-use crate::ui::{DateHandler, ShowUI, TabHandler};
-use crate::{AppEvent, EventBus};
+use crate::core::{MessageQuery, MessageRow, MessageStore, UserColumn, selected_channel_ids};
+use crate::ui::{DateCounts, DateHandler, ShowUI, StoreView, TabHandler, UserProfile};
+use crate::{AppEvent, EventBus, ExportData};
 
 #[derive(Default)]
 pub struct Config {
     copy_selected: bool,
+    view_profile: Option<i64>,
 }
 
 impl ColumnOperations<UserRowData, UserColumn, Config> for UserColumn {
@@ -33,6 +34,8 @@ impl ColumnOperations<UserRowData, UserColumn, Config> for UserColumn {
             UserColumn::FirstMessageSeen => row.first_seen.to_string(),
             UserColumn::LastMessageSeen => row.last_seen.to_string(),
             UserColumn::UniqueChannels => row.unique_channels.len().to_string(),
+            UserColumn::LeftGuild => left_text(row.left).to_string(),
+            UserColumn::Attachments => row.attachments.to_string(),
         }
     }
     fn create_header(
@@ -82,6 +85,13 @@ impl ColumnOperations<UserRowData, UserColumn, Config> for UserColumn {
                 "The number of unique channels this user was seen in. Click to sort by unique channels"
                     .to_string()
             }
+            UserColumn::LeftGuild => {
+                "Whether the last join or leave of the user was a leave. Click to sort by left guild"
+                    .to_string()
+            }
+            UserColumn::Attachments => {
+                "Total files attached to the messages. Click to sort by attachments".to_string()
+            }
         };
 
         let is_selected = if let Some(direction) = sort_order {
@@ -133,6 +143,8 @@ impl ColumnOperations<UserRowData, UserColumn, Config> for UserColumn {
             UserColumn::FirstMessageSeen => row_data.first_seen.to_string(),
             UserColumn::LastMessageSeen => row_data.last_seen.to_string(),
             UserColumn::UniqueChannels => row_data.unique_channels.len().to_string(),
+            UserColumn::LeftGuild => left_text(row_data.left).to_string(),
+            UserColumn::Attachments => row_data.attachments.to_string(),
         };
         let is_selected = column_selected;
 
@@ -144,11 +156,19 @@ impl ColumnOperations<UserRowData, UserColumn, Config> for UserColumn {
         if show_tooltip {
             label = label.on_hover_text(row_text);
         }
+        if label.double_clicked() {
+            table.config.view_profile = Some(row_data.id);
+        }
+
         label.context_menu(|ui| {
             if ui.button("Copy selected rows").clicked() {
                 table.config.copy_selected = true;
                 ui.close();
             }
+            if ui.button("View profile").clicked() {
+                table.config.view_profile = Some(row_data.id);
+                ui.close();
+            }
         });
         label
     }
@@ -172,6 +192,8 @@ impl ColumnOrdering<UserRowData> for UserColumn {
                 .unique_channels
                 .len()
                 .cmp(&row_2.unique_channels.len()),
+            UserColumn::LeftGuild => row_1.left.cmp(&row_2.left),
+            UserColumn::Attachments => row_1.attachments.cmp(&row_2.attachments),
         }
     }
 }
@@ -190,6 +212,13 @@ struct UserRowData {
     first_seen: NaiveDateTime,
     last_seen: NaiveDateTime,
     unique_channels: HashSet<i64>,
+    attachments: u32,
+    /// Whether the user is no longer in the guild
+    left: bool,
+}
+
+fn left_text(left: bool) -> &'static str {
+    if left { "Yes" } else { "No" }
 }
 
 impl UserRowData {
@@ -209,69 +238,57 @@ impl UserRowData {
             first_seen: date,
             last_seen: date,
             unique_channels: HashSet::new(),
+            attachments: 0,
+            left: false,
         }
     }
 
-    fn add_channel(&mut self, channel_id: i64) {
-        self.unique_channels.insert(channel_id);
-    }
+    /// Add a message of the user to the row
+    fn add_message(&mut self, message: &MessageRow) {
+        let time = message.time();
 
-    fn extend_channels(&mut self, list: &HashSet<i64>) {
-        self.unique_channels.extend(list);
-    }
-
-    /// Increment total message count by 1
-    fn increment_total_message(&mut self) {
-        self.total_message += 1;
-    }
-
-    /// Increment deleted message count by 1
-    fn increment_deleted_message(&mut self) {
-        self.deleted_message += 1;
-    }
-
-    fn increase_deleted_by(&mut self, amount: u32) {
-        self.deleted_message += amount;
-    }
-
-    /// Increment total message count by `amount`
-    fn increase_message_by(&mut self, amount: u32) {
-        self.total_message += amount;
-    }
+        if self.first_seen > time {
+            self.first_seen = time;
+        }
 
-    /// Increment total word count by `word_num`
-    fn increment_total_word(&mut self, word_num: u32) {
-        self.total_word += word_num;
-        self.average_word = self.total_word / self.total_message;
-    }
+        if self.last_seen < time {
+            self.last_seen = time;
+        }
 
-    /// Increment total char count by `char_num`
-    fn increment_total_char(&mut self, char_num: u32) {
-        self.total_char += char_num;
-        self.average_char = self.total_char / self.total_message;
-    }
+        if message.is_deleted() {
+            self.deleted_message += 1;
+        } else {
+            let message_text = message.content().unwrap_or_default();
+
+            self.total_message += 1;
+            self.total_word += message_text.split_whitespace().count() as u32;
+            self.total_char += message_text.len() as u32;
+            self.average_word = self.total_word / self.total_message;
+            self.average_char = self.total_char / self.total_message;
+            self.attachments += message.attachment_count();
+        }
 
-    /// Update the date this user was first seen in the chat
-    fn set_first_seen(&mut self, date: NaiveDateTime) {
-        self.first_seen = date;
+        self.unique_channels.insert(message.channel_id());
     }
+}
 
-    /// Update the date this user was last seen in the chat
-    fn set_last_seen(&mut self, date: NaiveDateTime) {
-        self.last_seen = date;
-    }
+/// The rows of a reload that is still running
+#[derive(Default)]
+struct UserTableReload {
+    rows: HashMap<i64, UserRowData>,
+    total_message: u32,
+    deleted_message: u32,
 }
 
 pub struct UserTable {
-    /// Key: The Date where at least one message/User was found
-    /// Contains all data points and UI points are recreated from here
-    user_data: HashMap<NaiveDate, HashMap<i64, HashMap<i64, UserRowData>>>,
     table: SelectableTable<UserRowData, UserColumn, Config>,
+    /// The rows that are shown in the table
+    rows: Vec<UserRowData>,
     /// Read only currently selected dates in the UI
     date_handler: DateHandler,
     total_message: u32,
     deleted_message: u32,
-    reload_count: u64,
+    reload: UserTableReload,
     channels: Vec<Channel>,
     selected_channels: HashSet<usize>,
 }
@@ -283,12 +300,12 @@ impl Default for UserTable {
             .horizontal_scroll()
             .serial_column();
         Self {
-            user_data: HashMap::new(),
             table,
+            rows: Vec::new(),
             date_handler: DateHandler::default(),
             total_message: 0,
             deleted_message: 0,
-            reload_count: 0,
+            reload: UserTableReload::default(),
             channels: Vec::default(),
             selected_channels: HashSet::default(),
         }
@@ -296,7 +313,7 @@ impl Default for UserTable {
 }
 
 impl ShowUI for UserTable {
-    fn show_ui(&mut self, ui: &mut Ui, _guild_id: i64, event_bus: &mut EventBus) {
+    fn show_ui(&mut self, ui: &mut Ui, guild_id: i64, event_bus: &mut EventBus) {
         let to_copy = self.table.config.copy_selected;
         if to_copy {
             self.table.config.copy_selected = false;
@@ -304,6 +321,10 @@ impl ShowUI for UserTable {
             event_bus.publish(AppEvent::CellsCopied);
         }
 
+        if let Some(user_id) = self.table.config.view_profile.take() {
+            event_bus.publish(AppEvent::OpenUserProfile(guild_id, user_id));
+        }
+
         let mut clip_added = 0;
 
         ui.horizontal(|ui| {
@@ -338,191 +359,135 @@ impl ShowUI for UserTable {
     }
 }
 
-impl UserTable {
-    fn handle_message(&mut self, message: &MessageWithUser, event_bus: &mut EventBus) {
-        self.reload_count += 1;
-        let username = &message.sender.username;
-        let global_name = if let Some(name) = &message.sender.global_name {
-            name
-        } else {
-            username
-        };
-        let user_id = message.sender.user_id;
-        let guild_id = message.message.guild_id;
-        let channel_id = message.message.channel_id;
-
-        let mut deleted_message = false;
-
-        let timestamp = if let Some(time) = message.message.delete_timestamp {
-            deleted_message = true;
-            time
-        } else {
-            message.message.message_timestamp
-        };
-
-        let datetime = DateTime::from_timestamp(timestamp, 0).unwrap();
-        let local_time = datetime.with_timezone(&Local).naive_local();
-        let local_date = local_time.date();
-
-        let user_row = UserRowData::new(global_name, username, user_id, local_time);
-
-        let channel_entry = self.user_data.entry(local_date).or_default();
-        let entry = channel_entry.entry(channel_id).or_default();
-        entry.entry(user_id).or_insert(user_row);
+impl StoreView for UserTable {
+    fn start_reload(&mut self, _store: &MessageStore) -> Option<MessageQuery> {
+        self.reload = UserTableReload::default();
 
-        let target_data = self.user_data.get_mut(&local_date).unwrap();
-        let user_row_data = target_data
-            .get_mut(&channel_id)
-            .unwrap()
-            .get_mut(&user_id)
-            .unwrap();
-
-        // Update last and first seen in this date for this user
-        if user_row_data.first_seen > local_time {
-            user_row_data.set_first_seen(local_time);
-        }
-
-        if user_row_data.last_seen < local_time {
-            user_row_data.set_last_seen(local_time);
-        }
-
-        // User table has a copy of the handler only. Modifying here doesn't impact the UI. Check
-        // here if update is necessary in the main UI, if yes, send an event for processing
-        let needs_update = self.date_handler.update_dates(local_date);
-        if needs_update {
-            event_bus.publish(AppEvent::UpdateDate(local_date, guild_id));
-        }
+        let selected_channels = selected_channel_ids(&self.channels, &self.selected_channels);
+        let query = MessageQuery::new()
+            .dates(self.date_handler.from, self.date_handler.to)
+            .channels(selected_channels);
+        Some(query)
+    }
 
-        if deleted_message {
-            user_row_data.increment_deleted_message();
+    fn add_message(&mut self, message: &MessageRow) {
+        if message.is_deleted() {
+            self.reload.deleted_message += 1;
         } else {
-            let message_text = message.message.message_content.clone().unwrap_or_default();
-
-            let total_char = message_text.len() as u32;
-            let total_word = message_text.split_whitespace().count() as u32;
-
-            user_row_data.increment_total_message();
-            user_row_data.increment_total_word(total_word);
-            user_row_data.increment_total_char(total_char);
+            self.reload.total_message += 1;
         }
 
-        user_row_data.add_channel(channel_id);
-
-        if self.reload_count == PAGE_VALUE * 5 {
-            event_bus.publish_if_needed(AppEvent::UserTableNeedsReload(guild_id));
-        }
-    }
-
-    fn get_total_user(&self) -> usize {
-        self.table.total_rows()
+        let user = message.user();
+        self.reload
+            .rows
+            .entry(message.user_id())
+            .or_insert_with(|| {
+                UserRowData::new(
+                    &user.name,
+                    &user.username,
+                    message.user_id(),
+                    message.time(),
+                )
+            })
+            .add_message(message);
     }
 
     /// Create the rows that will be shown in the UI.
-    fn create_rows(&mut self) {
-        self.reload_count = 0;
+    fn finish_reload(&mut self, store: &MessageStore) {
+        let reload = std::mem::take(&mut self.reload);
         self.table.clear_all_rows();
-        let mut total_message = 0;
-        let mut deleted_message = 0;
-        let mut id_map = HashMap::new();
-
-        let mut selected_channels = HashSet::default();
 
-        if self.selected_channels.is_empty() {
-            for channel in &self.channels {
-                selected_channels.insert(channel.channel_id);
-            }
-        } else {
-            for index in &self.selected_channels {
-                if index == &0_usize {
-                    for channel in &self.channels {
-                        selected_channels.insert(channel.channel_id);
-                    }
-                    break;
-                } else {
-                    let channel_id = self.channels.get(*index - 1).unwrap().channel_id;
-                    selected_channels.insert(channel_id);
-                }
-            }
+        self.rows = reload.rows.into_values().collect();
+        for row in &mut self.rows {
+            row.left = store.has_left(row.id);
+            self.table.add_modify_row(|_| Some(row.clone()));
         }
 
-        // Go by all the data that are within the range and join them together
-        for (date, data) in &self.user_data {
-            if !self.date_handler.within_range(*date) {
-                continue;
-            }
-
-            for (channel_id, row_data) in data {
-                if !selected_channels.contains(channel_id) {
-                    continue;
-                }
-                for (id, row) in row_data {
-                    total_message += row.total_message;
-                    deleted_message += row.deleted_message;
-
-                    if let Some(row_id) = id_map.get(id) {
-                        self.table.add_modify_row(|rows| {
-                            let target_row = rows.get_mut(row_id).unwrap();
-                            let user_row_data = &mut target_row.row_data;
-
-                            if user_row_data.first_seen > row.first_seen {
-                                user_row_data.set_first_seen(row.first_seen);
-                            }
-
-                            if user_row_data.last_seen < row.last_seen {
-                                user_row_data.set_last_seen(row.last_seen);
-                            }
-
-                            let total_char = row.total_char;
-                            let total_word = row.total_word;
-                            let total_message = row.total_message;
-                            let deleted_message = row.deleted_message;
-                            let channel_list = &row.unique_channels;
-
-                            user_row_data.increase_message_by(total_message);
-                            user_row_data.increase_deleted_by(deleted_message);
-                            user_row_data.increment_total_word(total_word);
-                            user_row_data.increment_total_char(total_char);
-                            user_row_data.extend_channels(channel_list);
-                            None
-                        });
-                    } else {
-                        let new_id = self.table.add_modify_row(|_| Some(row.clone()));
-                        id_map.insert(row.id, new_id.unwrap());
-                    }
-                }
-            }
-        }
-        self.total_message = total_message;
-        self.deleted_message = deleted_message;
+        self.total_message = reload.total_message;
+        self.deleted_message = reload.deleted_message;
         self.table.recreate_rows();
     }
 
-    pub fn set_date_handler(&mut self, handler: DateHandler) {
+    fn set_date_handler(&mut self, handler: DateHandler) {
         self.date_handler = handler;
     }
 
-    pub fn set_channels(&mut self, channels: Vec<Channel>) {
+    fn set_channels(&mut self, channels: Vec<Channel>) {
         self.channels = channels;
     }
-    pub fn set_selected_channels(&mut self, selected: HashSet<usize>) {
+
+    fn set_selected_channels(&mut self, selected: HashSet<usize>) {
         self.selected_channels = selected;
     }
 }
 
-impl TabHandler {
-    pub fn user_table_recreate_rows(&mut self, guild_id: i64) {
-        self.user_table.get_mut(&guild_id).unwrap().create_rows();
+impl UserTable {
+    fn get_total_user(&self) -> usize {
+        self.table.total_rows()
     }
 
-    pub fn handle_message_user_table(
-        &mut self,
-        message: &MessageWithUser,
-        event_bus: &mut EventBus,
-    ) {
-        let guild_id = message.message.guild_id;
-        self.user_table
-            .get_mut(&guild_id)
-            .unwrap()
-            .handle_message(message, event_bus);
+    /// Profile of the user within the selected dates and channels. None if the user was never seen
+    pub fn user_profile(
+        &self,
+        store: &MessageStore,
+        guild_id: i64,
+        user_id: i64,
+    ) -> Option<UserProfile> {
+        let user = store.user(user_id)?;
+        let mut profile = UserProfile::new(guild_id, user_id, &user.name, &user.username);
+
+        let selected_channels = selected_channel_ids(&self.channels, &self.selected_channels);
+        let query = MessageQuery::new()
+            .dates(self.date_handler.from, self.date_handler.to)
+            .channels(selected_channels)
+            .user(user_id);
+
+        let mut channel_data: HashMap<i64, (u32, u32)> = HashMap::new();
+        let mut timeline: DateCounts = self
+            .date_handler
+            .range_dates()
+            .map(|date| (date, 0))
+            .collect();
+        let mut hourly_activity = [0; 24];
+
+        for message in store.query(query) {
+            profile.add_seen(message.sent_time(), message.sent_time());
+            let entry = channel_data.entry(message.channel_id()).or_default();
+
+            if message.is_deleted() {
+                entry.1 += 1;
+            } else {
+                entry.0 += 1;
+                *timeline.entry(message.time().date()).or_default() += 1;
+                hourly_activity[message.time().hour() as usize] += 1;
+            }
+        }
+        profile.set_activity(timeline, hourly_activity);
+
+        for (channel_id, (total_message, deleted_message)) in channel_data {
+            let name = self
+                .channels
+                .iter()
+                .find(|channel| channel.channel_id == channel_id)
+                .map_or_else(
+                    || channel_id.to_string(),
+                    |channel| channel.channel_name.clone(),
+                );
+            profile.add_channel(name, total_message, deleted_message);
+        }
+        Some(profile)
+    }
+
+    /// The rows that are currently shown in the table
+    fn export_data(&self) -> ExportData {
+        ExportData::from_rows("User Table", &self.rows, |column: &UserColumn, row| {
+            column.column_text(row)
+        })
+    }
+}
+
+impl TabHandler {
+    pub fn user_table_export_data(&self, guild_id: i64) -> ExportData {
+        self.user_table.get(&guild_id).unwrap().export_data()
     }
 }
@@ -0,0 +1,227 @@
+use eframe::egui::ahash::{HashMap, HashSet};
+use eframe::egui::{CollapsingHeader, Grid, Slider, TextEdit, Ui};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+
+use crate::core::MessageRow;
+use crate::get_stripped_windows;
+
+/// Which words and phrases of the messages are counted in Common Words
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct WordFilter {
+    /// Words that are left out before the phrases are built
+    pub stop_words: Vec<String>,
+    /// Only the phrases that contain one of these are counted. Every phrase if empty
+    pub include_phrases: Vec<String>,
+    /// Phrases that contain one of these are not counted
+    pub ignore_phrases: Vec<String>,
+    /// Words with fewer characters are left out
+    pub min_word_length: usize,
+    /// Count words the same regardless of their case
+    pub case_folding: bool,
+    /// Use the message content instead of the content that was stripped by the server
+    pub raw_content: bool,
+}
+
+impl Default for WordFilter {
+    fn default() -> Self {
+        Self {
+            stop_words: Vec::new(),
+            include_phrases: Vec::new(),
+            ignore_phrases: Vec::new(),
+            min_word_length: 1,
+            case_folding: false,
+            raw_content: false,
+        }
+    }
+}
+
+impl WordFilter {
+    /// The filter in a form that can be checked against every message of a reload
+    #[must_use]
+    pub fn matcher(&self) -> PhraseMatcher {
+        let fold = |list: &[String]| -> Vec<String> {
+            list.iter()
+                .map(|value| fold_case(value, self.case_folding).into_owned())
+                .collect()
+        };
+
+        PhraseMatcher {
+            stop_words: fold(&self.stop_words).into_iter().collect(),
+            include_phrases: fold(&self.include_phrases),
+            ignore_phrases: fold(&self.ignore_phrases),
+            min_word_length: self.min_word_length,
+            case_folding: self.case_folding,
+            raw_content: self.raw_content,
+        }
+    }
+}
+
+/// A word filter that is ready to be applied to messages. The default counts every phrase of the
+/// stripped content
+#[derive(Default)]
+pub struct PhraseMatcher {
+    stop_words: HashSet<String>,
+    include_phrases: Vec<String>,
+    ignore_phrases: Vec<String>,
+    min_word_length: usize,
+    case_folding: bool,
+    raw_content: bool,
+}
+
+impl PhraseMatcher {
+    fn keep_word(&self, word: &str) -> bool {
+        let word = word.trim_end_matches(['.', ',', '?', '!']);
+        word.chars().count() >= self.min_word_length && !self.stop_words.contains(word)
+    }
+
+    fn keep_phrase(&self, phrase: &str) -> bool {
+        let included = self.include_phrases.is_empty()
+            || self
+                .include_phrases
+                .iter()
+                .any(|target| phrase.contains(target.as_str()));
+        included
+            && !self
+                .ignore_phrases
+                .iter()
+                .any(|target| phrase.contains(target.as_str()))
+    }
+}
+
+fn fold_case(text: &str, case_folding: bool) -> Cow<'_, str> {
+    if case_folding {
+        Cow::Owned(text.to_lowercase())
+    } else {
+        Cow::Borrowed(text)
+    }
+}
+
+/// Count every phrase of the message with the given phrase size that passes the filter
+pub fn add_phrases(
+    phrases: &mut HashMap<String, u32>,
+    message: &MessageRow,
+    window_size: usize,
+    matcher: &PhraseMatcher,
+) {
+    let content = if matcher.raw_content {
+        message.content()
+    } else {
+        message.stripped_content()
+    };
+    let Some(content) = content else {
+        return;
+    };
+
+    let content = fold_case(content, matcher.case_folding);
+    let words: Vec<&str> = content
+        .split_whitespace()
+        .filter(|word| matcher.keep_word(word))
+        .collect();
+    if words.len() < window_size {
+        return;
+    }
+
+    for phrase in get_stripped_windows(words, window_size) {
+        if matcher.keep_phrase(&phrase) {
+            *phrases.entry(phrase).or_default() += 1;
+        }
+    }
+}
+
+/// The comma separated lists of the filter that are being edited
+#[derive(Default)]
+pub struct WordFilterInput {
+    stop_words: String,
+    include_phrases: String,
+    ignore_phrases: String,
+}
+
+impl WordFilterInput {
+    #[must_use]
+    pub fn new(filter: &WordFilter) -> Self {
+        Self {
+            stop_words: filter.stop_words.join(", "),
+            include_phrases: filter.include_phrases.join(", "),
+            ignore_phrases: filter.ignore_phrases.join(", "),
+        }
+    }
+
+    fn split(list: &str) -> Vec<String> {
+        list.split(',')
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    /// Show the filter settings. Returns true if the filter was changed
+    pub fn show_ui(&mut self, ui: &mut Ui, filter: &mut WordFilter) -> bool {
+        let mut changed = false;
+
+        CollapsingHeader::new("Filters").show(ui, |ui| {
+            ui.horizontal(|ui| {
+                changed |= ui
+                    .checkbox(&mut filter.raw_content, "Raw Content")
+                    .on_hover_text(
+                        "Analyze the message as it was sent instead of the content that was stripped of common words",
+                    )
+                    .changed();
+                changed |= ui
+                    .checkbox(&mut filter.case_folding, "Ignore Case")
+                    .on_hover_text("Count words the same regardless of their case")
+                    .changed();
+                ui.separator();
+                ui.label("Minimum Word Length:");
+                changed |= ui
+                    .add(Slider::new(&mut filter.min_word_length, 1..=20))
+                    .changed();
+            });
+
+            Grid::new("word_filter_grid")
+                .num_columns(2)
+                .show(ui, |ui| {
+                    ui.label("Stop Words:");
+                    ui.add(
+                        TextEdit::singleline(&mut self.stop_words)
+                            .hint_text("Words that are left out, separated by commas")
+                            .desired_width(400.0),
+                    );
+                    ui.end_row();
+
+                    ui.label("Include Phrases:");
+                    ui.add(
+                        TextEdit::singleline(&mut self.include_phrases)
+                            .hint_text("Only count phrases that contain one of these")
+                            .desired_width(400.0),
+                    );
+                    ui.end_row();
+
+                    ui.label("Ignore Phrases:");
+                    ui.add(
+                        TextEdit::singleline(&mut self.ignore_phrases)
+                            .hint_text("Skip phrases that contain one of these")
+                            .desired_width(400.0),
+                    );
+                    ui.end_row();
+                });
+
+            ui.horizontal(|ui| {
+                if ui.button("Apply").clicked() {
+                    filter.stop_words = Self::split(&self.stop_words);
+                    filter.include_phrases = Self::split(&self.include_phrases);
+                    filter.ignore_phrases = Self::split(&self.ignore_phrases);
+                    changed = true;
+                }
+                if ui.button("Reset").clicked() {
+                    *filter = WordFilter::default();
+                    *self = Self::new(filter);
+                    changed = true;
+                }
+            });
+        });
+
+        changed
+    }
+}